@@ -3,7 +3,8 @@ use std::collections::BTreeMap;
 use std::hint::black_box;
 use std::time::Instant;
 
-fn bench_bplustree_get(n: usize, cap: usize) -> (f64, f64) {
+/// Build, get, get_batch (shuffled keys) and get_many_sorted (sorted keys) times.
+fn bench_bplustree_get(n: usize, cap: usize) -> (f64, f64, f64, f64) {
     // Build the tree
     let mut map = BPlusTreeMap::new(cap).expect("new");
     let mut state: u64 = 0x123456789abcdef0;
@@ -35,8 +36,17 @@ fn bench_bplustree_get(n: usize, cap: usize) -> (f64, f64) {
     }
     let get_time = get_start.elapsed().as_secs_f64();
 
+    let batch_start = Instant::now();
+    black_box(map.get_batch(&keys));
+    let batch_time = batch_start.elapsed().as_secs_f64();
+
+    keys.sort_unstable();
+    let sorted_start = Instant::now();
+    black_box(map.get_many_sorted(&keys));
+    let sorted_time = sorted_start.elapsed().as_secs_f64();
+
     black_box(map);
-    (build_time, get_time)
+    (build_time, get_time, batch_time, sorted_time)
 }
 
 fn bench_std_btree_get(n: usize) -> (f64, f64) {
@@ -90,7 +100,7 @@ fn main() {
         let _ = bench_std_btree_get(1000);
 
         // BPlusTreeMap
-        let (build_bp, get_bp) = bench_bplustree_get(n, cap);
+        let (build_bp, get_bp, batch_bp, sorted_bp) = bench_bplustree_get(n, cap);
         println!("  BPlusTreeMap (cap={}):", cap);
         println!(
             "    Build: {:.3}s ({:.0} ops/sec)",
@@ -102,6 +112,16 @@ fn main() {
             get_bp,
             n as f64 / get_bp
         );
+        println!(
+            "    Batch: {:.3}s ({:.0} ops/sec, get_batch)",
+            batch_bp,
+            n as f64 / batch_bp
+        );
+        println!(
+            "    Sorted: {:.3}s ({:.0} ops/sec, get_many_sorted)",
+            sorted_bp,
+            n as f64 / sorted_bp
+        );
 
         // std::BTreeMap
         let (build_std, get_std) = bench_std_btree_get(n);
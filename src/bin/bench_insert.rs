@@ -29,10 +29,12 @@ fn main() {
     println!("\n=== Complete Performance Benchmark ===");
     println!("items: {}  |  bplustree capacity: {}", n, cap);
     println!(
-        "{:<18} {:>10} {:>12} {:>10} {:>12} {:>10} {:>12} {:>10} {:>12} {:>10} {:>12}",
+        "{:<18} {:>10} {:>12} {:>10} {:>12} {:>10} {:>12} {:>10} {:>12} {:>10} {:>12} {:>10} {:>12}",
         "target",
         "ins(s)",
         "ins Mops",
+        "seq(s)",
+        "seq Mops",
         "get(s)",
         "get Mops",
         "del(s)",
@@ -44,10 +46,12 @@ fn main() {
     );
     for result in [current, /* previous, */ std_map] {
         println!(
-            "{:<18} {:>10.3} {:>12.2} {:>10.3} {:>12.2} {:>10.3} {:>12.2} {:>10.3} {:>12.2} {:>10.3} {:>12.2}",
+            "{:<18} {:>10.3} {:>12.2} {:>10.3} {:>12.2} {:>10.3} {:>12.2} {:>10.3} {:>12.2} {:>10.3} {:>12.2} {:>10.3} {:>12.2}",
             result.label,
             result.insert.as_secs_f64(),
             throughput(n, result.insert),
+            result.sequential.as_secs_f64(),
+            throughput(n, result.sequential),
             result.get.as_secs_f64(),
             throughput(n, result.get),
             result.delete.as_secs_f64(),
@@ -63,6 +67,8 @@ fn main() {
 struct BenchResult {
     label: &'static str,
     insert: Duration,
+    /// Inserting keys in ascending order, as timestamps or sequence ids.
+    sequential: Duration,
     get: Duration,
     delete: Duration,
     mixed: Duration,
@@ -75,6 +81,9 @@ fn bench_current(dataset: &[(u64, u64)], lookups: &[u64], cap: usize) -> BenchRe
     let get = time_get(|k| map.get(k), lookups);
     let iterate = time_iterate(&map);
 
+    let mut map_for_sequential = BPlusTreeMap::new(cap).expect("current new for sequential");
+    let sequential = time_insert(&mut map_for_sequential, &sorted(dataset));
+
     // For delete benchmark, create a fresh map
     let mut map_for_delete = BPlusTreeMap::new(cap).expect("current new for delete");
     for &(k, v) in dataset {
@@ -89,6 +98,7 @@ fn bench_current(dataset: &[(u64, u64)], lookups: &[u64], cap: usize) -> BenchRe
     BenchResult {
         label: "bplustree-current",
         insert,
+        sequential,
         get,
         delete,
         mixed,
@@ -113,6 +123,9 @@ fn bench_std(dataset: &[(u64, u64)], lookups: &[u64]) -> BenchResult {
     let get = time_get(|k| map.get(k), lookups);
     let iterate = time_iterate(&map);
 
+    let mut map_for_sequential = BTreeMap::new();
+    let sequential = time_insert(&mut map_for_sequential, &sorted(dataset));
+
     // For delete benchmark, create a fresh map
     let mut map_for_delete = BTreeMap::new();
     for &(k, v) in dataset {
@@ -127,6 +140,7 @@ fn bench_std(dataset: &[(u64, u64)], lookups: &[u64]) -> BenchResult {
     BenchResult {
         label: "std::BTreeMap",
         insert,
+        sequential,
         get,
         delete,
         mixed,
@@ -144,6 +158,12 @@ fn generate_dataset(n: usize) -> Vec<(u64, u64)> {
         .collect()
 }
 
+fn sorted(dataset: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut out = dataset.to_vec();
+    out.sort_unstable();
+    out
+}
+
 fn time_insert<M>(map: &mut M, dataset: &[(u64, u64)]) -> Duration
 where
     M: InsertBenchmark,
@@ -3,9 +3,10 @@ use std::collections::BTreeMap;
 use std::hint::black_box;
 use std::time::Instant;
 
-fn bench_bplustree_iterate(n: usize, cap: usize) -> (f64, f64, f64) {
+fn bench_bplustree_iterate(n: usize, cap: usize, prefetch: usize) -> (f64, f64, f64) {
     // Build the tree
     let mut map = BPlusTreeMap::new(cap).expect("new");
+    map.set_prefetch_distance(prefetch);
     let mut state: u64 = 0x123456789abcdef0;
 
     let build_start = Instant::now();
@@ -84,6 +85,7 @@ fn bench_std_btree_iterate(n: usize) -> (f64, f64, f64) {
 fn main() {
     let sizes = vec![10_000, 100_000, 1_000_000];
     let cap = 128;
+    let prefetch = 4;
 
     println!("Iteration Performance Benchmark");
     println!("================================\n");
@@ -92,11 +94,11 @@ fn main() {
         println!("Testing with {} items:", n);
 
         // Warmup
-        let _ = bench_bplustree_iterate(1000, cap);
+        let _ = bench_bplustree_iterate(1000, cap, 0);
         let _ = bench_std_btree_iterate(1000);
 
         // BPlusTreeMap
-        let (build_bp, forward_bp, backward_bp) = bench_bplustree_iterate(n, cap);
+        let (build_bp, forward_bp, backward_bp) = bench_bplustree_iterate(n, cap, 0);
         println!("  BPlusTreeMap (cap={}):", cap);
         println!(
             "    Build:    {:.3}s ({:.0} ops/sec)",
@@ -114,6 +116,22 @@ fn main() {
             n as f64 / backward_bp
         );
 
+        // BPlusTreeMap with leaf prefetching along the sibling chain
+        let (_, forward_pf, backward_pf) = bench_bplustree_iterate(n, cap, prefetch);
+        println!("  BPlusTreeMap (cap={}, prefetch={}):", cap, prefetch);
+        println!(
+            "    Forward:  {:.3}s ({:.0} ops/sec, {:.2}x without prefetch)",
+            forward_pf,
+            n as f64 / forward_pf,
+            forward_bp / forward_pf
+        );
+        println!(
+            "    Backward: {:.3}s ({:.0} ops/sec, {:.2}x without prefetch)",
+            backward_pf,
+            n as f64 / backward_pf,
+            backward_bp / backward_pf
+        );
+
         // std::BTreeMap
         let (build_std, forward_std, backward_std) = bench_std_btree_iterate(n);
         println!("  std::BTreeMap:");
@@ -3,11 +3,15 @@ use std::collections::BTreeMap;
 use std::hint::black_box;
 use std::time::Instant;
 
-fn bench_bplus_range(n: usize, range_size: usize, iterations: usize) -> f64 {
+/// Leaves prefetched ahead in the prefetching column.
+const PREFETCH_DISTANCE: usize = 4;
+
+fn bench_bplus_range(n: usize, range_size: usize, iterations: usize, prefetch: usize) -> f64 {
     let mut map = BPlusTreeMap::with_cache_lines(2, 2);
     for i in 0..n {
         map.insert(i, i * 2);
     }
+    map.set_prefetch_distance(prefetch);
 
     let start = n / 2 - range_size / 2;
     let end = start + range_size;
@@ -48,10 +52,14 @@ fn main() {
 
     println!("Range Benchmark (n={})", n);
     println!(
-        "{:<20} {:<15} {:<15} {:<10}",
-        "Range Size", "BPlusTree", "std::BTree", "Speedup"
+        "{:<20} {:<15} {:<15} {:<15} {:<10}",
+        "Range Size",
+        "BPlusTree",
+        format!("prefetch={}", PREFETCH_DISTANCE),
+        "std::BTree",
+        "Speedup"
     );
-    println!("{}", "=".repeat(65));
+    println!("{}", "=".repeat(80));
 
     let configs = vec![
         (100, 10_000),
@@ -62,14 +70,16 @@ fn main() {
     ];
 
     for (range_size, iterations) in configs {
-        let bplus_time = bench_bplus_range(n, range_size, iterations);
+        let bplus_time = bench_bplus_range(n, range_size, iterations, 0);
+        let prefetch_time = bench_bplus_range(n, range_size, iterations, PREFETCH_DISTANCE);
         let std_time = bench_std_range(n, range_size, iterations);
         let speedup = std_time / bplus_time;
 
         println!(
-            "{:<20} {:<15.6} {:<15.6} {:<10.2}x",
+            "{:<20} {:<15.6} {:<15.6} {:<15.6} {:<10.2}x",
             format!("{}", range_size),
             bplus_time,
+            prefetch_time,
             std_time,
             speedup
         );
@@ -0,0 +1,117 @@
+use bplustree::{BPlusTreeMap, SearchKey};
+use std::hint::black_box;
+use std::time::Instant;
+
+fn random_keys(n: usize) -> Vec<u64> {
+    let mut state: u64 = 0x123456789abcdef0;
+    (0..n)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            state
+        })
+        .collect()
+}
+
+fn shuffled(keys: &[u64]) -> Vec<u64> {
+    let mut keys = keys.to_vec();
+    let n = keys.len();
+    let mut lookup_state: u64 = 0xfedcba9876543210;
+    for i in 0..n {
+        lookup_state = lookup_state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1);
+        let j = (lookup_state as usize) % (n - i);
+        keys.swap(i, i + j);
+    }
+    keys
+}
+
+fn bench_get(keys: &[u64], lookups: &[u64], cap: usize, key_search: bool) -> f64 {
+    let mut map = BPlusTreeMap::new(cap).expect("new");
+    if key_search {
+        map.enable_key_search();
+    }
+    for (i, &k) in keys.iter().enumerate() {
+        map.insert(k, i);
+    }
+
+    let start = Instant::now();
+    for key in lookups {
+        black_box(map.get(key));
+    }
+    start.elapsed().as_secs_f64()
+}
+
+/// Best of `ROUNDS` runs, to keep scheduler noise out of the ratios.
+const ROUNDS: usize = 5;
+
+fn best_of(mut f: impl FnMut() -> f64) -> f64 {
+    (0..ROUNDS).map(|_| f()).fold(f64::INFINITY, f64::min)
+}
+
+/// Search a single node-sized key array, isolating the in-node search from
+/// the cache misses of a full descent.
+fn bench_node(cap: usize, probes: &[u64], key_search: bool) -> f64 {
+    let node: Vec<u64> = (0..cap as u64).map(|i| i * 0x1_0000_0001).collect();
+    let search = u64::searcher();
+    let start = Instant::now();
+    for p in probes {
+        let p = p % (cap as u64 * 0x1_0000_0001);
+        if key_search {
+            black_box(search(black_box(&node), &p)).ok();
+        } else {
+            black_box(black_box(&node).binary_search(&p)).ok();
+        }
+    }
+    start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let sizes = vec![10_000, 100_000, 1_000_000];
+    let caps = vec![16, 64, 128, 256];
+
+    println!("In-Node Key Search Benchmark (u64 keys, random gets)");
+    println!("=====================================================\n");
+
+    for &n in &sizes {
+        let keys = random_keys(n);
+        let lookups = shuffled(&keys);
+        println!("Testing with {} items:", n);
+
+        // Warmup
+        let _ = bench_get(&keys[..1000], &lookups[..1000], 64, false);
+        let _ = bench_get(&keys[..1000], &lookups[..1000], 64, true);
+
+        for &cap in &caps {
+            let generic = best_of(|| bench_get(&keys, &lookups, cap, false));
+            let special = best_of(|| bench_get(&keys, &lookups, cap, true));
+            println!("  cap={}:", cap);
+            println!(
+                "    Generic:    {:.3}s ({:.0} ops/sec)",
+                generic,
+                n as f64 / generic
+            );
+            println!(
+                "    SearchKey:  {:.3}s ({:.0} ops/sec)",
+                special,
+                n as f64 / special
+            );
+            println!("    Speedup:    {:.2}x", generic / special);
+        }
+        println!();
+    }
+
+    println!("Single node search ({} probes):", sizes[1]);
+    let probes = random_keys(sizes[1]);
+    for &cap in &caps {
+        let generic = best_of(|| bench_node(cap, &probes, false));
+        let special = best_of(|| bench_node(cap, &probes, true));
+        println!(
+            "  cap={:>3}: generic {:.4}s, SearchKey {:.4}s, speedup {:.2}x",
+            cap,
+            generic,
+            special,
+            generic / special
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use bplustree::{BPlusTreeMap, NodeTag};
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+static VISITS: AtomicUsize = AtomicUsize::new(0);
+
+fn count_visit(_: NodeTag) {
+    VISITS.fetch_add(1, Ordering::Relaxed);
+}
+
+fn main() {
+    println!("=== Profiling: Reverse Range Iteration ===\n");
+
+    let n = 10_000_000;
+    let cap = 128;
+    let take = 100;
+    let iterations = 100_000;
+
+    println!("Building tree with {} items (capacity {})...", n, cap);
+    let build_start = Instant::now();
+    let mut map = BPlusTreeMap::new(cap).expect("new");
+    for i in 0..n {
+        map.insert(i, i * 2);
+    }
+    println!("Tree built in {:?}\n", build_start.elapsed());
+
+    // Node visits first, with the hook set; timings below run without it.
+    println!("=== Nodes visited per query ===");
+    map.set_visit_hook(Some(count_visit));
+    let visits = |f: &dyn Fn()| {
+        VISITS.store(0, Ordering::Relaxed);
+        f();
+        VISITS.load(Ordering::Relaxed)
+    };
+    let tail = visits(&|| {
+        black_box(map.range(..).rev().take(take).count());
+    });
+    let window = visits(&|| {
+        black_box(map.range(n / 2..n / 2 + take).rev().count());
+    });
+    let last = visits(&|| {
+        black_box(map.last());
+    });
+    println!("range(..).rev().take({}): {} nodes", take, tail);
+    println!("range(a..a+{}).rev():     {} nodes", take, window);
+    println!("last():                    {} nodes", last);
+    println!();
+    map.set_visit_hook(None);
+
+    println!("=== range(..).rev().take({}) ===", take);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        for (k, v) in map.range(..).rev().take(take) {
+            black_box((k, v));
+        }
+    }
+    let tail_time = start.elapsed();
+    println!("{} iterations: {:?}", iterations, tail_time);
+    println!("Per iteration: {:?}\n", tail_time / iterations as u32);
+
+    println!("=== range(a..a+{}).rev() at spread starts ===", take);
+    let start = Instant::now();
+    for i in 0..iterations {
+        let a = (i * 7919) % (n - take);
+        for (k, v) in map.range(a..a + take).rev() {
+            black_box((k, v));
+        }
+    }
+    let window_time = start.elapsed();
+    println!("{} iterations: {:?}", iterations, window_time);
+    println!("Per iteration: {:?}\n", window_time / iterations as u32);
+
+    println!("=== std::BTreeMap comparison ===");
+    let mut std_map = BTreeMap::new();
+    for i in 0..n {
+        std_map.insert(i, i * 2);
+    }
+    let start = Instant::now();
+    for _ in 0..iterations {
+        for (k, v) in std_map.range(..).rev().take(take) {
+            black_box((k, v));
+        }
+    }
+    let std_tail_time = start.elapsed();
+    let start = Instant::now();
+    for i in 0..iterations {
+        let a = (i * 7919) % (n - take);
+        for (k, v) in std_map.range(a..a + take).rev() {
+            black_box((k, v));
+        }
+    }
+    let std_window_time = start.elapsed();
+    println!(
+        "range(..).rev().take({}) per iteration: {:?}",
+        take,
+        std_tail_time / iterations as u32
+    );
+    println!(
+        "range(a..a+{}).rev() per iteration:     {:?}",
+        take,
+        std_window_time / iterations as u32
+    );
+    println!();
+
+    println!("=== Summary ===");
+    println!(
+        "BPlusTreeMap tail: {:?}, window: {:?}",
+        tail_time / iterations as u32,
+        window_time / iterations as u32
+    );
+    println!(
+        "std::BTreeMap tail: {:?}, window: {:?}",
+        std_tail_time / iterations as u32,
+        std_window_time / iterations as u32
+    );
+    println!(
+        "Both ends are found by descent, so neither depends on the {} items before them.",
+        n
+    );
+}
@@ -0,0 +1,167 @@
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+
+use crate::layout;
+use crate::{
+    alloc_branch_block, alloc_leaf_block, BPlusTreeError, BPlusTreeMap, BTreeResult,
+    DeriveSeparator,
+};
+
+impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
+    /// Build a tree from entries in strictly increasing key order.
+    ///
+    /// Leaves are packed full left to right and linked as they are filled;
+    /// the last leaf takes entries from its left neighbor if it would be
+    /// underfull. Branch levels are then built bottom-up with children spread
+    /// evenly, so no insert-time splits happen. Returns `DataIntegrityError`
+    /// if the input is not strictly increasing.
+    pub fn from_sorted_iter<I>(capacity: usize, iter: I) -> BTreeResult<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut tree = Self::new(capacity)?;
+        tree.build_from_sorted(iter)?;
+        Ok(tree)
+    }
+}
+
+impl<K, V, C: DeriveSeparator<K>> BPlusTreeMap<K, V, C> {
+    /// Replace the (empty) contents of `self` with a bottom-up build of `iter`.
+    pub(crate) fn build_from_sorted<I>(&mut self, iter: I) -> BTreeResult<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.clear();
+        let cap = self.leaf_layout.cap as usize;
+        let mut leaves: Vec<NonNull<u8>> = Vec::new();
+        unsafe {
+            let mut cur = alloc_leaf_block(&self.leaf_layout).expect("alloc leaf");
+            leaves.push(cur);
+            let mut parts = layout::carve_leaf::<K, V>(cur, &self.leaf_layout);
+            let mut len = 0usize;
+
+            for (k, v) in iter {
+                if len == cap {
+                    let next = alloc_leaf_block(&self.leaf_layout).expect("alloc leaf");
+                    let next_parts = layout::carve_leaf::<K, V>(next, &self.leaf_layout);
+                    *parts.next_ptr = next.as_ptr();
+                    if let Some(prev_ptr) = next_parts.prev_ptr {
+                        *prev_ptr = cur.as_ptr();
+                    }
+                    leaves.push(next);
+                    cur = next;
+                    len = 0;
+                    let last = &*(parts.keys_ptr as *const K).add(cap - 1);
+                    parts = next_parts;
+                    if self.cmp.compare(last, &k).is_ge() {
+                        return Err(self.abandon_build(leaves));
+                    }
+                } else if len > 0
+                    && self
+                        .cmp
+                        .compare(&*(parts.keys_ptr as *const K).add(len - 1), &k)
+                        .is_ge()
+                {
+                    return Err(self.abandon_build(leaves));
+                }
+                self.write_kv_at(
+                    parts.keys_ptr as *mut K,
+                    parts.vals_ptr as *mut V,
+                    len,
+                    k,
+                    v,
+                );
+                len += 1;
+                (*parts.hdr).len = len as u16;
+            }
+
+            if leaves.len() > 1 && len < self.min_leaf_len() {
+                let left = layout::carve_leaf::<K, V>(leaves[leaves.len() - 2], &self.leaf_layout);
+                let need = self.min_leaf_len() - len;
+                self.shift_right_n(
+                    parts.keys_ptr as *mut K,
+                    parts.vals_ptr as *mut V,
+                    len,
+                    need,
+                );
+                core::ptr::copy_nonoverlapping(
+                    (left.keys_ptr as *const K).add(cap - need),
+                    parts.keys_ptr as *mut K,
+                    need,
+                );
+                core::ptr::copy_nonoverlapping(
+                    (left.vals_ptr as *const V).add(cap - need),
+                    parts.vals_ptr as *mut V,
+                    need,
+                );
+                (*left.hdr).len = (cap - need) as u16;
+                (*parts.hdr).len = (len + need) as u16;
+            }
+
+            let seps: Vec<K> = leaves
+                .windows(2)
+                .map(|pair| {
+                    let l = layout::carve_leaf::<K, V>(pair[0], &self.leaf_layout);
+                    let r = layout::carve_leaf::<K, V>(pair[1], &self.leaf_layout);
+                    let left_last = (l.keys_ptr as *const K).add((*l.hdr).len as usize - 1);
+                    self.cmp.separator(&*left_last, &*(r.keys_ptr as *const K))
+                })
+                .collect();
+            self.root = Some(self.build_branch_levels(leaves, seps));
+        }
+        Ok(())
+    }
+
+    /// Stack branch levels over `level` until a single root remains.
+    /// `seps[i]` is the smallest key under `level[i + 1]`.
+    unsafe fn build_branch_levels(
+        &mut self,
+        mut level: Vec<NonNull<u8>>,
+        mut seps: Vec<K>,
+    ) -> NonNull<u8> {
+        let fanout = self.branch_layout.cap as usize + 1;
+        while level.len() > 1 {
+            let n = level.len();
+            let groups = n.div_ceil(fanout);
+            let (base, extra) = (n / groups, n % groups);
+            let mut children = level.into_iter();
+            let mut keys = seps.into_iter();
+            let mut next_level = Vec::with_capacity(groups);
+            let mut next_seps = Vec::with_capacity(groups - 1);
+            for g in 0..groups {
+                let count = base + usize::from(g < extra);
+                let node = alloc_branch_block(&self.branch_layout).expect("alloc branch");
+                let b = layout::carve_branch::<K>(node, &self.branch_layout);
+                let cbase = b.children_ptr as *mut *mut u8;
+                for j in 0..count {
+                    *cbase.add(j) = children.next().expect("child").as_ptr();
+                }
+                for j in 0..count - 1 {
+                    self.write_key_at(b.keys_ptr as *mut K, j, keys.next().expect("separator"));
+                }
+                (*b.hdr).len = (count - 1) as u16;
+                if g + 1 < groups {
+                    next_seps.push(keys.next().expect("separator"));
+                }
+                next_level.push(node);
+            }
+            level = next_level;
+            seps = next_seps;
+        }
+        level[0]
+    }
+
+    #[inline(always)]
+    unsafe fn shift_right_n(&self, keys_ptr: *mut K, vals_ptr: *mut V, len: usize, n: usize) {
+        core::ptr::copy(keys_ptr, keys_ptr.add(n), len);
+        core::ptr::copy(vals_ptr, vals_ptr.add(n), len);
+    }
+
+    /// Free the leaves of a build that hit out-of-order input.
+    unsafe fn abandon_build(&mut self, leaves: Vec<NonNull<u8>>) -> BPlusTreeError {
+        for leaf in leaves {
+            self.free_tree_no_drop(leaf);
+        }
+        BPlusTreeError::data_integrity("build from sorted input", "keys not strictly increasing")
+    }
+}
@@ -0,0 +1,1174 @@
+//! A map keyed by byte strings, with prefix-compressed slotted nodes.
+//!
+//! [`BytesBPlusTreeMap`] is meant for keys such as URLs and file paths that
+//! share long prefixes. Nodes use fixed byte budgets like [`BPlusTreeMap`],
+//! but instead of `[K; cap]` arrays each node stores the prefix common to all
+//! of its keys once, followed by an offset table into a heap of key suffixes:
+//!
+//! ```text
+//! tag u8 | reserved u8 | len u16 | flags u8 | reserved u8 | prefix len u16
+//!        | heap top u16 | garbage u16 | reserved u32 | link ptr
+//!        | slots: len x [payload | cell offset u16 | suffix len u16 | tail len u16] ->
+//!        | free space | <- cells: [suffix | tail] | prefix
+//! ```
+//!
+//! A leaf's payload is a value and its link the next leaf. A branch's payload
+//! is the child to the right of that slot's separator, and its link the
+//! leftmost child. Each cell may carry a tail of opaque bytes after the key
+//! suffix, which [`VarBPlusTreeMap`] uses for inline values. Cell bytes freed
+//! by removals are counted as garbage and reclaimed the next time the node is
+//! rewritten.
+//!
+//! A node holds as many entries as fit. When an insert does not fit in place,
+//! the node is rewritten with a recomputed prefix and split if needed. Branch
+//! separators are the shortest prefix of the right child's first key that
+//! sorts after the left child's last key.
+//!
+//! [`BPlusTreeMap`]: crate::BPlusTreeMap
+//! [`VarBPlusTreeMap`]: crate::VarBPlusTreeMap
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+use core::ops::{Bound, RangeBounds};
+use core::ptr::{self, NonNull};
+
+use crate::{
+    align_up, alloc_raw, dealloc_raw, BPlusTreeError, BTreeResult, InvariantViolation, LeafLink,
+    NodeHdr, NodeTag,
+};
+
+const PREFIX_LEN_OFF: usize = 6;
+const HEAP_TOP_OFF: usize = 8;
+const GARBAGE_OFF: usize = 10;
+const LINK_OFF: usize = 16;
+const HDR_END: usize = LINK_OFF + size_of::<*mut u8>();
+
+const _: () = assert!(size_of::<NodeHdr>() <= PREFIX_LEN_OFF);
+
+type Child = *mut u8;
+
+/// New right siblings of a rewritten node, with the separators before them.
+type Splits = Vec<(Vec<u8>, NonNull<u8>)>;
+
+/// Byte layout of one node kind, for slot payload `P`.
+#[derive(Copy, Clone, Debug)]
+struct SlottedLayout {
+    bytes: usize,
+    align: usize,
+    slots_off: usize,
+    /// Offset of the cell offset and suffix/tail lengths within a slot.
+    meta_off: usize,
+    stride: usize,
+}
+
+impl SlottedLayout {
+    fn compute<P>(bytes: usize) -> Self {
+        let a = align_of::<P>().max(align_of::<u16>());
+        let meta_off = align_up(size_of::<P>(), align_of::<u16>());
+        Self {
+            bytes,
+            align: a.max(align_of::<Child>()),
+            slots_off: align_up(HDR_END, a),
+            meta_off,
+            stride: align_up(meta_off + 6, a),
+        }
+    }
+
+    /// Largest key plus tail this layout accepts. Any four entries fit in one
+    /// node, so every split and merge finds a packing.
+    fn max_key_len(&self) -> usize {
+        (self.bytes.saturating_sub(self.slots_off) / 4).saturating_sub(self.stride)
+    }
+
+    /// Bytes a node holding entries `a..b` occupies.
+    fn size<P>(&self, e: &Entries<P>, a: usize, b: usize) -> usize {
+        if a == b {
+            return self.slots_off;
+        }
+        let n = b - a;
+        let lcp = common_prefix_len(e.keys.get(a), e.keys.get(b - 1));
+        self.slots_off + n * self.stride + e.keys.span(a, b) - (n - 1) * lcp + e.tails.span(a, b)
+    }
+}
+
+/// Byte strings packed into one buffer.
+#[derive(Default)]
+struct Runs {
+    bytes: Vec<u8>,
+    ends: Vec<usize>,
+}
+
+impl Runs {
+    fn with_capacity(n: usize) -> Self {
+        Self {
+            bytes: Vec::new(),
+            ends: Vec::with_capacity(n),
+        }
+    }
+
+    fn start(&self, i: usize) -> usize {
+        if i == 0 {
+            0
+        } else {
+            self.ends[i - 1]
+        }
+    }
+
+    fn get(&self, i: usize) -> &[u8] {
+        &self.bytes[self.start(i)..self.ends[i]]
+    }
+
+    /// Total length of runs `a..b`, `a < b`.
+    fn span(&self, a: usize, b: usize) -> usize {
+        self.ends[b - 1] - self.start(a)
+    }
+
+    fn push(&mut self, parts: &[&[u8]]) {
+        for part in parts {
+            self.bytes.extend_from_slice(part);
+        }
+        self.ends.push(self.bytes.len());
+    }
+
+    fn insert(&mut self, i: usize, run: &[u8]) {
+        let at = self.start(i);
+        self.bytes.splice(at..at, run.iter().copied());
+        for end in &mut self.ends[i..] {
+            *end += run.len();
+        }
+        self.ends.insert(i, at + run.len());
+    }
+
+    fn remove(&mut self, i: usize) {
+        let (a, b) = (self.start(i), self.ends[i]);
+        self.bytes.drain(a..b);
+        self.ends.remove(i);
+        for end in &mut self.ends[i..] {
+            *end -= b - a;
+        }
+    }
+
+    fn append(&mut self, other: Runs) {
+        let base = self.bytes.len();
+        self.bytes.extend_from_slice(&other.bytes);
+        self.ends.extend(other.ends.iter().map(|e| e + base));
+    }
+}
+
+/// A node's entries moved out for rewriting: full keys, cell tails and
+/// payloads.
+struct Entries<P> {
+    keys: Runs,
+    tails: Runs,
+    vals: Vec<P>,
+}
+
+impl<P> Entries<P> {
+    fn with_capacity(n: usize) -> Self {
+        Self {
+            keys: Runs::with_capacity(n),
+            tails: Runs::with_capacity(n),
+            vals: Vec::with_capacity(n),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.vals.len()
+    }
+
+    fn push(&mut self, key: &[u8], tail: &[u8], val: P) {
+        self.keys.push(&[key]);
+        self.tails.push(&[tail]);
+        self.vals.push(val);
+    }
+
+    fn insert(&mut self, i: usize, key: &[u8], tail: &[u8], val: P) {
+        self.keys.insert(i, key);
+        self.tails.insert(i, tail);
+        self.vals.insert(i, val);
+    }
+
+    fn remove(&mut self, i: usize) -> P {
+        self.keys.remove(i);
+        self.tails.remove(i);
+        self.vals.remove(i)
+    }
+
+    fn append(&mut self, other: Entries<P>) {
+        self.keys.append(other.keys);
+        self.tails.append(other.tails);
+        self.vals.extend(other.vals);
+    }
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// Shortest prefix of `right` that sorts after `left`, given `left < right`.
+fn separator<'a>(left: &[u8], right: &'a [u8]) -> &'a [u8] {
+    &right[..common_prefix_len(left, right) + 1]
+}
+
+/// Split entries `0..n` into node-sized chunks `a..b`. Branches pass
+/// `gap = 1`: the entry between two chunks moves up to the parent instead.
+fn plan<P>(l: &SlottedLayout, e: &Entries<P>, gap: usize) -> Vec<(usize, usize)> {
+    let n = e.len();
+    if l.size(e, 0, n) <= l.bytes {
+        return alloc::vec![(0, n)];
+    }
+    // Prefer the most even two-way split.
+    let mut best: Option<(usize, usize)> = None;
+    for s in 1..n.saturating_sub(gap) {
+        let worst = l.size(e, 0, s).max(l.size(e, s + gap, n));
+        if worst <= l.bytes && best.is_none_or(|(_, w)| worst < w) {
+            best = Some((s, worst));
+        }
+    }
+    if let Some((s, _)) = best {
+        return alloc::vec![(0, s), (s + gap, n)];
+    }
+    // Otherwise pack greedily, which needs the fewest nodes.
+    let mut chunks = Vec::new();
+    let mut a = 0;
+    loop {
+        let mut b = a + 1;
+        while b < n && l.size(e, a, b + 1) <= l.bytes {
+            b += 1;
+        }
+        if b < n && b + gap == n {
+            // Leave a key for the last branch chunk.
+            b -= 1;
+        }
+        chunks.push((a, b));
+        if b >= n {
+            return chunks;
+        }
+        a = b + gap;
+    }
+}
+
+#[inline(always)]
+unsafe fn read_u16(p: *const u8) -> usize {
+    (p as *const u16).read() as usize
+}
+
+#[inline(always)]
+unsafe fn write_u16(p: *mut u8, v: usize) {
+    (p as *mut u16).write(v as u16)
+}
+
+#[inline(always)]
+unsafe fn node_hdr(node: NonNull<u8>) -> *mut NodeHdr {
+    node.as_ptr() as *mut NodeHdr
+}
+
+#[inline(always)]
+unsafe fn node_len(node: NonNull<u8>) -> usize {
+    (*node_hdr(node)).len as usize
+}
+
+#[inline(always)]
+unsafe fn is_leaf(node: NonNull<u8>) -> bool {
+    (*node_hdr(node)).tag == NodeTag::Leaf
+}
+
+#[inline(always)]
+unsafe fn link(node: NonNull<u8>) -> *mut Child {
+    node.as_ptr().add(LINK_OFF) as *mut Child
+}
+
+#[inline(always)]
+unsafe fn prefix<'a>(node: NonNull<u8>, l: &SlottedLayout) -> &'a [u8] {
+    let p = node.as_ptr();
+    let n = read_u16(p.add(PREFIX_LEN_OFF));
+    core::slice::from_raw_parts(p.add(l.bytes - n), n)
+}
+
+#[inline(always)]
+unsafe fn slot(node: NonNull<u8>, l: &SlottedLayout, i: usize) -> *mut u8 {
+    node.as_ptr().add(l.slots_off + i * l.stride)
+}
+
+#[inline(always)]
+unsafe fn payload<P>(node: NonNull<u8>, l: &SlottedLayout, i: usize) -> *mut P {
+    slot(node, l, i) as *mut P
+}
+
+/// Cell offset, suffix length and tail length of slot `i`.
+#[inline(always)]
+unsafe fn cell(node: NonNull<u8>, l: &SlottedLayout, i: usize) -> (usize, usize, usize) {
+    let m = slot(node, l, i).add(l.meta_off);
+    (read_u16(m), read_u16(m.add(2)), read_u16(m.add(4)))
+}
+
+#[inline(always)]
+unsafe fn set_cell(s: *mut u8, l: &SlottedLayout, off: usize, suffix_len: usize, tail_len: usize) {
+    let m = s.add(l.meta_off);
+    write_u16(m, off);
+    write_u16(m.add(2), suffix_len);
+    write_u16(m.add(4), tail_len);
+}
+
+#[inline(always)]
+unsafe fn suffix<'a>(node: NonNull<u8>, l: &SlottedLayout, i: usize) -> &'a [u8] {
+    let (off, len, _) = cell(node, l, i);
+    core::slice::from_raw_parts(node.as_ptr().add(off), len)
+}
+
+#[inline(always)]
+unsafe fn tail<'a>(node: NonNull<u8>, l: &SlottedLayout, i: usize) -> &'a [u8] {
+    let (off, suffix_len, len) = cell(node, l, i);
+    core::slice::from_raw_parts(node.as_ptr().add(off + suffix_len), len)
+}
+
+unsafe fn full_key(node: NonNull<u8>, l: &SlottedLayout, i: usize) -> Vec<u8> {
+    let mut key = Vec::from(prefix(node, l));
+    key.extend_from_slice(suffix(node, l, i));
+    key
+}
+
+/// Position of `key` among a node's keys, as `slice::binary_search`.
+unsafe fn search(node: NonNull<u8>, l: &SlottedLayout, key: &[u8]) -> Result<usize, usize> {
+    let n = node_len(node);
+    let pre = prefix(node, l);
+    match key[..pre.len().min(key.len())].cmp(pre) {
+        Ordering::Less => return Err(0),
+        Ordering::Greater => return Err(n),
+        Ordering::Equal => {}
+    }
+    let rest = &key[pre.len()..];
+    let (mut lo, mut hi) = (0, n);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        match suffix(node, l, mid).cmp(rest) {
+            Ordering::Less => lo = mid + 1,
+            Ordering::Greater => hi = mid,
+            Ordering::Equal => return Ok(mid),
+        }
+    }
+    Err(lo)
+}
+
+/// Index of the branch child covering `key`, 0 being the leftmost.
+#[inline]
+unsafe fn route(node: NonNull<u8>, l: &SlottedLayout, key: &[u8]) -> usize {
+    match search(node, l, key) {
+        Ok(i) => i + 1,
+        Err(i) => i,
+    }
+}
+
+#[inline]
+unsafe fn child(node: NonNull<u8>, l: &SlottedLayout, idx: usize) -> NonNull<u8> {
+    let p = if idx == 0 {
+        *link(node)
+    } else {
+        *payload::<Child>(node, l, idx - 1)
+    };
+    NonNull::new_unchecked(p)
+}
+
+unsafe fn alloc_node(l: &SlottedLayout, what: &str) -> NonNull<u8> {
+    alloc_raw(l.bytes, l.align).expect(what)
+}
+
+unsafe fn init_node(node: NonNull<u8>, l: &SlottedLayout, tag: NodeTag) {
+    let p = node.as_ptr();
+    ptr::write(
+        p as *mut NodeHdr,
+        NodeHdr {
+            tag,
+            len: 0,
+            flags: 0,
+        },
+    );
+    write_u16(p.add(PREFIX_LEN_OFF), 0);
+    write_u16(p.add(HEAP_TOP_OFF), l.bytes);
+    write_u16(p.add(GARBAGE_OFF), 0);
+    *link(node) = ptr::null_mut();
+}
+
+/// Replace a node's entries with keys and tails `a..b` and the next `b - a`
+/// payloads from `vals`. The link pointer is left untouched.
+unsafe fn write_node<P>(
+    node: NonNull<u8>,
+    l: &SlottedLayout,
+    tag: NodeTag,
+    (keys, tails): (&Runs, &Runs),
+    (a, b): (usize, usize),
+    vals: &mut impl Iterator<Item = P>,
+) {
+    let p = node.as_ptr();
+    let pre = if a < b {
+        common_prefix_len(keys.get(a), keys.get(b - 1))
+    } else {
+        0
+    };
+    ptr::write(
+        p as *mut NodeHdr,
+        NodeHdr {
+            tag,
+            len: (b - a) as u16,
+            flags: 0,
+        },
+    );
+    let mut top = l.bytes - pre;
+    if a < b {
+        ptr::copy_nonoverlapping(keys.get(a).as_ptr(), p.add(top), pre);
+    }
+    for (j, i) in (a..b).enumerate() {
+        let (suf, tl) = (&keys.get(i)[pre..], tails.get(i));
+        top -= suf.len() + tl.len();
+        ptr::copy_nonoverlapping(suf.as_ptr(), p.add(top), suf.len());
+        ptr::copy_nonoverlapping(tl.as_ptr(), p.add(top + suf.len()), tl.len());
+        let s = slot(node, l, j);
+        ptr::write(s as *mut P, vals.next().expect("one payload per key"));
+        set_cell(s, l, top, suf.len(), tl.len());
+    }
+    debug_assert!(top >= l.slots_off + (b - a) * l.stride);
+    write_u16(p.add(PREFIX_LEN_OFF), pre);
+    write_u16(p.add(HEAP_TOP_OFF), top);
+    write_u16(p.add(GARBAGE_OFF), 0);
+}
+
+/// Move a node's entries out, leaving it empty.
+unsafe fn take_entries<P>(node: NonNull<u8>, l: &SlottedLayout) -> Entries<P> {
+    let n = node_len(node);
+    let pre = prefix(node, l);
+    let mut e = Entries::with_capacity(n);
+    for i in 0..n {
+        e.keys.push(&[pre, suffix(node, l, i)]);
+        e.tails.push(&[tail(node, l, i)]);
+        e.vals.push(ptr::read(payload::<P>(node, l, i)));
+    }
+    (*node_hdr(node)).len = 0;
+    e
+}
+
+/// Insert at slot `i` without rewriting the node, if `key` shares the node's
+/// prefix and its cell fits in the free space.
+unsafe fn insert_in_place<P>(
+    node: NonNull<u8>,
+    l: &SlottedLayout,
+    i: usize,
+    (key, tl): (&[u8], &[u8]),
+    val: P,
+) -> Result<(), P> {
+    let p = node.as_ptr();
+    let pre = prefix(node, l);
+    if !key.starts_with(pre) {
+        return Err(val);
+    }
+    let suf = &key[pre.len()..];
+    let n = node_len(node);
+    let top = read_u16(p.add(HEAP_TOP_OFF));
+    if top - (l.slots_off + n * l.stride) < l.stride + suf.len() + tl.len() {
+        return Err(val);
+    }
+    let s = slot(node, l, i);
+    ptr::copy(s, s.add(l.stride), (n - i) * l.stride);
+    let top = top - suf.len() - tl.len();
+    ptr::copy_nonoverlapping(suf.as_ptr(), p.add(top), suf.len());
+    ptr::copy_nonoverlapping(tl.as_ptr(), p.add(top + suf.len()), tl.len());
+    ptr::write(s as *mut P, val);
+    set_cell(s, l, top, suf.len(), tl.len());
+    write_u16(p.add(HEAP_TOP_OFF), top);
+    (*node_hdr(node)).len += 1;
+    Ok(())
+}
+
+/// Remove slot `i`, leaving its cell bytes as garbage.
+unsafe fn remove_in_place<P>(node: NonNull<u8>, l: &SlottedLayout, i: usize) -> P {
+    let p = node.as_ptr();
+    let n = node_len(node);
+    let s = slot(node, l, i);
+    let val = ptr::read(s as *const P);
+    let (_, suffix_len, tail_len) = cell(node, l, i);
+    let garbage = read_u16(p.add(GARBAGE_OFF)) + suffix_len + tail_len;
+    write_u16(p.add(GARBAGE_OFF), garbage);
+    ptr::copy(s.add(l.stride), s, (n - i - 1) * l.stride);
+    (*node_hdr(node)).len -= 1;
+    val
+}
+
+/// Whether a non-root node is empty or less than a quarter full.
+unsafe fn is_underfull(node: NonNull<u8>, l: &SlottedLayout) -> bool {
+    let p = node.as_ptr();
+    let n = node_len(node);
+    let heap = l.bytes - read_u16(p.add(HEAP_TOP_OFF)) - read_u16(p.add(GARBAGE_OFF));
+    n == 0 || n * l.stride + heap < (l.bytes - l.slots_off) / 4
+}
+
+/// What a subtree reports to its parent after a change.
+enum Change {
+    None,
+    Split(Splits),
+    Underfull,
+}
+
+/// Ordered map from byte strings to `V` with prefix-compressed nodes.
+///
+/// Keys are borrowed on insert and lookup and reassembled into owned
+/// `Vec<u8>`s when iterating. Keys longer than
+/// [`BytesBPlusTreeMap::max_key_len`] are rejected.
+pub struct BytesBPlusTreeMap<V> {
+    root: Option<NonNull<u8>>,
+    leaf: SlottedLayout,
+    branch: SlottedLayout,
+    len: usize,
+    _marker: PhantomData<V>,
+}
+
+impl<V> Drop for BytesBPlusTreeMap<V> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<V> BytesBPlusTreeMap<V> {
+    /// Common cache line size assumption (bytes).
+    pub const CACHE_LINE_BYTES: usize = 64;
+
+    /// Largest node budget: in-node offsets are 16-bit.
+    pub const MAX_NODE_BYTES: usize = u16::MAX as usize;
+
+    /// Construct with explicit byte budgets for leaves and branches.
+    ///
+    /// Fails if a budget exceeds [`Self::MAX_NODE_BYTES`] or is too small to
+    /// hold four entries with keys of at least one byte.
+    pub fn with_budgets(leaf_bytes: usize, branch_bytes: usize) -> Result<Self, BPlusTreeError> {
+        let leaf = SlottedLayout::compute::<V>(leaf_bytes);
+        let branch = SlottedLayout::compute::<Child>(branch_bytes);
+        for (kind, l) in [("leaf", &leaf), ("branch", &branch)] {
+            if l.bytes > Self::MAX_NODE_BYTES {
+                return Err(BPlusTreeError::InvalidCapacity(format!(
+                    "{} budget of {} bytes exceeds {}",
+                    kind,
+                    l.bytes,
+                    Self::MAX_NODE_BYTES
+                )));
+            }
+            if l.max_key_len() == 0 {
+                return Err(BPlusTreeError::InvalidCapacity(format!(
+                    "{} budget of {} bytes cannot hold four entries",
+                    kind, l.bytes
+                )));
+            }
+        }
+        Ok(Self {
+            root: None,
+            leaf,
+            branch,
+            len: 0,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Construct using cache-line counts for leaf and branch nodes.
+    pub fn with_cache_lines(
+        leaf_lines: usize,
+        branch_lines: usize,
+    ) -> Result<Self, BPlusTreeError> {
+        let lb = leaf_lines.saturating_mul(Self::CACHE_LINE_BYTES);
+        let bb = branch_lines.saturating_mul(Self::CACHE_LINE_BYTES);
+        Self::with_budgets(lb, bb)
+    }
+
+    /// Longest key [`BytesBPlusTreeMap::insert`] accepts.
+    pub fn max_key_len(&self) -> usize {
+        self.leaf.max_key_len().min(self.branch.max_key_len())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        let (leaf, i) = self.find(key)?;
+        unsafe { Some(&*payload::<V>(leaf, &self.leaf, i)) }
+    }
+
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        let (leaf, i) = self.find(key)?;
+        unsafe { Some(&mut *payload::<V>(leaf, &self.leaf, i)) }
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Value and tail stored for `key`.
+    pub(crate) fn get_entry(&self, key: &[u8]) -> Option<(&V, &[u8])> {
+        let (leaf, i) = self.find(key)?;
+        unsafe {
+            Some((
+                &*payload::<V>(leaf, &self.leaf, i),
+                tail(leaf, &self.leaf, i),
+            ))
+        }
+    }
+
+    /// Longest key plus tail a leaf entry may hold.
+    pub(crate) fn max_entry_len(&self) -> usize {
+        self.leaf.max_key_len()
+    }
+
+    /// Insert or replace the value for `key`, returning the old value.
+    pub fn insert(&mut self, key: &[u8], value: V) -> BTreeResult<Option<V>> {
+        Ok(self.insert_entry(key, &[], value)?.map(|(old, _)| old))
+    }
+
+    /// Insert with `tail` stored inline after the key suffix, returning the
+    /// old value and tail.
+    pub(crate) fn insert_entry(
+        &mut self,
+        key: &[u8],
+        tail: &[u8],
+        value: V,
+    ) -> BTreeResult<Option<(V, Vec<u8>)>> {
+        if key.len() > self.max_key_len() {
+            return Err(BPlusTreeError::invalid_state(
+                "insert",
+                &format!(
+                    "key of {} bytes exceeds the {}-byte limit",
+                    key.len(),
+                    self.max_key_len()
+                ),
+            ));
+        }
+        debug_assert!(key.len() + tail.len() <= self.max_entry_len());
+        let old = unsafe {
+            let root = match self.root {
+                Some(root) => root,
+                None => {
+                    let leaf = alloc_node(&self.leaf, "alloc leaf");
+                    init_node(leaf, &self.leaf, NodeTag::Leaf);
+                    self.root = Some(leaf);
+                    leaf
+                }
+            };
+            let (old, change) = self.insert_rec(root, key, tail, value);
+            self.fix_root(change);
+            old
+        };
+        if old.is_none() {
+            self.len += 1;
+        }
+        Ok(old)
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        self.remove_entry(key).map(|(old, _)| old)
+    }
+
+    /// Remove `key`, returning its value and tail.
+    pub(crate) fn remove_entry(&mut self, key: &[u8]) -> Option<(V, Vec<u8>)> {
+        let root = self.root?;
+        let old = unsafe {
+            let (old, change) = self.remove_rec(root, key);
+            self.fix_root(change);
+            old
+        }?;
+        self.len -= 1;
+        Some(old)
+    }
+
+    pub fn clear(&mut self) {
+        if let Some(root) = self.root.take() {
+            unsafe { self.free_rec(root) };
+        }
+        self.len = 0;
+    }
+
+    /// All entries in key order.
+    pub fn iter(&self) -> BytesRange<'_, V> {
+        self.range::<core::ops::RangeFull>(..)
+    }
+
+    /// Entries whose keys fall within `r`, a range of byte slices such as
+    /// `&b"a"[..]..&b"b"[..]` or `(Bound::Excluded(lo), Bound::Unbounded)`.
+    pub fn range<'k, R: RangeBounds<&'k [u8]>>(&self, r: R) -> BytesRange<'_, V> {
+        let (leaf, idx) = match r.start_bound().map(|k| *k) {
+            Bound::Unbounded => (self.leftmost_leaf(), 0),
+            Bound::Included(k) | Bound::Excluded(k) => match self.leaf_for(k) {
+                None => (None, 0),
+                Some(leaf) => {
+                    let idx = match unsafe { search(leaf, &self.leaf, k) } {
+                        Ok(i) if matches!(r.start_bound(), Bound::Excluded(_)) => i + 1,
+                        Ok(i) | Err(i) => i,
+                    };
+                    (Some(leaf), idx)
+                }
+            },
+        };
+        BytesRange {
+            leaf,
+            idx,
+            end: r.end_bound().map(|k| k.to_vec()),
+            layout: self.leaf,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        let mut count = 0;
+        let mut cur = self.leftmost_leaf();
+        while let Some(leaf) = cur {
+            count += 1;
+            cur = NonNull::new(unsafe { *link(leaf) });
+        }
+        count
+    }
+
+    /// Check ordering, separator bounds, in-node space accounting, uniform
+    /// leaf depth and the leaf chain.
+    pub fn check_invariants_detailed(&self) -> Result<(), InvariantViolation> {
+        let root = match self.root {
+            Some(root) => root,
+            None if self.len == 0 => return Ok(()),
+            None => {
+                return Err(InvariantViolation::CountMismatch {
+                    counted: 0,
+                    len: self.len,
+                })
+            }
+        };
+        let mut st = CheckState {
+            path: Vec::new(),
+            leaves: Vec::new(),
+            entries: 0,
+            leaf_depth: None,
+        };
+        unsafe { self.check_node(root, 0, None, None, &mut st)? };
+        // Each leaf's `next` must be the leaf after it in tree order.
+        let mut cur = self.leftmost_leaf();
+        let mut prev: Option<&Vec<usize>> = None;
+        for (leaf, path) in &st.leaves {
+            if cur != Some(*leaf) {
+                return Err(InvariantViolation::BrokenLink {
+                    path: prev.cloned().unwrap_or_default(),
+                    link: LeafLink::Next,
+                });
+            }
+            cur = NonNull::new(unsafe { *link(*leaf) });
+            prev = Some(path);
+        }
+        if cur.is_some() {
+            return Err(InvariantViolation::BrokenLink {
+                path: prev.cloned().unwrap_or_default(),
+                link: LeafLink::Next,
+            });
+        }
+        if st.entries != self.len {
+            return Err(InvariantViolation::CountMismatch {
+                counted: st.entries,
+                len: self.len,
+            });
+        }
+        Ok(())
+    }
+
+    fn find(&self, key: &[u8]) -> Option<(NonNull<u8>, usize)> {
+        let leaf = self.leaf_for(key)?;
+        unsafe { search(leaf, &self.leaf, key).ok().map(|i| (leaf, i)) }
+    }
+
+    fn leaf_for(&self, key: &[u8]) -> Option<NonNull<u8>> {
+        let mut node = self.root?;
+        unsafe {
+            while !is_leaf(node) {
+                node = child(node, &self.branch, route(node, &self.branch, key));
+            }
+        }
+        Some(node)
+    }
+
+    fn leftmost_leaf(&self) -> Option<NonNull<u8>> {
+        let mut node = self.root?;
+        unsafe {
+            while !is_leaf(node) {
+                node = child(node, &self.branch, 0);
+            }
+        }
+        Some(node)
+    }
+
+    unsafe fn insert_rec(
+        &mut self,
+        node: NonNull<u8>,
+        key: &[u8],
+        tl: &[u8],
+        value: V,
+    ) -> (Option<(V, Vec<u8>)>, Change) {
+        if is_leaf(node) {
+            let l = self.leaf;
+            let (i, old) = match search(node, &l, key) {
+                Ok(i) if tl.is_empty() && tail(node, &l, i).is_empty() => {
+                    let old = ptr::replace(payload(node, &l, i), value);
+                    return (Some((old, Vec::new())), Change::None);
+                }
+                Ok(i) => {
+                    let old_tail = tail(node, &l, i).to_vec();
+                    (i, Some((remove_in_place::<V>(node, &l, i), old_tail)))
+                }
+                Err(i) => (i, None),
+            };
+            let value = match insert_in_place(node, &l, i, (key, tl), value) {
+                Ok(()) => return (old, self.settled(node, &l, Vec::new())),
+                Err(value) => value,
+            };
+            let mut e = take_entries::<V>(node, &l);
+            e.insert(i, key, tl, value);
+            let splits = self.store_leaf(node, e);
+            return (old, self.settled(node, &l, splits));
+        }
+        let idx = route(node, &self.branch, key);
+        let (old, change) = self.insert_rec(child(node, &self.branch, idx), key, tl, value);
+        (old, self.absorb(node, idx, change))
+    }
+
+    unsafe fn remove_rec(
+        &mut self,
+        node: NonNull<u8>,
+        key: &[u8],
+    ) -> (Option<(V, Vec<u8>)>, Change) {
+        if is_leaf(node) {
+            let l = self.leaf;
+            return match search(node, &l, key) {
+                Ok(i) => {
+                    let old_tail = tail(node, &l, i).to_vec();
+                    let old = remove_in_place::<V>(node, &l, i);
+                    (Some((old, old_tail)), self.settled(node, &l, Vec::new()))
+                }
+                Err(_) => (None, Change::None),
+            };
+        }
+        let idx = route(node, &self.branch, key);
+        let (old, change) = self.remove_rec(child(node, &self.branch, idx), key);
+        (old, self.absorb(node, idx, change))
+    }
+
+    unsafe fn settled(&self, node: NonNull<u8>, l: &SlottedLayout, splits: Splits) -> Change {
+        if !splits.is_empty() {
+            Change::Split(splits)
+        } else if is_underfull(node, l) {
+            Change::Underfull
+        } else {
+            Change::None
+        }
+    }
+
+    /// Apply a change reported by child `idx` of branch `node`.
+    unsafe fn absorb(&mut self, node: NonNull<u8>, idx: usize, change: Change) -> Change {
+        if let Change::None = change {
+            return Change::None;
+        }
+        let bl = self.branch;
+        let leftmost = *link(node);
+        let mut e = take_entries::<Child>(node, &bl);
+        match change {
+            Change::None => unreachable!(),
+            Change::Split(splits) => {
+                for (j, (sep, sib)) in splits.into_iter().enumerate() {
+                    e.insert(idx + j, &sep, &[], sib.as_ptr());
+                }
+            }
+            Change::Underfull if e.len() > 0 => {
+                // Merge the child with a sibling, then re-split if needed.
+                let li = idx.saturating_sub(1);
+                let left = child_of(leftmost, &e, li);
+                let sep = e.keys.get(li).to_vec();
+                let right = NonNull::new_unchecked(e.remove(li));
+                let splits = if is_leaf(left) {
+                    let mut merged = take_entries::<V>(left, &self.leaf);
+                    merged.append(take_entries::<V>(right, &self.leaf));
+                    *link(left) = *link(right);
+                    dealloc_raw(right, self.leaf.bytes, self.leaf.align);
+                    self.store_leaf(left, merged)
+                } else {
+                    let mut merged = take_entries::<Child>(left, &bl);
+                    merged.push(&sep, &[], *link(right));
+                    merged.append(take_entries::<Child>(right, &bl));
+                    dealloc_raw(right, bl.bytes, bl.align);
+                    self.store_branch(left, *link(left), merged)
+                };
+                for (j, (sep, sib)) in splits.into_iter().enumerate() {
+                    e.insert(li + j, &sep, &[], sib.as_ptr());
+                }
+            }
+            // A lone child has no sibling; its parent is underfull too.
+            Change::Underfull => {}
+        }
+        let splits = self.store_branch(node, leftmost, e);
+        self.settled(node, &bl, splits)
+    }
+
+    /// Rewrite leaf `node` with `e`, splitting off new right siblings if the
+    /// entries do not fit in one node.
+    unsafe fn store_leaf(&mut self, node: NonNull<u8>, e: Entries<V>) -> Splits {
+        let l = self.leaf;
+        let chunks = plan(&l, &e, 0);
+        let mut nodes = Vec::with_capacity(chunks.len());
+        nodes.push(node);
+        for _ in 1..chunks.len() {
+            nodes.push(alloc_node(&l, "alloc leaf"));
+        }
+        let next = *link(node);
+        let Entries { keys, tails, vals } = e;
+        let mut vals = vals.into_iter();
+        let mut splits = Vec::new();
+        for (c, &(a, b)) in chunks.iter().enumerate() {
+            write_node(
+                nodes[c],
+                &l,
+                NodeTag::Leaf,
+                (&keys, &tails),
+                (a, b),
+                &mut vals,
+            );
+            *link(nodes[c]) = nodes.get(c + 1).map_or(next, |n| n.as_ptr());
+            if c > 0 {
+                splits.push((separator(keys.get(a - 1), keys.get(a)).to_vec(), nodes[c]));
+            }
+        }
+        splits
+    }
+
+    /// Rewrite branch `node` with `leftmost` and `e`, splitting off new right
+    /// siblings if needed; the key between two chunks moves up.
+    unsafe fn store_branch(
+        &mut self,
+        node: NonNull<u8>,
+        leftmost: Child,
+        e: Entries<Child>,
+    ) -> Splits {
+        let l = self.branch;
+        let chunks = plan(&l, &e, 1);
+        let mut splits = Vec::new();
+        for (c, &(a, b)) in chunks.iter().enumerate() {
+            let target = if c == 0 {
+                node
+            } else {
+                alloc_node(&l, "alloc branch")
+            };
+            write_node(
+                target,
+                &l,
+                NodeTag::Branch,
+                (&e.keys, &e.tails),
+                (a, b),
+                &mut e.vals[a..b].iter().copied(),
+            );
+            if c == 0 {
+                *link(target) = leftmost;
+            } else {
+                *link(target) = e.vals[a - 1];
+                splits.push((e.keys.get(a - 1).to_vec(), target));
+            }
+        }
+        splits
+    }
+
+    /// Grow the tree on a root split, or drop an empty root.
+    unsafe fn fix_root(&mut self, change: Change) {
+        if let Change::Split(mut splits) = change {
+            while !splits.is_empty() {
+                let old = self.root.expect("split root").as_ptr();
+                let root = alloc_node(&self.branch, "alloc new root branch");
+                let mut e = Entries::with_capacity(splits.len());
+                for (sep, sib) in &splits {
+                    e.push(sep, &[], sib.as_ptr());
+                }
+                splits = self.store_branch(root, old, e);
+                self.root = Some(root);
+            }
+            return;
+        }
+        while let Some(root) = self.root {
+            if node_len(root) > 0 {
+                return;
+            }
+            if is_leaf(root) {
+                dealloc_raw(root, self.leaf.bytes, self.leaf.align);
+                self.root = None;
+            } else {
+                self.root = Some(child(root, &self.branch, 0));
+                dealloc_raw(root, self.branch.bytes, self.branch.align);
+            }
+        }
+    }
+
+    unsafe fn free_rec(&mut self, node: NonNull<u8>) {
+        if is_leaf(node) {
+            for i in 0..node_len(node) {
+                ptr::drop_in_place(payload::<V>(node, &self.leaf, i));
+            }
+            dealloc_raw(node, self.leaf.bytes, self.leaf.align);
+        } else {
+            for idx in 0..=node_len(node) {
+                self.free_rec(child(node, &self.branch, idx));
+            }
+            dealloc_raw(node, self.branch.bytes, self.branch.align);
+        }
+    }
+
+    unsafe fn check_node(
+        &self,
+        node: NonNull<u8>,
+        depth: usize,
+        lower: Option<&[u8]>,
+        upper: Option<&[u8]>,
+        st: &mut CheckState,
+    ) -> Result<(), InvariantViolation> {
+        let leaf = is_leaf(node);
+        let l = if leaf { &self.leaf } else { &self.branch };
+        let p = node.as_ptr();
+        let n = node_len(node);
+        let pre = read_u16(p.add(PREFIX_LEN_OFF));
+        let top = read_u16(p.add(HEAP_TOP_OFF));
+        let garbage = read_u16(p.add(GARBAGE_OFF));
+        let corrupt = |st: &CheckState| InvariantViolation::CorruptHeap {
+            path: st.path.clone(),
+        };
+        if top < l.slots_off + n * l.stride || top + pre > l.bytes {
+            return Err(corrupt(st));
+        }
+        let mut used = garbage;
+        for i in 0..n {
+            let (off, suffix_len, tail_len) = cell(node, l, i);
+            if off < top || off + suffix_len + tail_len > l.bytes - pre {
+                return Err(corrupt(st));
+            }
+            used += suffix_len + tail_len;
+        }
+        if used != l.bytes - pre - top {
+            return Err(corrupt(st));
+        }
+
+        let keys: Vec<Vec<u8>> = (0..n).map(|i| full_key(node, l, i)).collect();
+        if let Some(i) = keys.windows(2).position(|w| w[0] >= w[1]) {
+            return Err(InvariantViolation::UnsortedKeys {
+                path: st.path.clone(),
+                index: i + 1,
+            });
+        }
+        for (i, k) in keys.iter().enumerate() {
+            // Leaf keys may equal the lower bound; separators may not.
+            let above = lower.is_none_or(|lo| if leaf { lo <= &k[..] } else { lo < &k[..] });
+            if !above || upper.is_some_and(|hi| &k[..] >= hi) {
+                return Err(InvariantViolation::SeparatorBound {
+                    path: st.path.clone(),
+                    index: i,
+                });
+            }
+        }
+
+        if n == 0 {
+            return Err(InvariantViolation::EmptyNode {
+                path: st.path.clone(),
+            });
+        }
+        if leaf {
+            match st.leaf_depth {
+                None => st.leaf_depth = Some(depth),
+                Some(d) if d != depth => {
+                    return Err(InvariantViolation::UnequalLeafDepth {
+                        path: st.path.clone(),
+                        depth,
+                        expected: d,
+                    })
+                }
+                Some(_) => {}
+            }
+            st.leaves.push((node, st.path.clone()));
+            st.entries += n;
+            return Ok(());
+        }
+        for idx in 0..=n {
+            let lo = if idx == 0 {
+                lower
+            } else {
+                Some(&keys[idx - 1][..])
+            };
+            let hi = if idx == n {
+                upper
+            } else {
+                Some(&keys[idx][..])
+            };
+            st.path.push(idx);
+            self.check_node(child(node, l, idx), depth + 1, lo, hi, st)?;
+            st.path.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Child `i` of a branch whose entries were taken out as `e`.
+unsafe fn child_of(leftmost: Child, e: &Entries<Child>, i: usize) -> NonNull<u8> {
+    NonNull::new_unchecked(if i == 0 { leftmost } else { e.vals[i - 1] })
+}
+
+struct CheckState {
+    /// Path of the node being checked.
+    path: Vec<usize>,
+    /// Leaves in tree order, with their paths.
+    leaves: Vec<(NonNull<u8>, Vec<usize>)>,
+    entries: usize,
+    leaf_depth: Option<usize>,
+}
+
+/// Iterator over a key range of a [`BytesBPlusTreeMap`], yielding each key
+/// reassembled from its leaf prefix and suffix.
+pub struct BytesRange<'a, V> {
+    leaf: Option<NonNull<u8>>,
+    idx: usize,
+    end: Bound<Vec<u8>>,
+    layout: SlottedLayout,
+    _marker: PhantomData<&'a V>,
+}
+
+impl<'a, V> BytesRange<'a, V> {
+    /// Next key with its value and tail.
+    pub(crate) fn next_entry(&mut self) -> Option<(Vec<u8>, &'a V, &'a [u8])> {
+        loop {
+            let leaf = self.leaf?;
+            unsafe {
+                if self.idx >= node_len(leaf) {
+                    self.leaf = NonNull::new(*link(leaf));
+                    self.idx = 0;
+                    continue;
+                }
+                let key = full_key(leaf, &self.layout, self.idx);
+                let within = match &self.end {
+                    Bound::Unbounded => true,
+                    Bound::Included(e) => key <= *e,
+                    Bound::Excluded(e) => key < *e,
+                };
+                if !within {
+                    self.leaf = None;
+                    return None;
+                }
+                let v = &*payload::<V>(leaf, &self.layout, self.idx);
+                let t = tail(leaf, &self.layout, self.idx);
+                self.idx += 1;
+                return Some((key, v, t));
+            }
+        }
+    }
+}
+
+impl<'a, V> Iterator for BytesRange<'a, V> {
+    type Item = (Vec<u8>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().map(|(k, v, _)| (k, v))
+    }
+}
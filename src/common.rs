@@ -1,17 +1,25 @@
-use alloc::format;
-use alloc::string::String;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
 use core::ptr::NonNull;
 
 use crate::layout;
-use crate::{BPlusTreeMap, NodeHdr, NodeTag};
+use crate::{BPlusTreeMap, Comparator, InvariantViolation, LeafLink, NodeHdr, NodeTag};
 
-pub(crate) struct ValidationState<K> {
+pub(crate) struct ValidationState<'a, K> {
     pub(crate) total_items: usize,
     pub(crate) prev_leaf: Option<NonNull<u8>>,
-    pub(crate) prev_key: Option<K>,
+    pub(crate) prev_key: Option<&'a K>,
+    /// Child indices from the root to the node being checked.
+    pub(crate) path: Vec<usize>,
+    /// Path to `prev_leaf`.
+    pub(crate) prev_path: Vec<usize>,
+    /// Depth of the first leaf; every leaf must match it.
+    pub(crate) leaf_depth: Option<usize>,
+    /// Addresses of the nodes checked so far.
+    pub(crate) seen: BTreeSet<usize>,
 }
 
-impl<K, V> BPlusTreeMap<K, V> {
+impl<K, V, C> BPlusTreeMap<K, V, C> {
     #[inline(always)]
     pub(crate) unsafe fn shift_right(
         &self,
@@ -56,23 +64,31 @@ impl<K, V> BPlusTreeMap<K, V> {
         (k, v)
     }
 
-    #[inline]
-    pub(crate) unsafe fn key_clone_at(&self, keys_ptr: *const K, idx: usize) -> K
+    /// Centralized binary search for keys in a node, using the specialized
+    /// [`crate::SearchKey`] path when the map has one enabled.
+    #[inline(always)]
+    pub(crate) fn binary_search_keys(&self, keys: &[K], target: &K) -> Result<usize, usize>
     where
-        K: Clone,
+        C: Comparator<K>,
     {
-        (*keys_ptr.add(idx)).clone()
+        match self.search {
+            Some(search) => search(keys, target),
+            None => keys.binary_search_by(|k| self.cmp.compare(k, target)),
+        }
     }
 
-    /// Centralized binary search for keys in a node.
-    /// This function will be optimized for performance in future iterations.
-    #[inline(always)]
-    pub(crate) fn binary_search_keys<T: Ord>(
-        &self,
-        keys: &[T],
-        target: &T,
-    ) -> Result<usize, usize> {
-        keys.binary_search(target)
+    /// Number of `keys` ordered before `target`, counting equal keys too when
+    /// `past_equal`: where a run of duplicates of `target` starts or ends.
+    #[inline]
+    pub(crate) fn partition_keys(&self, keys: &[K], target: &K, past_equal: bool) -> usize
+    where
+        C: Comparator<K>,
+    {
+        keys.partition_point(|k| match self.cmp.compare(k, target) {
+            core::cmp::Ordering::Less => true,
+            core::cmp::Ordering::Equal => past_equal,
+            core::cmp::Ordering::Greater => false,
+        })
     }
 
     /// Safely move a key-value pair from one location to another, ensuring sources are cleared.
@@ -120,7 +136,7 @@ impl<K, V> BPlusTreeMap<K, V> {
     }
 }
 
-impl<K: Ord, V> BPlusTreeMap<K, V> {
+impl<K, V, C: Comparator<K>> BPlusTreeMap<K, V, C> {
     #[inline(always)]
     pub(crate) unsafe fn child_for_key(
         &self,
@@ -130,19 +146,49 @@ impl<K: Ord, V> BPlusTreeMap<K, V> {
         let parts = layout::carve_branch::<K>(branch, &self.branch_layout);
         let len = (*parts.hdr).len as usize;
         let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
-        let child_idx = match self.binary_search_keys(keys, key) {
-            Ok(i) => i + 1,
-            Err(i) => i,
+        let child_idx = if self.duplicates {
+            // Past every separator equal to `key`, where it would be appended.
+            self.partition_keys(keys, key, true)
+        } else {
+            match self.binary_search_keys(keys, key) {
+                Ok(i) => i + 1,
+                Err(i) => i,
+            }
         };
         let child_ptr = *(parts.children_ptr.add(child_idx) as *const *mut u8);
         NonNull::new(child_ptr).map(|child| (child, child_idx))
     }
 
+    /// With duplicates, the leftmost child that may hold `key`: equal keys
+    /// can run on from it into the following children.
+    #[inline]
+    pub(crate) unsafe fn first_child_for_key(
+        &self,
+        branch: NonNull<u8>,
+        key: &K,
+    ) -> Option<(NonNull<u8>, usize)> {
+        let parts = layout::carve_branch::<K>(branch, &self.branch_layout);
+        let len = (*parts.hdr).len as usize;
+        let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
+        let child_idx = self.partition_keys(keys, key, false);
+        let child_ptr = *(parts.children_ptr.add(child_idx) as *const *mut u8);
+        NonNull::new(child_ptr).map(|child| (child, child_idx))
+    }
+
+    /// Report `node` to the hook set by [`BPlusTreeMap::set_visit_hook`].
+    #[inline(always)]
+    pub(crate) fn visit(&self, node: NonNull<u8>) {
+        if let Some(hook) = self.visit_hook {
+            hook(unsafe { (*(node.as_ptr() as *const NodeHdr)).tag });
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn leaf_for_key(&self, key: &K) -> Option<NonNull<u8>> {
         let mut cur = self.root?;
         unsafe {
             loop {
+                self.visit(cur);
                 let hdr = &*(cur.as_ptr() as *const NodeHdr);
                 match hdr.tag {
                     NodeTag::Leaf => return Some(cur),
@@ -163,6 +209,7 @@ impl<K: Ord, V> BPlusTreeMap<K, V> {
         let mut cur = self.root?;
         unsafe {
             loop {
+                self.visit(cur);
                 let hdr = &*(cur.as_ptr() as *const NodeHdr);
                 match hdr.tag {
                     NodeTag::Leaf => return Some(cur),
@@ -180,12 +227,50 @@ impl<K: Ord, V> BPlusTreeMap<K, V> {
     }
 }
 
-impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
+impl<K, V, C: Comparator<K>> BPlusTreeMap<K, V, C> {
+    /// The leaf before the non-empty `leaf`: its back link, or with
+    /// singly-linked leaves the last leaf under the deepest left turn on the
+    /// path to `leaf`'s first key.
+    pub(crate) unsafe fn prev_leaf(&self, leaf: NonNull<u8>) -> Option<NonNull<u8>> {
+        let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
+        if let Some(prev_ptr) = parts.prev_ptr {
+            let prev = NonNull::new(*prev_ptr)?;
+            self.visit(prev);
+            return Some(prev);
+        }
+        let first = &*(parts.keys_ptr as *const K);
+        let mut left = None;
+        let mut cur = self.root?;
+        while cur != leaf {
+            self.visit(cur);
+            if (*(cur.as_ptr() as *const NodeHdr)).tag == NodeTag::Leaf {
+                return None;
+            }
+            let (child, idx) = self.child_for_key(cur, first)?;
+            if idx > 0 {
+                let b = layout::carve_branch::<K>(cur, &self.branch_layout);
+                left = NonNull::new(*(b.children_ptr.add(idx - 1) as *const *mut u8));
+            }
+            cur = child;
+        }
+        let mut cur = left?;
+        loop {
+            self.visit(cur);
+            let hdr = &*(cur.as_ptr() as *const NodeHdr);
+            if hdr.tag == NodeTag::Leaf {
+                return Some(cur);
+            }
+            let b = layout::carve_branch::<K>(cur, &self.branch_layout);
+            cur = NonNull::new(*(b.children_ptr.add(hdr.len as usize) as *const *mut u8))?;
+        }
+    }
+
     #[inline]
     pub(crate) fn rightmost_leaf(&self) -> Option<NonNull<u8>> {
         let mut cur = self.root?;
         unsafe {
             loop {
+                self.visit(cur);
                 let hdr = &*(cur.as_ptr() as *const NodeHdr);
                 match hdr.tag {
                     NodeTag::Leaf => return Some(cur),
@@ -234,41 +319,58 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         self.check_invariants_detailed().is_ok()
     }
 
-    pub fn check_invariants_detailed(&self) -> Result<(), String> {
+    pub fn check_invariants_detailed(&self) -> Result<(), InvariantViolation> {
+        let Some(root) = self.root else {
+            return Ok(());
+        };
         let mut state = ValidationState {
             total_items: 0,
             prev_leaf: None,
             prev_key: None,
+            path: Vec::new(),
+            prev_path: Vec::new(),
+            leaf_depth: None,
+            seen: BTreeSet::new(),
         };
 
         unsafe {
-            match self.root {
-                None => Ok(()),
-                Some(root) => {
-                    self.validate_node(root, None, None, true, &mut state)?;
-
-                    if let Some(last_leaf) = state.prev_leaf {
-                        let next_ptr =
-                            *(last_leaf.as_ptr().add(self.leaf_layout.next_off) as *const *mut u8);
-                        if !next_ptr.is_null() {
-                            return Err("Tail leaf next pointer should be null".into());
-                        }
-                    }
-
-                    Ok(())
+            self.validate_node(root, None, None, true, &mut state)?;
+
+            if let Some(last_leaf) = state.prev_leaf {
+                let next_ptr =
+                    *(last_leaf.as_ptr().add(self.leaf_layout.next_off) as *const *mut u8);
+                if !next_ptr.is_null() {
+                    return Err(InvariantViolation::BrokenLink {
+                        path: state.prev_path,
+                        link: LeafLink::Next,
+                    });
                 }
             }
         }
+
+        let chain = self.len();
+        if chain != state.total_items {
+            return Err(InvariantViolation::LengthMismatch {
+                tree: state.total_items,
+                chain,
+            });
+        }
+        Ok(())
     }
 
-    pub(crate) unsafe fn validate_node(
-        &self,
+    pub(crate) unsafe fn validate_node<'s>(
+        &'s self,
         node: NonNull<u8>,
         lower: Option<&K>,
         upper: Option<&K>,
         is_root: bool,
-        state: &mut ValidationState<K>,
-    ) -> Result<Option<(K, K)>, String> {
+        state: &mut ValidationState<'s, K>,
+    ) -> Result<(), InvariantViolation> {
+        if !state.seen.insert(node.as_ptr() as usize) {
+            return Err(InvariantViolation::CycleDetected {
+                path: state.path.clone(),
+            });
+        }
         let hdr = &*(node.as_ptr() as *const NodeHdr);
         match hdr.tag {
             NodeTag::Leaf => self.validate_leaf(node, lower, upper, is_root, state),
@@ -276,187 +378,242 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         }
     }
 
-    pub(crate) unsafe fn validate_leaf(
-        &self,
+    pub(crate) unsafe fn validate_leaf<'s>(
+        &'s self,
         leaf: NonNull<u8>,
         lower: Option<&K>,
         upper: Option<&K>,
         is_root: bool,
-        state: &mut ValidationState<K>,
-    ) -> Result<Option<(K, K)>, String> {
+        state: &mut ValidationState<'s, K>,
+    ) -> Result<(), InvariantViolation> {
         let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
         let hdr = &*parts.hdr;
         let len = hdr.len as usize;
         let cap = self.leaf_layout.cap as usize;
+        let path = &state.path;
 
         if len > cap {
-            return Err(format!("Leaf has {} keys but capacity is {}", len, cap));
+            return Err(InvariantViolation::Overfull {
+                path: path.clone(),
+                len,
+                cap,
+            });
+        }
+
+        let depth = path.len();
+        match state.leaf_depth {
+            None => state.leaf_depth = Some(depth),
+            Some(expected) if expected != depth => {
+                return Err(InvariantViolation::UnequalLeafDepth {
+                    path: path.clone(),
+                    depth,
+                    expected,
+                })
+            }
+            Some(_) => {}
         }
 
         if len == 0 {
             if is_root {
-                return Ok(None);
+                return Ok(());
             } else {
-                return Err("Non-root leaf is empty".into());
+                return Err(InvariantViolation::EmptyNode { path: path.clone() });
             }
         }
 
         let min_required = self.min_leaf_len();
-        if !is_root && len < min_required {
-            return Err(format!(
-                "Leaf underfull: has {} keys, minimum is {}",
-                len, min_required
-            ));
+        if !is_root && len < min_required && !self.may_underfill(hdr) {
+            return Err(InvariantViolation::Underfull {
+                path: path.clone(),
+                len,
+                min: min_required,
+            });
         }
 
-        let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
+        let keys: &'s [K] = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
 
-        for window in keys.windows(2) {
-            if window[0] >= window[1] {
-                return Err("Leaf keys not strictly increasing".into());
-            }
+        if let Some(i) = keys
+            .windows(2)
+            .position(|w| self.out_of_order(&w[0], &w[1]))
+        {
+            return Err(InvariantViolation::UnsortedKeys {
+                path: path.clone(),
+                index: i + 1,
+            });
         }
 
         if let Some(low) = lower {
-            if keys[0] < *low {
-                return Err("Leaf keys fall below lower bound".into());
+            if self.cmp.compare(&keys[0], low).is_lt() {
+                return Err(InvariantViolation::SeparatorBound {
+                    path: path.clone(),
+                    index: 0,
+                });
             }
         }
         if let Some(high) = upper {
-            if keys[len - 1] >= *high {
-                return Err("Leaf keys exceed upper bound".into());
+            if self.out_of_order(&keys[len - 1], high) {
+                return Err(InvariantViolation::SeparatorBound {
+                    path: path.clone(),
+                    index: len - 1,
+                });
             }
         }
 
         if let Some(prev_leaf) = state.prev_leaf {
             let prev_next = *(prev_leaf.as_ptr().add(self.leaf_layout.next_off) as *const *mut u8);
             if prev_next != leaf.as_ptr() {
-                return Err("Leaf next pointer mismatch".into());
+                return Err(InvariantViolation::BrokenLink {
+                    path: state.prev_path.clone(),
+                    link: LeafLink::Next,
+                });
             }
         }
 
         if let Some(prev_ptr) = parts.prev_ptr {
-            match state.prev_leaf {
-                Some(prev) => {
-                    if *prev_ptr != prev.as_ptr() {
-                        return Err("Leaf prev pointer mismatch".into());
-                    }
-                }
-                None => {
-                    if !(*prev_ptr).is_null() {
-                        return Err("First leaf prev pointer should be null".into());
-                    }
-                }
+            let want = state
+                .prev_leaf
+                .map_or(core::ptr::null_mut(), |p| p.as_ptr());
+            if *prev_ptr != want {
+                return Err(InvariantViolation::BrokenLink {
+                    path: path.clone(),
+                    link: LeafLink::Prev,
+                });
             }
         }
 
-        state.prev_leaf = Some(leaf);
-
         if let Some(prev_key) = &state.prev_key {
-            if keys[0] <= *prev_key {
-                return Err("Leaf keys not globally increasing".into());
+            if self.out_of_order(prev_key, &keys[0]) {
+                return Err(InvariantViolation::UnsortedKeys {
+                    path: path.clone(),
+                    index: 0,
+                });
             }
         }
-        state.prev_key = Some(keys[len - 1].clone());
+
+        state.prev_leaf = Some(leaf);
+        state.prev_path.clone_from(&state.path);
+        state.prev_key = Some(&keys[len - 1]);
         state.total_items += len;
 
-        Ok(Some((keys[0].clone(), keys[len - 1].clone())))
+        Ok(())
     }
 
-    pub(crate) unsafe fn validate_branch(
-        &self,
+    pub(crate) unsafe fn validate_branch<'s>(
+        &'s self,
         branch: NonNull<u8>,
         lower: Option<&K>,
         upper: Option<&K>,
         is_root: bool,
-        state: &mut ValidationState<K>,
-    ) -> Result<Option<(K, K)>, String> {
+        state: &mut ValidationState<'s, K>,
+    ) -> Result<(), InvariantViolation> {
         let parts = layout::carve_branch::<K>(branch, &self.branch_layout);
         let len = (*parts.hdr).len as usize;
         let cap = self.branch_layout.cap as usize;
 
         if len > cap {
-            return Err(format!("Branch has {} keys but capacity is {}", len, cap));
+            return Err(InvariantViolation::Overfull {
+                path: state.path.clone(),
+                len,
+                cap,
+            });
         }
 
         if len == 0 {
             if !is_root {
-                return Err("Non-root branch has no keys".into());
+                return Err(InvariantViolation::EmptyNode {
+                    path: state.path.clone(),
+                });
             }
             let child_ptr = *(parts.children_ptr as *const *mut u8);
             if child_ptr.is_null() {
-                return Ok(None);
+                return Ok(());
             }
         }
 
         let min_required = self.min_branch_len();
-        if !is_root && len < min_required {
-            return Err(format!(
-                "Branch underfull: has {} keys, minimum is {}",
-                len, min_required
-            ));
+        if !is_root && len < min_required && !self.may_underfill(&*parts.hdr) {
+            return Err(InvariantViolation::Underfull {
+                path: state.path.clone(),
+                len,
+                min: min_required,
+            });
         }
 
         let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
-        for window in keys.windows(2) {
-            if window[0] >= window[1] {
-                return Err("Branch keys not strictly increasing".into());
-            }
+        if let Some(i) = keys
+            .windows(2)
+            .position(|w| self.out_of_order(&w[0], &w[1]))
+        {
+            return Err(InvariantViolation::UnsortedKeys {
+                path: state.path.clone(),
+                index: i + 1,
+            });
         }
 
         if let Some(low) = lower {
-            if len > 0 && keys[0] < *low {
-                return Err("Branch keys fall below lower bound".into());
+            if len > 0 && self.cmp.compare(&keys[0], low).is_lt() {
+                return Err(InvariantViolation::SeparatorBound {
+                    path: state.path.clone(),
+                    index: 0,
+                });
             }
         }
         if let Some(high) = upper {
-            if len > 0 && keys[len - 1] >= *high {
-                return Err("Branch keys exceed upper bound".into());
+            if len > 0 && self.out_of_order(&keys[len - 1], high) {
+                return Err(InvariantViolation::SeparatorBound {
+                    path: state.path.clone(),
+                    index: len - 1,
+                });
             }
         }
 
-        let mut subtree_min: Option<K> = None;
-        let mut subtree_max: Option<K> = None;
-
         for i in 0..=len {
             let child_ptr = *(parts.children_ptr.add(i) as *const *mut u8);
             let child = match NonNull::new(child_ptr) {
                 Some(child) => child,
-                None => return Err("Branch child pointer is null".into()),
+                None => {
+                    return Err(InvariantViolation::NullChild {
+                        path: state.path.clone(),
+                        index: i,
+                    })
+                }
             };
 
             let lower_bound = if i == 0 { lower } else { Some(&keys[i - 1]) };
             let upper_bound = if i == len { upper } else { Some(&keys[i]) };
 
-            if let Some((child_min, child_max)) =
-                self.validate_node(child, lower_bound, upper_bound, false, state)?
-            {
-                if subtree_min.is_none() {
-                    subtree_min = Some(child_min.clone());
-                }
-                subtree_max = Some(child_max);
-            }
+            state.path.push(i);
+            self.validate_node(child, lower_bound, upper_bound, false, state)?;
+            state.path.pop();
         }
 
-        Ok(match (subtree_min, subtree_max) {
-            (Some(min), Some(max)) => Some((min, max)),
-            _ => None,
-        })
+        Ok(())
+    }
+
+    /// Whether `a` may not come before `b`. Keys increase strictly, except
+    /// that with duplicates equal keys may repeat, within a node and on both
+    /// sides of a separator.
+    fn out_of_order(&self, a: &K, b: &K) -> bool {
+        match self.cmp.compare(a, b) {
+            core::cmp::Ordering::Less => false,
+            core::cmp::Ordering::Equal => !self.duplicates,
+            core::cmp::Ordering::Greater => true,
+        }
+    }
+
+    /// Deferred deletes (see [`crate::MergeThreshold`]) and skewed splits
+    /// (see [`crate::SplitPolicy`]) flag the nodes they leave underfull.
+    fn may_underfill(&self, hdr: &NodeHdr) -> bool {
+        hdr.flags & (NodeHdr::UNDERFULL | NodeHdr::SKEWED) != 0
     }
 
     #[inline(always)]
     pub(crate) fn min_leaf_len(&self) -> usize {
-        let cap = self.leaf_layout.cap as usize;
-        cap / 2
+        self.underflow.min_len(self.leaf_layout.cap as usize)
     }
 
     #[inline(always)]
     pub(crate) fn min_branch_len(&self) -> usize {
-        let cap = self.branch_layout.cap as usize;
-        if cap <= 2 {
-            1
-        } else {
-            cap / 2
-        }
+        self.underflow.min_len(self.branch_layout.cap as usize)
     }
 }
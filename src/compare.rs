@@ -0,0 +1,125 @@
+//! Key orderings for [`BPlusTreeMap`](crate::BPlusTreeMap).
+//!
+//! Every search, bound check and validation step in the map compares keys
+//! through a [`Comparator`] stored in the map, so keys need not implement
+//! `Ord`. Paths that change the tree's shape also need the separator keys
+//! stored in branches, which a [`DeriveSeparator`] comparator builds. The
+//! default [`OrdComparator`] is zero-sized, defers to `Ord` and clones
+//! separators; [`SeparatorOrd`] builds them through [`SeparatorKey`] instead,
+//! so keys need not be `Clone`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// A total order over `K`.
+///
+/// The order must be consistent for the lifetime of a map: changing it while
+/// entries are stored breaks the tree's invariants.
+pub trait Comparator<K> {
+    fn compare(&self, a: &K, b: &K) -> Ordering;
+}
+
+/// A [`Comparator`] that also builds branch separators.
+///
+/// Inserts, removes and bulk builds require it; lookups, iteration and
+/// validation only need the order.
+pub trait DeriveSeparator<K>: Comparator<K> {
+    /// A key `s` with `left < s <= right` in this order, stored in a branch
+    /// to route searches between two adjacent subtrees.
+    fn separator(&self, left: &K, right: &K) -> K;
+}
+
+/// The natural `Ord` order of `K`; separators are clones of `right`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OrdComparator;
+
+impl<K: Ord> Comparator<K> for OrdComparator {
+    #[inline(always)]
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<K: Ord + Clone> DeriveSeparator<K> for OrdComparator {
+    #[inline]
+    fn separator(&self, _left: &K, right: &K) -> K {
+        right.clone()
+    }
+}
+
+/// Closures and function pointers, e.g. `fn(&K, &K) -> Ordering`;
+/// separators are clones of `right`.
+impl<K, F: Fn(&K, &K) -> Ordering> Comparator<K> for F {
+    #[inline(always)]
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        self(a, b)
+    }
+}
+
+impl<K: Clone, F: Fn(&K, &K) -> Ordering> DeriveSeparator<K> for F {
+    #[inline]
+    fn separator(&self, _left: &K, right: &K) -> K {
+        right.clone()
+    }
+}
+
+/// Keys that can build their own branch separators without cloning.
+pub trait SeparatorKey: Ord + Sized {
+    /// A key `s` with `left < s <= right`, ideally cheaper to store than
+    /// `right` (e.g. its shortest prefix that still sorts after `left`).
+    fn separator(left: &Self, right: &Self) -> Self;
+}
+
+/// The natural `Ord` order of `K`, with separators from [`SeparatorKey`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SeparatorOrd;
+
+impl<K: Ord> Comparator<K> for SeparatorOrd {
+    #[inline(always)]
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<K: SeparatorKey> DeriveSeparator<K> for SeparatorOrd {
+    #[inline]
+    fn separator(&self, left: &K, right: &K) -> K {
+        K::separator(left, right)
+    }
+}
+
+macro_rules! impl_separator_copy {
+    ($($t:ty),*) => {$(
+        impl SeparatorKey for $t {
+            #[inline]
+            fn separator(_left: &Self, right: &Self) -> Self {
+                *right
+            }
+        }
+    )*};
+}
+
+impl_separator_copy!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, char, bool);
+
+/// Length of the shortest prefix of `right` that sorts after `left`.
+fn distinguishing_len(left: &[u8], right: &[u8]) -> usize {
+    let common = left.iter().zip(right).take_while(|(a, b)| a == b).count();
+    (common + 1).min(right.len())
+}
+
+impl SeparatorKey for Vec<u8> {
+    fn separator(left: &Self, right: &Self) -> Self {
+        right[..distinguishing_len(left, right)].to_vec()
+    }
+}
+
+impl SeparatorKey for String {
+    fn separator(left: &Self, right: &Self) -> Self {
+        let mut n = distinguishing_len(left.as_bytes(), right.as_bytes());
+        while !right.is_char_boundary(n) {
+            n += 1;
+        }
+        String::from(&right[..n])
+    }
+}
@@ -0,0 +1,239 @@
+//! Construction options for [`BPlusTreeMap`].
+//!
+//! [`TreeConfig`] gathers everything fixed when a tree is built, checks it,
+//! and reports it back from a live tree through [`BPlusTreeMap::config`].
+//! The older constructors are shorthands for particular configurations.
+
+use alloc::format;
+use core::mem::size_of;
+
+use crate::node_alloc::alloc_leaf_block;
+use crate::{
+    BPlusTreeError, BPlusTreeMap, BranchLayout, Comparator, LeafLayout, MergeThreshold,
+    OrdComparator, SplitPolicy,
+};
+
+/// How large a node of one kind is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeSize {
+    /// Exactly this many entries (leaves) or separator keys (branches).
+    Capacity(usize),
+    /// As many entries or separators as fit in this many bytes.
+    Bytes(usize),
+}
+
+/// Options for building a [`BPlusTreeMap`].
+///
+/// ```
+/// use bplustree::{SplitPolicy, TreeConfig};
+///
+/// let tree = TreeConfig::new()
+///     .leaf_capacity(128)
+///     .branch_bytes(1024)
+///     .split_policy(SplitPolicy::Adaptive)
+///     .build::<u64, u64>()
+///     .unwrap();
+/// assert_eq!(tree.config().leaf, bplustree::NodeSize::Capacity(128));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeConfig {
+    pub leaf: NodeSize,
+    pub branch: NodeSize,
+    /// Whether leaves link back to their predecessor. Singly-linked leaves
+    /// save a pointer per leaf; iterating backwards then descends from the
+    /// root once per leaf.
+    pub doubly_linked: bool,
+    pub split_policy: SplitPolicy,
+    pub merge_threshold: MergeThreshold,
+    /// Allocate an empty root leaf when the tree is built rather than on the
+    /// first insert.
+    pub eager_root: bool,
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TreeConfig {
+    /// Node capacity of [`TreeConfig::new`].
+    pub const DEFAULT_CAPACITY: usize = 64;
+    /// Smallest capacity a node may have.
+    pub const MIN_CAPACITY: usize = 4;
+    /// Largest capacity a node may have: node lengths are 16-bit.
+    pub const MAX_CAPACITY: usize = u16::MAX as usize;
+
+    /// [`TreeConfig::DEFAULT_CAPACITY`] for both node kinds, doubly-linked
+    /// leaves, and the default split policy and merge threshold.
+    pub const fn new() -> Self {
+        Self {
+            leaf: NodeSize::Capacity(Self::DEFAULT_CAPACITY),
+            branch: NodeSize::Capacity(Self::DEFAULT_CAPACITY),
+            doubly_linked: true,
+            split_policy: SplitPolicy::Even,
+            merge_threshold: MergeThreshold::Half,
+            eager_root: false,
+        }
+    }
+
+    /// Same capacity for leaves and branches.
+    pub fn capacity(self, capacity: usize) -> Self {
+        self.leaf_capacity(capacity).branch_capacity(capacity)
+    }
+
+    pub fn leaf_capacity(mut self, capacity: usize) -> Self {
+        self.leaf = NodeSize::Capacity(capacity);
+        self
+    }
+
+    pub fn branch_capacity(mut self, capacity: usize) -> Self {
+        self.branch = NodeSize::Capacity(capacity);
+        self
+    }
+
+    pub fn leaf_bytes(mut self, bytes: usize) -> Self {
+        self.leaf = NodeSize::Bytes(bytes);
+        self
+    }
+
+    pub fn branch_bytes(mut self, bytes: usize) -> Self {
+        self.branch = NodeSize::Bytes(bytes);
+        self
+    }
+
+    /// Byte budgets of whole 64-byte cache lines.
+    pub fn cache_lines(self, leaf_lines: usize, branch_lines: usize) -> Self {
+        let line = BPlusTreeMap::<(), ()>::CACHE_LINE_BYTES;
+        self.leaf_bytes(leaf_lines.saturating_mul(line))
+            .branch_bytes(branch_lines.saturating_mul(line))
+    }
+
+    pub fn doubly_linked(mut self, doubly_linked: bool) -> Self {
+        self.doubly_linked = doubly_linked;
+        self
+    }
+
+    pub fn split_policy(mut self, policy: SplitPolicy) -> Self {
+        self.split_policy = policy;
+        self
+    }
+
+    pub fn merge_threshold(mut self, threshold: MergeThreshold) -> Self {
+        self.merge_threshold = threshold;
+        self
+    }
+
+    pub fn eager_root(mut self, eager_root: bool) -> Self {
+        self.eager_root = eager_root;
+        self
+    }
+
+    /// Build an empty tree ordering keys by `Ord`.
+    pub fn build<K: Ord, V>(self) -> Result<BPlusTreeMap<K, V>, BPlusTreeError> {
+        self.build_with_comparator(OrdComparator)
+    }
+
+    /// Build an empty tree ordering keys with `cmp`.
+    ///
+    /// Fails with [`BPlusTreeError::InvalidCapacity`] if a capacity lies
+    /// outside `MIN_CAPACITY..=MAX_CAPACITY`, or a byte budget holds fewer
+    /// than `MIN_CAPACITY` entries or more than `MAX_CAPACITY`.
+    pub fn build_with_comparator<K, V, C: Comparator<K>>(
+        self,
+        cmp: C,
+    ) -> Result<BPlusTreeMap<K, V, C>, BPlusTreeError> {
+        let (leaf_layout, branch_layout) = self.layouts::<K, V>()?;
+        let mut tree = BPlusTreeMap::from_layouts(self, leaf_layout, branch_layout, cmp);
+        if self.eager_root {
+            let leaf = unsafe { alloc_leaf_block(&tree.leaf_layout) }
+                .ok_or_else(|| BPlusTreeError::AllocationError("leaf root".into()))?;
+            tree.root = Some(leaf);
+        }
+        Ok(tree)
+    }
+
+    /// Node layouts for `K` and `V`, checked against the capacity limits.
+    pub(crate) fn layouts<K, V>(&self) -> Result<(LeafLayout, BranchLayout), BPlusTreeError> {
+        let leaf = match self.leaf {
+            NodeSize::Capacity(cap) => {
+                LeafLayout::compute_for_cap::<K, V>(checked_cap("leaf", cap)?, self.doubly_linked)
+            }
+            NodeSize::Bytes(bytes) => {
+                let layout = LeafLayout::compute::<K, V>(bytes, self.doubly_linked);
+                // Any budget that fits one zero-sized entry fits the most.
+                let widest = match size_of::<K>() + size_of::<V>() {
+                    0 => usize::MAX,
+                    _ => LeafLayout::compute_for_cap::<K, V>(u16::MAX, self.doubly_linked).bytes,
+                };
+                checked_budget("leaf", bytes, layout.cap, widest)?;
+                layout
+            }
+        };
+        let branch = match self.branch {
+            NodeSize::Capacity(cap) => {
+                BranchLayout::compute_for_cap::<K>(checked_cap("branch", cap)?)
+            }
+            NodeSize::Bytes(bytes) => {
+                let layout = BranchLayout::compute::<K>(bytes);
+                let widest = BranchLayout::compute_for_cap::<K>(u16::MAX);
+                checked_budget("branch", bytes, layout.cap, widest.bytes)?;
+                layout
+            }
+        };
+        Ok((leaf, branch))
+    }
+}
+
+fn checked_cap(kind: &str, cap: usize) -> Result<u16, BPlusTreeError> {
+    if cap < TreeConfig::MIN_CAPACITY {
+        return Err(BPlusTreeError::invalid_capacity(
+            cap,
+            TreeConfig::MIN_CAPACITY,
+        ));
+    }
+    u16::try_from(cap).map_err(|_| {
+        BPlusTreeError::InvalidCapacity(format!(
+            "{} capacity {} exceeds {}",
+            kind,
+            cap,
+            TreeConfig::MAX_CAPACITY
+        ))
+    })
+}
+
+/// Check that a `bytes` budget yielding `cap` slots is neither too small nor
+/// larger than a node of the maximum capacity, `widest` bytes.
+fn checked_budget(kind: &str, bytes: usize, cap: u16, widest: usize) -> Result<(), BPlusTreeError> {
+    if (cap as usize) < TreeConfig::MIN_CAPACITY {
+        return Err(BPlusTreeError::InvalidCapacity(format!(
+            "{} budget of {} bytes holds {} entries (minimum required: {})",
+            kind,
+            bytes,
+            cap,
+            TreeConfig::MIN_CAPACITY
+        )));
+    }
+    if bytes > widest {
+        return Err(BPlusTreeError::InvalidCapacity(format!(
+            "{} budget of {} bytes exceeds the {} bytes of a node holding {} entries",
+            kind,
+            bytes,
+            widest,
+            TreeConfig::MAX_CAPACITY
+        )));
+    }
+    Ok(())
+}
+
+impl<K, V, C> BPlusTreeMap<K, V, C> {
+    /// The options this tree was built with, with its current split policy
+    /// and merge threshold.
+    pub fn config(&self) -> TreeConfig {
+        TreeConfig {
+            split_policy: self.split.policy,
+            merge_threshold: self.underflow.threshold,
+            ..self.config
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use crate::{dealloc_raw, layout, BPlusTreeError, BPlusTreeMap, NodeHdr, NodeTag};
+use crate::{dealloc_raw, layout, BPlusTreeError, BPlusTreeMap, DeriveSeparator, NodeHdr, NodeTag};
+use alloc::vec::Vec;
 use core::ptr::{self, NonNull};
 
-impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
+impl<K, V, C: DeriveSeparator<K>> BPlusTreeMap<K, V, C> {
     pub fn remove(&mut self, key: &K) -> Option<V> {
         let root = self.root?;
         let result = unsafe { self.remove_rec(root, key) };
@@ -20,13 +21,14 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         result
     }
 
-    unsafe fn check_root_collapse(&mut self) {
+    pub(crate) unsafe fn check_root_collapse(&mut self) {
         if let Some(root) = self.root {
             let hdr = &*(root.as_ptr() as *const NodeHdr);
             if hdr.tag == NodeTag::Branch {
                 let parts = layout::carve_branch::<K>(root, &self.branch_layout);
                 let len = (*parts.hdr).len as usize;
                 if len <= 1 {
+                    self.drop_finger();
                     let child_count = len + 1;
                     let mut keep_child: Option<NonNull<u8>> = None;
                     let mut keep_is_leaf = false;
@@ -43,7 +45,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                             NodeTag::Leaf => {
                                 let child = NonNull::new_unchecked(child_ptr);
                                 if (*child_hdr).len == 0 {
-                                    self.free_leaf_node(child);
+                                    self.free_leaf_node(child, keep_child.filter(|_| keep_is_leaf));
                                     *slot = ptr::null_mut();
                                     continue;
                                 }
@@ -58,7 +60,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                                         return;
                                     }
                                     self.merge_leaf_into(existing, child);
-                                    self.free_leaf_node(child);
+                                    self.free_leaf_node(child, Some(existing));
                                     *slot = ptr::null_mut();
                                 } else {
                                     keep_child = Some(child);
@@ -90,6 +92,20 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         }
     }
 
+    /// Collapse the root until it is a leaf or a branch with two children
+    /// that do not fit in one node.
+    pub(crate) unsafe fn collapse_root(&mut self) {
+        while let Some(root) = self.root {
+            if (*(root.as_ptr() as *const NodeHdr)).tag == NodeTag::Leaf {
+                break;
+            }
+            self.check_root_collapse();
+            if self.root == Some(root) {
+                break;
+            }
+        }
+    }
+
     unsafe fn make_leaf_root(&self, leaf: NonNull<u8>) {
         let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
         if let Some(prev_ptr) = parts.prev_ptr {
@@ -97,12 +113,15 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         }
     }
 
-    unsafe fn free_leaf_node(&mut self, leaf: NonNull<u8>) {
+    /// Unlink and free an emptied leaf whose left neighbor is `left`, the
+    /// preceding child of the same parent; singly-linked leaves cannot tell.
+    unsafe fn free_leaf_node(&mut self, leaf: NonNull<u8>, left: Option<NonNull<u8>>) {
+        self.drop_finger();
         let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
         let next = *parts.next_ptr;
         let prev = match parts.prev_ptr {
             Some(prev_ptr) => *prev_ptr,
-            None => ptr::null_mut(),
+            None => left.map_or(ptr::null_mut(), NonNull::as_ptr),
         };
 
         // Unlink from sibling chain
@@ -163,6 +182,8 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         }
 
         (*target_parts.hdr).len = (target_len + source_len) as u16;
+        (*target_parts.hdr).flags =
+            ((*target_parts.hdr).flags | (*source_parts.hdr).flags) & !NodeHdr::SKEWED;
         (*source_parts.hdr).len = 0;
     }
 
@@ -180,6 +201,9 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
             return;
         };
 
+        if self.defer_underflow(NonNull::new_unchecked(child_ptr)) {
+            return;
+        }
         let child_hdr = &*(child_ptr as *const NodeHdr);
         match child_hdr.tag {
             NodeTag::Leaf => self.rebalance_leaf_child(branch, idx, len),
@@ -187,7 +211,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         }
     }
 
-    unsafe fn rebalance_leaf_child(
+    pub(crate) unsafe fn rebalance_leaf_child(
         &mut self,
         branch: NonNull<u8>,
         child_idx: usize,
@@ -204,6 +228,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         if child_len >= min {
             return;
         }
+        self.drop_finger();
 
         if child_idx > 0 {
             let left_ptr = *children.add(child_idx - 1);
@@ -214,6 +239,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                     let left_len = (*left_parts.hdr).len as usize;
                     if left_len > min {
                         self.borrow_from_left_leaf(branch, child_idx);
+                        self.settle_skewed(child);
                         return;
                     }
                 }
@@ -229,6 +255,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                     let right_len = (*right_parts.hdr).len as usize;
                     if right_len > min {
                         self.borrow_from_right_leaf(branch, child_idx);
+                        self.settle_skewed(child);
                         return;
                     }
                 }
@@ -242,7 +269,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         }
     }
 
-    unsafe fn rebalance_branch_child(
+    pub(crate) unsafe fn rebalance_branch_child(
         &mut self,
         branch: NonNull<u8>,
         child_idx: usize,
@@ -259,6 +286,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         if child_len >= min {
             return;
         }
+        self.drop_finger();
 
         if child_idx > 0 {
             let left_ptr = *children.add(child_idx - 1);
@@ -267,6 +295,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                 let left_len = (*left_parts.hdr).len as usize;
                 if left_len > min {
                     self.borrow_from_left_branch(branch, child_idx);
+                    self.settle_skewed(child);
                     return;
                 }
             }
@@ -279,6 +308,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                 let right_len = (*right_parts.hdr).len as usize;
                 if right_len > min {
                     self.borrow_from_right_branch(branch, child_idx);
+                    self.settle_skewed(child);
                     return;
                 }
             }
@@ -424,6 +454,8 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
             *left_children.add(left_len + 1 + i) = *child_children.add(i);
         }
         (*left_parts.hdr).len = (left_len + 1 + child_len) as u16;
+        (*left_parts.hdr).flags =
+            ((*left_parts.hdr).flags | (*child_parts.hdr).flags) & !NodeHdr::SKEWED;
         (*child_parts.hdr).len = 0;
 
         self.free_branch_node(child);
@@ -476,6 +508,8 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
             *child_children.add(child_len + 1 + i) = *right_children.add(i);
         }
         (*child_parts.hdr).len = (child_len + 1 + right_len) as u16;
+        (*child_parts.hdr).flags =
+            ((*child_parts.hdr).flags | (*right_parts.hdr).flags) & !NodeHdr::SKEWED;
         (*right_parts.hdr).len = 0;
 
         self.free_branch_node(right);
@@ -560,7 +594,10 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         (*left_parts.hdr).len = (left_len - 1) as u16;
         (*child_parts.hdr).len = (child_len + 1) as u16;
 
-        let new_sep = self.key_clone_at(child_parts.keys_ptr as *const K, 0);
+        let new_sep = self.cmp.separator(
+            &*(left_parts.keys_ptr as *const K).add(left_len - 2),
+            &*(child_parts.keys_ptr as *const K),
+        );
         let sep_slot = keys.add(child_idx - 1);
         let old_sep = core::ptr::read(sep_slot);
         drop(old_sep);
@@ -614,7 +651,10 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         // If right_len == 1, we've already transferred the only item, so nothing to drop
         (*right_parts.hdr).len = (right_len - 1) as u16;
 
-        let new_sep = self.key_clone_at(right_parts.keys_ptr as *const K, 0);
+        let new_sep = self.cmp.separator(
+            &*(child_parts.keys_ptr as *const K).add(child_len),
+            &*(right_parts.keys_ptr as *const K),
+        );
         let sep_slot = keys.add(child_idx);
         let old_sep = core::ptr::read(sep_slot);
         drop(old_sep);
@@ -631,7 +671,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         let child = NonNull::new_unchecked(child_ptr);
 
         self.merge_leaf_into(left, child);
-        self.free_leaf_node(child);
+        self.free_leaf_node(child, Some(left));
         self.remove_branch_entry(branch, child_idx - 1);
     }
 
@@ -645,7 +685,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         let right = NonNull::new_unchecked(right_ptr);
 
         self.merge_leaf_into(child, right);
-        self.free_leaf_node(right);
+        self.free_leaf_node(right, Some(child));
         self.remove_branch_entry(branch, child_idx);
     }
 
@@ -678,6 +718,29 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         let hdr = &*(node.as_ptr() as *const NodeHdr);
         match hdr.tag {
             NodeTag::Leaf => self.leaf_remove(node, key),
+            NodeTag::Branch if self.duplicates => {
+                // Duplicates of `key` may start at the end of one child and
+                // run on into the next; remove the first.
+                let (mut child, mut idx) = self.first_child_for_key(node, key)?;
+                loop {
+                    if let Some(value) = self.remove_rec(child, key) {
+                        self.fix_branch_child(node, idx);
+                        return Some(value);
+                    }
+                    let parts = layout::carve_branch::<K>(node, &self.branch_layout);
+                    let len = (*parts.hdr).len as usize;
+                    if idx == len
+                        || self
+                            .cmp
+                            .compare(&*(parts.keys_ptr.add(idx) as *const K), key)
+                            .is_ne()
+                    {
+                        return None;
+                    }
+                    idx += 1;
+                    child = NonNull::new(*(parts.children_ptr.add(idx) as *const *mut u8))?;
+                }
+            }
             NodeTag::Branch => {
                 let (child, idx) = self.child_for_key(node, key)?;
                 let result = self.remove_rec(child, key);
@@ -693,7 +756,14 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
         let len = (*parts.hdr).len as usize;
         let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
-        let idx = self.binary_search_keys(keys, key).ok()?;
+        let idx = if self.duplicates {
+            let first = self.partition_keys(keys, key, false);
+            keys.get(first)
+                .filter(|k| self.cmp.compare(k, key).is_eq())
+                .map(|_| first)?
+        } else {
+            self.binary_search_keys(keys, key).ok()?
+        };
 
         // Read the key and value (transferring ownership)
         let removed_key = core::ptr::read((parts.keys_ptr as *const K).add(idx));
@@ -720,4 +790,129 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
     pub fn remove_item(&mut self, key: &K) -> Result<V, BPlusTreeError> {
         self.remove(key).ok_or(BPlusTreeError::KeyNotFound)
     }
+
+    /// Bulk delete: keep only the entries for which `f` returns true.
+    /// The predicate sees every entry once in key order and may mutate values
+    /// in place. Verdicts are collected first, so the tree is not restructured
+    /// while `f` runs; then each leaf holding rejected entries is compacted in
+    /// place and the nodes left underfull are rebalanced as `remove` would.
+    /// Returns the number of removed entries.
+    pub fn retain<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        // Positions in key order of the rejected entries.
+        let mut rejected: Vec<usize> = Vec::new();
+        let mut pos = 0usize;
+        let mut cur = match self.leftmost_leaf() {
+            Some(p) => p.as_ptr(),
+            None => ptr::null_mut(),
+        };
+        unsafe {
+            while !cur.is_null() {
+                let parts =
+                    layout::carve_leaf::<K, V>(NonNull::new_unchecked(cur), &self.leaf_layout);
+                let len = (*parts.hdr).len as usize;
+                for i in 0..len {
+                    let k = &*(parts.keys_ptr.add(i) as *const K);
+                    let v = &mut *(parts.vals_ptr.add(i) as *mut V);
+                    if !f(k, v) {
+                        rejected.push(pos + i);
+                    }
+                }
+                pos += len;
+                cur = *parts.next_ptr;
+            }
+        }
+        if let (false, Some(root)) = (rejected.is_empty(), self.root) {
+            self.drop_finger();
+            let mut doomed = Vec::new();
+            unsafe {
+                self.retain_below(root, &mut rejected.as_slice(), &mut 0, &mut doomed);
+                self.collapse_root();
+            }
+        }
+        rejected.len()
+    }
+
+    /// Drop the entries of `node`'s subtree whose positions lead `rejected`,
+    /// the first entry of the subtree being at `pos`, then rebalance its
+    /// children. Leaves without rejected entries are left untouched.
+    unsafe fn retain_below(
+        &mut self,
+        node: NonNull<u8>,
+        rejected: &mut &[usize],
+        pos: &mut usize,
+        doomed: &mut Vec<(K, V)>,
+    ) {
+        if (*(node.as_ptr() as *const NodeHdr)).tag == NodeTag::Leaf {
+            let parts = layout::carve_leaf::<K, V>(node, &self.leaf_layout);
+            let len = (*parts.hdr).len as usize;
+            let end = *pos + len;
+            if rejected.first().is_some_and(|&r| r < end) {
+                let keys = parts.keys_ptr as *mut K;
+                let vals = parts.vals_ptr as *mut V;
+                let mut kept = 0;
+                for i in 0..len {
+                    let (k, v) = self.read_kv_at(keys, vals, i);
+                    if rejected.first() == Some(&(*pos + i)) {
+                        *rejected = &rejected[1..];
+                        doomed.push((k, v));
+                    } else {
+                        self.write_kv_at(keys, vals, kept, k, v);
+                        kept += 1;
+                    }
+                }
+                (*parts.hdr).len = kept as u16;
+                // Dropped only once the leaf is consistent again.
+                doomed.clear();
+            }
+            *pos = end;
+            return;
+        }
+
+        let parts = layout::carve_branch::<K>(node, &self.branch_layout);
+        let children = parts.children_ptr as *const *mut u8;
+        for i in 0..=(*parts.hdr).len as usize {
+            self.retain_below(
+                NonNull::new_unchecked(*children.add(i)),
+                rejected,
+                pos,
+                doomed,
+            );
+        }
+        self.fix_children(node);
+    }
+
+    /// Left to right, borrow into or merge away each underfull child of
+    /// `branch` until it is no longer underfull.
+    unsafe fn fix_children(&mut self, branch: NonNull<u8>) {
+        let parts = layout::carve_branch::<K>(branch, &self.branch_layout);
+        let children = parts.children_ptr as *const *mut u8;
+        let mut i = 0;
+        loop {
+            let len = (*parts.hdr).len as usize;
+            if len == 0 || i > len {
+                break;
+            }
+            let child = NonNull::new_unchecked(*children.add(i));
+            if !self.is_underfull(child) || self.defer_underflow(child) {
+                i += 1;
+                continue;
+            }
+            match (*(child.as_ptr() as *const NodeHdr)).tag {
+                NodeTag::Leaf => self.rebalance_leaf_child(branch, i, len),
+                NodeTag::Branch => {
+                    self.rebalance_branch_child(branch, i, len);
+                    // A grandchild that was the only child of its parent
+                    // now has siblings to borrow from or merge with.
+                    i = i.saturating_sub(1);
+                    let last = (*parts.hdr).len as usize;
+                    for j in i..=(i + 1).min(last) {
+                        self.fix_children(NonNull::new_unchecked(*children.add(j)));
+                    }
+                }
+            }
+        }
+    }
 }
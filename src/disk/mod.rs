@@ -0,0 +1,32 @@
+//! Page-based, file-backed B+ tree.
+//!
+//! Nodes live in fixed-size page slots of a single file. Child and sibling
+//! links are page ids instead of raw pointers, and a bounded buffer pool with
+//! clock eviction keeps hot pages in memory. Keys and values must be [`Pod`]
+//! so they can be stored as fixed-width byte images.
+//!
+//! A tree may optionally keep a write-ahead log of page images next to the
+//! data file. Every operation is then atomic across a crash: on open, the
+//! committed prefix of the log is replayed and torn or uncommitted records
+//! are discarded.
+
+mod node;
+mod pool;
+mod storage;
+mod tree;
+mod wal;
+
+pub use node::PageLayout;
+pub use pool::{BufferPool, PoolStats};
+pub use storage::{FileStorage, MemStorage, Storage};
+pub use tree::{wal_path, DiskBPlusTree, DiskRange};
+pub use wal::RecoveryStats;
+
+pub use crate::pod::Pod;
+pub(crate) use crate::pod::{pod_read, pod_write};
+
+/// Index of a page slot within the backing file.
+pub type PageId = u32;
+
+/// Sentinel for "no page" in child, sibling, root and free-list links.
+pub const NO_PAGE: PageId = u32::MAX;
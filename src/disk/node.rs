@@ -0,0 +1,240 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use super::{pod_read, pod_write, PageId, Pod, NO_PAGE};
+
+pub(crate) const TAG_BRANCH: u8 = 0;
+pub(crate) const TAG_LEAF: u8 = 1;
+pub(crate) const TAG_FREE: u8 = 2;
+
+const TAG_OFF: usize = 0;
+const LEN_OFF: usize = 2;
+const LEAF_NEXT_OFF: usize = 4;
+const LEAF_PREV_OFF: usize = 8;
+const LEAF_HDR: usize = 12;
+const BRANCH_HDR: usize = 4;
+const FREE_NEXT_OFF: usize = 4;
+
+/// Byte layout of leaf and branch pages for a given page size and K/V widths.
+///
+/// Leaf page:   `[tag u8][flags u8][len u16][next u32][prev u32][K; cap][V; cap]`
+/// Branch page: `[tag u8][flags u8][len u16][PageId; cap+1][K; cap]`
+///
+/// All fields are stored unaligned, so arrays are packed back to back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PageLayout {
+    pub bytes: usize,
+    pub key_size: usize,
+    pub val_size: usize,
+    pub leaf_cap: u16,
+    pub branch_cap: u16,
+    pub leaf_keys_off: usize,
+    pub leaf_vals_off: usize,
+    pub children_off: usize,
+    pub branch_keys_off: usize,
+}
+
+impl PageLayout {
+    /// Compute the layout of `bytes`-sized pages holding keys `K` and values `V`.
+    pub fn compute<K: Pod, V: Pod>(bytes: usize) -> Self {
+        let ks = size_of::<K>();
+        let vs = size_of::<V>();
+        let ps = size_of::<PageId>();
+
+        let leaf_cap = match ks + vs {
+            0 => u16::MAX as usize,
+            w => bytes.saturating_sub(LEAF_HDR) / w,
+        }
+        .min(u16::MAX as usize);
+        let branch_cap =
+            (bytes.saturating_sub(BRANCH_HDR + ps) / (ks + ps)).min(u16::MAX as usize - 1);
+
+        Self {
+            bytes,
+            key_size: ks,
+            val_size: vs,
+            leaf_cap: leaf_cap as u16,
+            branch_cap: branch_cap as u16,
+            leaf_keys_off: LEAF_HDR,
+            leaf_vals_off: LEAF_HDR + leaf_cap * ks,
+            children_off: BRANCH_HDR,
+            branch_keys_off: BRANCH_HDR + (branch_cap + 1) * ps,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn min_leaf_len(&self) -> usize {
+        self.leaf_cap as usize / 2
+    }
+
+    #[inline(always)]
+    pub(crate) fn min_branch_len(&self) -> usize {
+        let cap = self.branch_cap as usize;
+        if cap <= 2 {
+            1
+        } else {
+            cap / 2
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn leaf_key<K: Pod>(&self, page: &[u8], i: usize) -> K {
+        pod_read(&page[self.leaf_keys_off + i * self.key_size..])
+    }
+
+    #[inline(always)]
+    pub(crate) fn leaf_val<V: Pod>(&self, page: &[u8], i: usize) -> V {
+        pod_read(&page[self.leaf_vals_off + i * self.val_size..])
+    }
+
+    #[inline(always)]
+    pub(crate) fn branch_key<K: Pod>(&self, page: &[u8], i: usize) -> K {
+        pod_read(&page[self.branch_keys_off + i * self.key_size..])
+    }
+
+    #[inline(always)]
+    pub(crate) fn child(&self, page: &[u8], i: usize) -> PageId {
+        pod_read(&page[self.children_off + i * size_of::<PageId>()..])
+    }
+}
+
+#[inline(always)]
+pub(crate) fn page_tag(page: &[u8]) -> u8 {
+    page[TAG_OFF]
+}
+
+#[inline(always)]
+pub(crate) fn page_len(page: &[u8]) -> usize {
+    pod_read::<u16>(&page[LEN_OFF..]) as usize
+}
+
+#[inline(always)]
+pub(crate) fn leaf_next(page: &[u8]) -> PageId {
+    pod_read(&page[LEAF_NEXT_OFF..])
+}
+
+#[inline(always)]
+pub(crate) fn leaf_prev(page: &[u8]) -> PageId {
+    pod_read(&page[LEAF_PREV_OFF..])
+}
+
+#[inline(always)]
+pub(crate) fn free_next(page: &[u8]) -> PageId {
+    pod_read(&page[FREE_NEXT_OFF..])
+}
+
+/// Encode a free-list page pointing at `next`.
+pub(crate) fn encode_free(page: &mut [u8], next: PageId) {
+    page.fill(0);
+    page[TAG_OFF] = TAG_FREE;
+    pod_write(&mut page[FREE_NEXT_OFF..], next);
+}
+
+/// Decoded leaf page.
+pub(crate) struct LeafNode<K, V> {
+    pub(crate) keys: Vec<K>,
+    pub(crate) vals: Vec<V>,
+    pub(crate) next: PageId,
+    pub(crate) prev: PageId,
+}
+
+/// Decoded branch page.
+pub(crate) struct BranchNode<K> {
+    pub(crate) keys: Vec<K>,
+    pub(crate) children: Vec<PageId>,
+}
+
+pub(crate) enum Node<K, V> {
+    Leaf(LeafNode<K, V>),
+    Branch(BranchNode<K>),
+}
+
+impl<K, V> LeafNode<K, V> {
+    pub(crate) fn empty() -> Self {
+        Self {
+            keys: Vec::new(),
+            vals: Vec::new(),
+            next: NO_PAGE,
+            prev: NO_PAGE,
+        }
+    }
+}
+
+impl PageLayout {
+    /// Binary search the keys of a leaf page without decoding it.
+    pub(crate) fn leaf_search<K: Pod + Ord>(&self, page: &[u8], key: &K) -> Result<usize, usize> {
+        let (mut lo, mut hi) = (0usize, page_len(page));
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.leaf_key::<K>(page, mid).cmp(key) {
+                core::cmp::Ordering::Less => lo = mid + 1,
+                core::cmp::Ordering::Greater => hi = mid,
+                core::cmp::Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(lo)
+    }
+
+    /// Index of the child of a branch page that covers `key`.
+    pub(crate) fn child_index<K: Pod + Ord>(&self, page: &[u8], key: &K) -> usize {
+        let (mut lo, mut hi) = (0usize, page_len(page));
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.branch_key::<K>(page, mid) <= *key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    pub(crate) fn decode<K: Pod, V: Pod>(&self, page: &[u8]) -> Option<Node<K, V>> {
+        let len = page_len(page);
+        match page_tag(page) {
+            TAG_LEAF if len <= self.leaf_cap as usize => Some(Node::Leaf(LeafNode {
+                keys: (0..len).map(|i| self.leaf_key(page, i)).collect(),
+                vals: (0..len).map(|i| self.leaf_val(page, i)).collect(),
+                next: leaf_next(page),
+                prev: leaf_prev(page),
+            })),
+            TAG_BRANCH if len <= self.branch_cap as usize => Some(Node::Branch(BranchNode {
+                keys: (0..len).map(|i| self.branch_key(page, i)).collect(),
+                children: (0..=len).map(|i| self.child(page, i)).collect(),
+            })),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn encode_leaf<K: Pod, V: Pod>(&self, leaf: &LeafNode<K, V>, page: &mut [u8]) {
+        debug_assert!(leaf.keys.len() <= self.leaf_cap as usize);
+        page.fill(0);
+        page[TAG_OFF] = TAG_LEAF;
+        pod_write(&mut page[LEN_OFF..], leaf.keys.len() as u16);
+        pod_write(&mut page[LEAF_NEXT_OFF..], leaf.next);
+        pod_write(&mut page[LEAF_PREV_OFF..], leaf.prev);
+        for (i, (k, v)) in leaf.keys.iter().zip(&leaf.vals).enumerate() {
+            pod_write(&mut page[self.leaf_keys_off + i * self.key_size..], *k);
+            pod_write(&mut page[self.leaf_vals_off + i * self.val_size..], *v);
+        }
+    }
+
+    pub(crate) fn encode_branch<K: Pod>(&self, branch: &BranchNode<K>, page: &mut [u8]) {
+        debug_assert!(branch.keys.len() <= self.branch_cap as usize);
+        debug_assert_eq!(branch.children.len(), branch.keys.len() + 1);
+        page.fill(0);
+        page[TAG_OFF] = TAG_BRANCH;
+        pod_write(&mut page[LEN_OFF..], branch.keys.len() as u16);
+        for (i, c) in branch.children.iter().enumerate() {
+            pod_write(&mut page[self.children_off + i * size_of::<PageId>()..], *c);
+        }
+        for (i, k) in branch.keys.iter().enumerate() {
+            pod_write(&mut page[self.branch_keys_off + i * self.key_size..], *k);
+        }
+    }
+}
+
+/// Patch the `prev` link of a leaf page in place.
+pub(crate) fn set_leaf_prev(page: &mut [u8], prev: PageId) {
+    pod_write(&mut page[LEAF_PREV_OFF..], prev);
+}
@@ -0,0 +1,279 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use std::collections::HashMap;
+use std::io;
+
+use super::storage::Storage;
+use super::wal::{Lsn, Wal};
+use super::PageId;
+
+struct Frame {
+    page: PageId,
+    data: Box<[u8]>,
+    dirty: bool,
+    referenced: bool,
+    /// Modified by the operation in progress; must not be written back yet.
+    pinned: bool,
+    /// Log record holding this frame's latest image.
+    lsn: Lsn,
+}
+
+/// Counters describing buffer pool behavior since it was created.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub writebacks: u64,
+}
+
+/// Bounded page cache with clock (second-chance) eviction.
+///
+/// Callers never hold references into a frame across pool calls; pages are
+/// either inspected through a closure or copied out, so any frame may be
+/// evicted by the next call.
+///
+/// With a write-ahead log attached, pages written during an operation stay
+/// pinned until [`BufferPool::commit`] logs their images, and a dirty frame is
+/// only written back once the log is durable up to its LSN. If an operation
+/// pins more pages than the pool holds, the pool grows temporarily.
+pub struct BufferPool<S> {
+    storage: S,
+    page_size: usize,
+    capacity: usize,
+    frames: Vec<Frame>,
+    table: HashMap<PageId, usize>,
+    hand: usize,
+    stats: PoolStats,
+    log: Option<Wal<S>>,
+    op_pages: Vec<PageId>,
+}
+
+impl<S: Storage> BufferPool<S> {
+    pub fn new(storage: S, page_size: usize, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            storage,
+            page_size,
+            capacity,
+            frames: Vec::with_capacity(capacity),
+            table: HashMap::with_capacity(capacity),
+            hand: 0,
+            stats: PoolStats::default(),
+            log: None,
+            op_pages: Vec::new(),
+        }
+    }
+
+    pub(crate) fn attach_log(&mut self, log: Wal<S>) {
+        self.log = Some(log);
+    }
+
+    pub(crate) fn log(&self) -> Option<&Wal<S>> {
+        self.log.as_ref()
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        self.stats
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn storage_mut(&mut self) -> &mut S {
+        &mut self.storage
+    }
+
+    /// Consume the pool without writing back dirty pages.
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    /// Inspect page `pid` in place.
+    pub fn with_page<R>(&mut self, pid: PageId, f: impl FnOnce(&[u8]) -> R) -> io::Result<R> {
+        let idx = self.frame_for(pid, true)?;
+        Ok(f(&self.frames[idx].data))
+    }
+
+    /// Copy page `pid` out of the pool.
+    pub fn read_page(&mut self, pid: PageId) -> io::Result<Vec<u8>> {
+        self.with_page(pid, |p| p.to_vec())
+    }
+
+    /// Replace the contents of page `pid`; the page is written back lazily.
+    pub fn write_page(&mut self, pid: PageId, data: &[u8]) -> io::Result<()> {
+        debug_assert_eq!(data.len(), self.page_size);
+        let idx = self.frame_for(pid, false)?;
+        let frame = &mut self.frames[idx];
+        frame.data.copy_from_slice(data);
+        frame.dirty = true;
+        if self.log.is_some() && !frame.pinned {
+            frame.pinned = true;
+            self.op_pages.push(pid);
+        }
+        Ok(())
+    }
+
+    /// Log the images of every page written since the last commit, followed
+    /// by a commit record, and unpin them. The records are buffered; they are
+    /// durable after [`BufferPool::sync_log`].
+    pub(crate) fn commit(&mut self) -> io::Result<()> {
+        let Some(log) = self.log.as_mut() else {
+            return Ok(());
+        };
+        if self.op_pages.is_empty() {
+            return Ok(());
+        }
+        for pid in self.op_pages.drain(..) {
+            let frame = &mut self.frames[self.table[&pid]];
+            frame.lsn = log.append_page(pid, &frame.data);
+            frame.pinned = false;
+        }
+        log.append_commit();
+        while self.frames.len() > self.capacity {
+            let victim = self.pick_victim().expect("no pinned frames after commit");
+            self.write_back(victim)?;
+            self.table.remove(&self.frames[victim].page);
+            self.frames.swap_remove(victim);
+            self.rebuild_table();
+            self.stats.evictions += 1;
+        }
+        Ok(())
+    }
+
+    /// Make every committed operation durable in the log.
+    pub(crate) fn sync_log(&mut self) -> io::Result<()> {
+        match self.log.as_mut() {
+            Some(log) => log.force(),
+            None => Ok(()),
+        }
+    }
+
+    /// Write every dirty page back to storage and sync it. With a log
+    /// attached this is a checkpoint: the log is truncated afterwards.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.commit()?;
+        for i in 0..self.frames.len() {
+            self.write_back(i)?;
+        }
+        self.storage.sync()?;
+        if let Some(log) = self.log.as_mut() {
+            log.truncate()?;
+        }
+        Ok(())
+    }
+
+    /// Forget all cached pages without writing them back.
+    pub fn discard(&mut self) {
+        self.frames.clear();
+        self.table.clear();
+        self.op_pages.clear();
+        self.hand = 0;
+    }
+
+    fn write_back(&mut self, idx: usize) -> io::Result<()> {
+        let frame = &mut self.frames[idx];
+        debug_assert!(!frame.pinned, "pinned frame written back");
+        if frame.dirty {
+            if let Some(log) = self.log.as_mut() {
+                if frame.lsn > log.durable_lsn() {
+                    log.force()?;
+                }
+            }
+            let offset = frame.page as u64 * self.page_size as u64;
+            self.storage.write_at(offset, &frame.data)?;
+            frame.dirty = false;
+            self.stats.writebacks += 1;
+        }
+        Ok(())
+    }
+
+    /// Locate or install the frame holding `pid`, evicting if the pool is full.
+    /// When `load` is false the caller is about to overwrite the whole page.
+    fn frame_for(&mut self, pid: PageId, load: bool) -> io::Result<usize> {
+        if let Some(&idx) = self.table.get(&pid) {
+            self.frames[idx].referenced = true;
+            self.stats.hits += 1;
+            return Ok(idx);
+        }
+        self.stats.misses += 1;
+
+        let victim = if self.frames.len() < self.capacity {
+            None
+        } else {
+            self.pick_victim()
+        };
+        let idx = match victim {
+            None => {
+                self.frames.push(Frame {
+                    page: pid,
+                    data: vec![0u8; self.page_size].into_boxed_slice(),
+                    dirty: false,
+                    referenced: true,
+                    pinned: false,
+                    lsn: 0,
+                });
+                self.frames.len() - 1
+            }
+            Some(victim) => {
+                self.write_back(victim)?;
+                self.table.remove(&self.frames[victim].page);
+                self.stats.evictions += 1;
+                let frame = &mut self.frames[victim];
+                frame.page = pid;
+                frame.referenced = true;
+                frame.lsn = 0;
+                victim
+            }
+        };
+
+        if load {
+            let offset = pid as u64 * self.page_size as u64;
+            if let Err(e) = self.storage.read_at(offset, &mut self.frames[idx].data) {
+                self.frames.swap_remove(idx);
+                self.rebuild_table();
+                return Err(e);
+            }
+        }
+        self.table.insert(pid, idx);
+        Ok(idx)
+    }
+
+    /// Clock sweep over unpinned frames; `None` if every frame is pinned.
+    fn pick_victim(&mut self) -> Option<usize> {
+        for _ in 0..2 * self.frames.len() {
+            let idx = self.hand;
+            self.hand = (self.hand + 1) % self.frames.len();
+            let frame = &mut self.frames[idx];
+            if frame.pinned {
+                continue;
+            }
+            if frame.referenced {
+                frame.referenced = false;
+            } else {
+                return Some(idx);
+            }
+        }
+        None
+    }
+
+    fn rebuild_table(&mut self) {
+        self.table.clear();
+        for (i, f) in self.frames.iter().enumerate() {
+            self.table.insert(f.page, i);
+        }
+        if self.hand >= self.frames.len() {
+            self.hand = 0;
+        }
+    }
+}
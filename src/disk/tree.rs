@@ -0,0 +1,1118 @@
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use core::mem::{size_of, ManuallyDrop};
+use core::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+
+use super::node::{
+    encode_free, free_next, page_tag, set_leaf_prev, BranchNode, LeafNode, Node, PageLayout,
+    TAG_BRANCH, TAG_FREE, TAG_LEAF,
+};
+use super::pool::{BufferPool, PoolStats};
+use super::storage::{FileStorage, Storage};
+use super::wal::{RecoveryStats, Wal};
+use super::{pod_read, pod_write, PageId, Pod, NO_PAGE};
+use crate::{BPlusTreeError, BTreeResult, InvariantViolation, LeafLink};
+
+const MAGIC: &[u8; 8] = b"BPTDISK1";
+const FORMAT_VERSION: u32 = 1;
+const META_PAGE: PageId = 0;
+const META_BYTES: usize = 48;
+
+/// Smallest supported page size; large enough for the meta page and a few entries.
+pub const MIN_PAGE_SIZE: usize = 64;
+
+/// In-memory copy of the meta page (page 0).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Meta {
+    page_size: u32,
+    key_size: u32,
+    val_size: u32,
+    root: PageId,
+    page_count: u32,
+    free_head: PageId,
+    len: u64,
+}
+
+impl Meta {
+    fn encode(&self, page: &mut [u8]) {
+        page.fill(0);
+        page[..8].copy_from_slice(MAGIC);
+        pod_write(&mut page[8..], FORMAT_VERSION);
+        pod_write(&mut page[12..], self.page_size);
+        pod_write(&mut page[16..], self.key_size);
+        pod_write(&mut page[20..], self.val_size);
+        pod_write(&mut page[24..], self.root);
+        pod_write(&mut page[28..], self.page_count);
+        pod_write(&mut page[32..], self.free_head);
+        pod_write(&mut page[40..], self.len);
+    }
+
+    fn decode(page: &[u8]) -> BTreeResult<Self> {
+        if page.len() < META_BYTES || &page[..8] != MAGIC {
+            return Err(BPlusTreeError::corrupted_tree("meta page", "bad magic"));
+        }
+        if pod_read::<u32>(&page[8..]) != FORMAT_VERSION {
+            return Err(BPlusTreeError::corrupted_tree(
+                "meta page",
+                "unsupported format version",
+            ));
+        }
+        Ok(Self {
+            page_size: pod_read(&page[12..]),
+            key_size: pod_read(&page[16..]),
+            val_size: pod_read(&page[20..]),
+            root: pod_read(&page[24..]),
+            page_count: pod_read(&page[28..]),
+            free_head: pod_read(&page[32..]),
+            len: pod_read(&page[40..]),
+        })
+    }
+}
+
+enum Ins<K, V> {
+    Done(Option<V>),
+    Split { sep: K, right: PageId },
+}
+
+enum Step<V> {
+    Child(PageId),
+    Found(Option<V>),
+    Corrupt,
+}
+
+/// B+ tree whose nodes are pages of a single file (or any other [`Storage`]).
+///
+/// Reads go through a bounded [`BufferPool`]; modified pages are written back
+/// on eviction and on [`DiskBPlusTree::flush`] (also attempted on drop).
+///
+/// Trees opened with a write-ahead log (`create_logged`, `open_logged`,
+/// `*_with_log`) log every page an operation modifies before any of them can
+/// reach the data file. Operations become durable in groups on
+/// [`DiskBPlusTree::sync`]; [`DiskBPlusTree::checkpoint`] writes all pages
+/// back and truncates the log. If a write fails mid-operation the tree refuses
+/// further use and must be reopened, which runs recovery.
+pub struct DiskBPlusTree<K, V, S: Storage = FileStorage> {
+    pool: RefCell<BufferPool<S>>,
+    layout: PageLayout,
+    meta: Meta,
+    poisoned: bool,
+    recovery: RecoveryStats,
+    _marker: PhantomData<(K, V)>,
+}
+
+/// Path of the write-ahead log kept next to the data file at `path`.
+pub fn wal_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut os = std::ffi::OsString::from(path.as_ref().as_os_str());
+    os.push(".wal");
+    PathBuf::from(os)
+}
+
+impl<K: Pod + Ord, V: Pod> DiskBPlusTree<K, V, FileStorage> {
+    /// Create a new tree in the file at `path`, replacing any existing file.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        page_size: usize,
+        pool_pages: usize,
+    ) -> BTreeResult<Self> {
+        Self::create_with_storage(FileStorage::create(path)?, page_size, pool_pages)
+    }
+
+    /// Open a tree previously created at `path`.
+    pub fn open<P: AsRef<Path>>(path: P, pool_pages: usize) -> BTreeResult<Self> {
+        Self::open_with_storage(FileStorage::open(path)?, pool_pages)
+    }
+
+    /// Like [`DiskBPlusTree::create`], with a write-ahead log at [`wal_path`].
+    pub fn create_logged<P: AsRef<Path>>(
+        path: P,
+        page_size: usize,
+        pool_pages: usize,
+    ) -> BTreeResult<Self> {
+        let log = FileStorage::create(wal_path(&path))?;
+        Self::create_with_log(FileStorage::create(path)?, log, page_size, pool_pages)
+    }
+
+    /// Open a logged tree, replaying its write-ahead log first.
+    pub fn open_logged<P: AsRef<Path>>(path: P, pool_pages: usize) -> BTreeResult<Self> {
+        let log = FileStorage::open(wal_path(&path))?;
+        Self::open_with_log(FileStorage::open(path)?, log, pool_pages)
+    }
+}
+
+impl<K: Pod + Ord, V: Pod, S: Storage> DiskBPlusTree<K, V, S> {
+    /// Initialize an empty tree on `storage` with `page_size`-byte pages.
+    pub fn create_with_storage(
+        mut storage: S,
+        page_size: usize,
+        pool_pages: usize,
+    ) -> BTreeResult<Self> {
+        let layout = Self::checked_layout(page_size)?;
+        storage.set_len(0)?;
+        let meta = Meta {
+            page_size: page_size as u32,
+            key_size: size_of::<K>() as u32,
+            val_size: size_of::<V>() as u32,
+            root: NO_PAGE,
+            page_count: 1,
+            free_head: NO_PAGE,
+            len: 0,
+        };
+        let tree = Self {
+            pool: RefCell::new(BufferPool::new(storage, page_size, pool_pages)),
+            layout,
+            meta,
+            poisoned: false,
+            recovery: RecoveryStats::default(),
+            _marker: PhantomData,
+        };
+        tree.write_meta()?;
+        tree.flush()?;
+        Ok(tree)
+    }
+
+    /// Initialize an empty tree on `storage` with a write-ahead log on `log`.
+    pub fn create_with_log(
+        storage: S,
+        log: S,
+        page_size: usize,
+        pool_pages: usize,
+    ) -> BTreeResult<Self> {
+        let tree = Self::create_with_storage(storage, page_size, pool_pages)?;
+        let wal = Wal::create(log, page_size)?;
+        tree.pool.borrow_mut().attach_log(wal);
+        Ok(tree)
+    }
+
+    /// Open a tree on `storage`, first replaying the committed records of
+    /// `log` and discarding any torn or uncommitted tail.
+    pub fn open_with_log(mut storage: S, mut log: S, pool_pages: usize) -> BTreeResult<Self> {
+        let (wal, recovery) = match Wal::read_page_size(&mut log)? {
+            Some(page_size) => Wal::recover(log, &mut storage, page_size)?,
+            None => {
+                let mut head = [0u8; META_BYTES];
+                storage.read_at(0, &mut head)?;
+                let page_size = Meta::decode(&head)?.page_size as usize;
+                (Wal::create(log, page_size)?, RecoveryStats::default())
+            }
+        };
+        let mut tree = Self::open_with_storage(storage, pool_pages)?;
+        tree.pool.get_mut().attach_log(wal);
+        tree.recovery = recovery;
+        Ok(tree)
+    }
+
+    /// Open an existing tree stored on `storage`.
+    pub fn open_with_storage(mut storage: S, pool_pages: usize) -> BTreeResult<Self> {
+        let mut head = [0u8; META_BYTES];
+        storage.read_at(0, &mut head)?;
+        let meta = Meta::decode(&head)?;
+        if meta.key_size as usize != size_of::<K>() || meta.val_size as usize != size_of::<V>() {
+            return Err(BPlusTreeError::invalid_state(
+                "open disk tree",
+                "key/value widths do not match the file",
+            ));
+        }
+        let layout = Self::checked_layout(meta.page_size as usize)?;
+        Ok(Self {
+            pool: RefCell::new(BufferPool::new(
+                storage,
+                meta.page_size as usize,
+                pool_pages,
+            )),
+            layout,
+            meta,
+            poisoned: false,
+            recovery: RecoveryStats::default(),
+            _marker: PhantomData,
+        })
+    }
+
+    fn checked_layout(page_size: usize) -> BTreeResult<PageLayout> {
+        if !(MIN_PAGE_SIZE..=u16::MAX as usize + 1).contains(&page_size) {
+            return Err(BPlusTreeError::InvalidCapacity(format!(
+                "page size {} outside {}..={}",
+                page_size,
+                MIN_PAGE_SIZE,
+                u16::MAX as usize + 1
+            )));
+        }
+        let layout = PageLayout::compute::<K, V>(page_size);
+        if layout.leaf_cap < 4 || layout.branch_cap < 4 {
+            return Err(BPlusTreeError::invalid_capacity(
+                layout.leaf_cap.min(layout.branch_cap) as usize,
+                4,
+            ));
+        }
+        Ok(layout)
+    }
+
+    pub fn layout(&self) -> &PageLayout {
+        &self.layout
+    }
+
+    pub fn len(&self) -> usize {
+        self.meta.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meta.len == 0
+    }
+
+    /// Number of page slots in the file, including the meta page and free pages.
+    pub fn page_count(&self) -> u32 {
+        self.meta.page_count
+    }
+
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.borrow().stats()
+    }
+
+    pub fn is_logged(&self) -> bool {
+        self.pool.borrow().log().is_some()
+    }
+
+    /// Bytes of write-ahead log accumulated since the last checkpoint.
+    pub fn log_len(&self) -> u64 {
+        self.pool.borrow().log().map_or(0, |l| l.len())
+    }
+
+    /// What recovery did when this tree was opened.
+    pub fn recovery_stats(&self) -> RecoveryStats {
+        self.recovery
+    }
+
+    fn check_usable(&self) -> BTreeResult<()> {
+        if self.poisoned {
+            return Err(BPlusTreeError::invalid_state(
+                "use disk tree",
+                "a previous write failed; reopen to recover",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run a mutating operation and commit its pages to the log as one group.
+    /// Any failure poisons the tree, since pages may be half updated.
+    fn mutate<R>(&mut self, op: impl FnOnce(&mut Self) -> BTreeResult<R>) -> BTreeResult<R> {
+        self.check_usable()?;
+        let result = op(self).and_then(|r| {
+            self.pool.get_mut().commit()?;
+            Ok(r)
+        });
+        if result.is_err() {
+            self.poisoned = true;
+        }
+        result
+    }
+
+    /// Make all completed operations durable. With a write-ahead log this is
+    /// a group commit that forces only the log; otherwise it is a full flush.
+    pub fn sync(&mut self) -> BTreeResult<()> {
+        self.check_usable()?;
+        if !self.is_logged() {
+            return self.flush();
+        }
+        let result = self.pool.get_mut().sync_log();
+        if result.is_err() {
+            self.poisoned = true;
+        }
+        Ok(result?)
+    }
+
+    /// Write all dirty pages back and sync the storage. With a write-ahead
+    /// log this is a checkpoint and the log is truncated afterwards.
+    pub fn flush(&self) -> BTreeResult<()> {
+        self.check_usable()?;
+        self.write_meta()?;
+        self.pool.borrow_mut().flush()?;
+        Ok(())
+    }
+
+    /// Alias for [`DiskBPlusTree::flush`], named for logged trees.
+    pub fn checkpoint(&mut self) -> BTreeResult<()> {
+        let result = self.flush();
+        if result.is_err() {
+            self.poisoned = true;
+        }
+        result
+    }
+
+    /// Flush and hand back the underlying storage.
+    pub fn into_storage(self) -> BTreeResult<S> {
+        self.flush()?;
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so the pool is moved out exactly once.
+        let pool = unsafe { core::ptr::read(&this.pool) };
+        Ok(pool.into_inner().into_storage())
+    }
+
+    // ---------- page helpers ----------
+
+    fn write_meta(&self) -> BTreeResult<()> {
+        let mut page = vec![0u8; self.layout.bytes];
+        self.meta.encode(&mut page);
+        self.pool.borrow_mut().write_page(META_PAGE, &page)?;
+        Ok(())
+    }
+
+    fn check_pid(&self, pid: PageId) -> BTreeResult<()> {
+        if pid == META_PAGE || pid >= self.meta.page_count {
+            return Err(BPlusTreeError::corrupted_tree(
+                "page link",
+                &format!("page id {} out of range", pid),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fail once a walk has followed `steps` links. A well-formed tree
+    /// never needs as many as it has pages, so reaching that many means a
+    /// child or sibling link leads back to a page already visited.
+    fn check_steps(&self, steps: u32) -> BTreeResult<()> {
+        if steps >= self.meta.page_count {
+            return Err(BPlusTreeError::corrupted_tree(
+                "page link",
+                "walk exceeds the page count; links form a cycle",
+            ));
+        }
+        Ok(())
+    }
+
+    fn load(&self, pid: PageId) -> BTreeResult<Node<K, V>> {
+        self.check_pid(pid)?;
+        let layout = self.layout;
+        self.pool
+            .borrow_mut()
+            .with_page(pid, |p| layout.decode::<K, V>(p))?
+            .ok_or_else(|| {
+                BPlusTreeError::corrupted_tree("node page", &format!("page {} is not a node", pid))
+            })
+    }
+
+    fn load_leaf(&self, pid: PageId) -> BTreeResult<LeafNode<K, V>> {
+        match self.load(pid)? {
+            Node::Leaf(l) => Ok(l),
+            Node::Branch(_) => Err(BPlusTreeError::corrupted_tree(
+                "leaf page",
+                "expected leaf, found branch",
+            )),
+        }
+    }
+
+    fn load_branch(&self, pid: PageId) -> BTreeResult<BranchNode<K>> {
+        match self.load(pid)? {
+            Node::Branch(b) => Ok(b),
+            Node::Leaf(_) => Err(BPlusTreeError::corrupted_tree(
+                "branch page",
+                "expected branch, found leaf",
+            )),
+        }
+    }
+
+    fn store_leaf(&self, pid: PageId, leaf: &LeafNode<K, V>) -> BTreeResult<()> {
+        let mut page = vec![0u8; self.layout.bytes];
+        self.layout.encode_leaf(leaf, &mut page);
+        self.pool.borrow_mut().write_page(pid, &page)?;
+        Ok(())
+    }
+
+    fn store_branch(&self, pid: PageId, branch: &BranchNode<K>) -> BTreeResult<()> {
+        let mut page = vec![0u8; self.layout.bytes];
+        self.layout.encode_branch(branch, &mut page);
+        self.pool.borrow_mut().write_page(pid, &page)?;
+        Ok(())
+    }
+
+    fn set_prev(&self, pid: PageId, prev: PageId) -> BTreeResult<()> {
+        if pid == NO_PAGE {
+            return Ok(());
+        }
+        self.check_pid(pid)?;
+        let mut pool = self.pool.borrow_mut();
+        let mut page = pool.read_page(pid)?;
+        set_leaf_prev(&mut page, prev);
+        pool.write_page(pid, &page)?;
+        Ok(())
+    }
+
+    fn alloc_page(&mut self) -> BTreeResult<PageId> {
+        if self.meta.free_head != NO_PAGE {
+            let pid = self.meta.free_head;
+            self.check_pid(pid)?;
+            let (tag, next) = self
+                .pool
+                .borrow_mut()
+                .with_page(pid, |p| (page_tag(p), free_next(p)))?;
+            if tag != TAG_FREE {
+                return Err(BPlusTreeError::corrupted_tree(
+                    "free list",
+                    "free-list page is in use",
+                ));
+            }
+            self.meta.free_head = next;
+            return Ok(pid);
+        }
+        if self.meta.page_count == NO_PAGE {
+            return Err(BPlusTreeError::allocation_error("page", "file is full"));
+        }
+        let pid = self.meta.page_count;
+        self.meta.page_count += 1;
+        Ok(pid)
+    }
+
+    fn free_page(&mut self, pid: PageId) -> BTreeResult<()> {
+        let mut page = vec![0u8; self.layout.bytes];
+        encode_free(&mut page, self.meta.free_head);
+        self.pool.borrow_mut().write_page(pid, &page)?;
+        self.meta.free_head = pid;
+        Ok(())
+    }
+
+    // ---------- lookups ----------
+
+    pub fn get(&self, key: &K) -> BTreeResult<Option<V>> {
+        let mut pid = self.meta.root;
+        if pid == NO_PAGE {
+            return Ok(None);
+        }
+        let layout = self.layout;
+        for steps in 0.. {
+            self.check_steps(steps)?;
+            self.check_pid(pid)?;
+            let step = self
+                .pool
+                .borrow_mut()
+                .with_page(pid, |p| match page_tag(p) {
+                    TAG_LEAF => Step::Found(
+                        layout
+                            .leaf_search(p, key)
+                            .ok()
+                            .map(|i| layout.leaf_val::<V>(p, i)),
+                    ),
+                    TAG_BRANCH => Step::Child(layout.child(p, layout.child_index(p, key))),
+                    _ => Step::Corrupt,
+                })?;
+            match step {
+                Step::Child(c) => pid = c,
+                Step::Found(v) => return Ok(v),
+                Step::Corrupt => {
+                    return Err(BPlusTreeError::corrupted_tree(
+                        "node page",
+                        &format!("page {} is not a node", pid),
+                    ))
+                }
+            }
+        }
+        unreachable!("check_steps bounds the descent")
+    }
+
+    pub fn contains_key(&self, key: &K) -> BTreeResult<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Leaf page that would contain `key` (or the leftmost leaf for `None`).
+    fn find_leaf(&self, key: Option<&K>) -> BTreeResult<PageId> {
+        let mut pid = self.meta.root;
+        let mut steps = 0;
+        while pid != NO_PAGE {
+            self.check_steps(steps)?;
+            steps += 1;
+            self.check_pid(pid)?;
+            let layout = self.layout;
+            let next = self
+                .pool
+                .borrow_mut()
+                .with_page(pid, |p| match page_tag(p) {
+                    TAG_LEAF => Step::Found(None),
+                    TAG_BRANCH => Step::Child(match key {
+                        Some(k) => layout.child(p, layout.child_index(p, k)),
+                        None => layout.child(p, 0),
+                    }),
+                    _ => Step::<V>::Corrupt,
+                })?;
+            match next {
+                Step::Found(_) => return Ok(pid),
+                Step::Child(c) => pid = c,
+                Step::Corrupt => {
+                    return Err(BPlusTreeError::corrupted_tree(
+                        "node page",
+                        &format!("page {} is not a node", pid),
+                    ))
+                }
+            }
+        }
+        Ok(NO_PAGE)
+    }
+
+    /// Iterate entries whose keys fall in `r`, in key order.
+    pub fn range<R: RangeBounds<K>>(&self, r: R) -> DiskRange<'_, K, V, S> {
+        let start = match r.start_bound() {
+            Bound::Included(k) => Bound::Included(*k),
+            Bound::Excluded(k) => Bound::Excluded(*k),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match r.end_bound() {
+            Bound::Included(k) => Bound::Included(*k),
+            Bound::Excluded(k) => Bound::Excluded(*k),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        DiskRange {
+            tree: self,
+            start: Some(start),
+            end,
+            buf: Vec::new(),
+            pos: 0,
+            next_leaf: NO_PAGE,
+            leaves: 0,
+            done: false,
+        }
+    }
+
+    pub fn iter(&self) -> DiskRange<'_, K, V, S> {
+        self.range(..)
+    }
+
+    // ---------- insert ----------
+
+    /// Insert `key`, returning the previous value if the key was present.
+    pub fn insert(&mut self, key: K, value: V) -> BTreeResult<Option<V>> {
+        self.mutate(|t| t.insert_inner(key, value))
+    }
+
+    fn insert_inner(&mut self, key: K, value: V) -> BTreeResult<Option<V>> {
+        if self.meta.root == NO_PAGE {
+            let pid = self.alloc_page()?;
+            self.store_leaf(pid, &LeafNode::empty())?;
+            self.meta.root = pid;
+        }
+        let root = self.meta.root;
+        let old = match self.insert_rec(root, key, value, 0)? {
+            Ins::Done(old) => old,
+            Ins::Split { sep, right } => {
+                let new_root = self.alloc_page()?;
+                self.store_branch(
+                    new_root,
+                    &BranchNode {
+                        keys: vec![sep],
+                        children: vec![root, right],
+                    },
+                )?;
+                self.meta.root = new_root;
+                None
+            }
+        };
+        if old.is_none() {
+            self.meta.len += 1;
+        }
+        self.write_meta()?;
+        Ok(old)
+    }
+
+    fn insert_rec(&mut self, pid: PageId, key: K, value: V, depth: u32) -> BTreeResult<Ins<K, V>> {
+        self.check_steps(depth)?;
+        match self.load(pid)? {
+            Node::Leaf(mut leaf) => match leaf.keys.binary_search(&key) {
+                Ok(i) => {
+                    let old = core::mem::replace(&mut leaf.vals[i], value);
+                    self.store_leaf(pid, &leaf)?;
+                    Ok(Ins::Done(Some(old)))
+                }
+                Err(i) => {
+                    leaf.keys.insert(i, key);
+                    leaf.vals.insert(i, value);
+                    if leaf.keys.len() <= self.layout.leaf_cap as usize {
+                        self.store_leaf(pid, &leaf)?;
+                        return Ok(Ins::Done(None));
+                    }
+                    let mid = leaf.keys.len() / 2;
+                    let right_pid = self.alloc_page()?;
+                    let right = LeafNode {
+                        keys: leaf.keys.split_off(mid),
+                        vals: leaf.vals.split_off(mid),
+                        next: leaf.next,
+                        prev: pid,
+                    };
+                    self.set_prev(leaf.next, right_pid)?;
+                    leaf.next = right_pid;
+                    self.store_leaf(right_pid, &right)?;
+                    self.store_leaf(pid, &leaf)?;
+                    Ok(Ins::Split {
+                        sep: right.keys[0],
+                        right: right_pid,
+                    })
+                }
+            },
+            Node::Branch(mut branch) => {
+                let idx = branch.keys.partition_point(|k| *k <= key);
+                let child = branch.children[idx];
+                match self.insert_rec(child, key, value, depth + 1)? {
+                    done @ Ins::Done(_) => Ok(done),
+                    Ins::Split { sep, right } => {
+                        branch.keys.insert(idx, sep);
+                        branch.children.insert(idx + 1, right);
+                        if branch.keys.len() <= self.layout.branch_cap as usize {
+                            self.store_branch(pid, &branch)?;
+                            return Ok(Ins::Done(None));
+                        }
+                        let mid = branch.keys.len() / 2;
+                        let right_node = BranchNode {
+                            keys: branch.keys.split_off(mid + 1),
+                            children: branch.children.split_off(mid + 1),
+                        };
+                        let promote = branch.keys.pop().expect("promoted separator");
+                        let right_pid = self.alloc_page()?;
+                        self.store_branch(right_pid, &right_node)?;
+                        self.store_branch(pid, &branch)?;
+                        Ok(Ins::Split {
+                            sep: promote,
+                            right: right_pid,
+                        })
+                    }
+                }
+            }
+        }
+    }
+
+    // ---------- remove ----------
+
+    /// Remove `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: &K) -> BTreeResult<Option<V>> {
+        self.mutate(|t| t.remove_inner(key))
+    }
+
+    fn remove_inner(&mut self, key: &K) -> BTreeResult<Option<V>> {
+        let root = self.meta.root;
+        if root == NO_PAGE {
+            return Ok(None);
+        }
+        let removed = self.remove_rec(root, key, 0)?;
+        if removed.is_some() {
+            self.meta.len -= 1;
+            match self.load(root)? {
+                Node::Branch(b) if b.keys.is_empty() => {
+                    self.meta.root = b.children[0];
+                    self.free_page(root)?;
+                }
+                Node::Leaf(l) if l.keys.is_empty() => {
+                    self.meta.root = NO_PAGE;
+                    self.free_page(root)?;
+                }
+                _ => {}
+            }
+            self.write_meta()?;
+        }
+        Ok(removed)
+    }
+
+    fn remove_rec(&mut self, pid: PageId, key: &K, depth: u32) -> BTreeResult<Option<V>> {
+        self.check_steps(depth)?;
+        match self.load(pid)? {
+            Node::Leaf(mut leaf) => match leaf.keys.binary_search(key) {
+                Ok(i) => {
+                    leaf.keys.remove(i);
+                    let v = leaf.vals.remove(i);
+                    self.store_leaf(pid, &leaf)?;
+                    Ok(Some(v))
+                }
+                Err(_) => Ok(None),
+            },
+            Node::Branch(branch) => {
+                let idx = branch.keys.partition_point(|k| k <= key);
+                let removed = self.remove_rec(branch.children[idx], key, depth + 1)?;
+                if removed.is_some() {
+                    self.fix_child(pid, idx)?;
+                }
+                Ok(removed)
+            }
+        }
+    }
+
+    /// Restore minimum occupancy of child `idx` of branch `pid` by borrowing
+    /// from a sibling or merging with one.
+    fn fix_child(&mut self, pid: PageId, idx: usize) -> BTreeResult<()> {
+        let mut parent = self.load_branch(pid)?;
+        let child_pid = parent.children[idx];
+        let left_pid = if idx > 0 {
+            Some(parent.children[idx - 1])
+        } else {
+            None
+        };
+        let right_pid = parent.children.get(idx + 1).copied();
+
+        match self.load(child_pid)? {
+            Node::Leaf(mut child) => {
+                let min = self.layout.min_leaf_len();
+                if child.keys.len() >= min {
+                    return Ok(());
+                }
+                if let Some(lp) = left_pid {
+                    let mut left = self.load_leaf(lp)?;
+                    if left.keys.len() > min {
+                        child.keys.insert(0, left.keys.pop().expect("left key"));
+                        child.vals.insert(0, left.vals.pop().expect("left val"));
+                        parent.keys[idx - 1] = child.keys[0];
+                        self.store_leaf(lp, &left)?;
+                        self.store_leaf(child_pid, &child)?;
+                        return self.store_branch(pid, &parent);
+                    }
+                }
+                if let Some(rp) = right_pid {
+                    let mut right = self.load_leaf(rp)?;
+                    if right.keys.len() > min {
+                        child.keys.push(right.keys.remove(0));
+                        child.vals.push(right.vals.remove(0));
+                        parent.keys[idx] = right.keys[0];
+                        self.store_leaf(rp, &right)?;
+                        self.store_leaf(child_pid, &child)?;
+                        return self.store_branch(pid, &parent);
+                    }
+                }
+                // Merge the right node of the pair into the left one.
+                let (keep_pid, mut keep, gone_pid, gone, sep_idx) = match left_pid {
+                    Some(lp) => (lp, self.load_leaf(lp)?, child_pid, child, idx - 1),
+                    None => {
+                        let rp = right_pid.expect("non-root branch has two children");
+                        (child_pid, child, rp, self.load_leaf(rp)?, idx)
+                    }
+                };
+                keep.keys.extend(gone.keys);
+                keep.vals.extend(gone.vals);
+                keep.next = gone.next;
+                self.set_prev(gone.next, keep_pid)?;
+                self.store_leaf(keep_pid, &keep)?;
+                self.free_page(gone_pid)?;
+                parent.keys.remove(sep_idx);
+                parent.children.remove(sep_idx + 1);
+                self.store_branch(pid, &parent)
+            }
+            Node::Branch(mut child) => {
+                let min = self.layout.min_branch_len();
+                if child.keys.len() >= min {
+                    return Ok(());
+                }
+                if let Some(lp) = left_pid {
+                    let mut left = self.load_branch(lp)?;
+                    if left.keys.len() > min {
+                        child.keys.insert(0, parent.keys[idx - 1]);
+                        child
+                            .children
+                            .insert(0, left.children.pop().expect("left child"));
+                        parent.keys[idx - 1] = left.keys.pop().expect("left key");
+                        self.store_branch(lp, &left)?;
+                        self.store_branch(child_pid, &child)?;
+                        return self.store_branch(pid, &parent);
+                    }
+                }
+                if let Some(rp) = right_pid {
+                    let mut right = self.load_branch(rp)?;
+                    if right.keys.len() > min {
+                        child.keys.push(parent.keys[idx]);
+                        child.children.push(right.children.remove(0));
+                        parent.keys[idx] = right.keys.remove(0);
+                        self.store_branch(rp, &right)?;
+                        self.store_branch(child_pid, &child)?;
+                        return self.store_branch(pid, &parent);
+                    }
+                }
+                let (keep_pid, mut keep, gone_pid, gone, sep_idx) = match left_pid {
+                    Some(lp) => (lp, self.load_branch(lp)?, child_pid, child, idx - 1),
+                    None => {
+                        let rp = right_pid.expect("non-root branch has two children");
+                        (child_pid, child, rp, self.load_branch(rp)?, idx)
+                    }
+                };
+                keep.keys.push(parent.keys.remove(sep_idx));
+                keep.keys.extend(gone.keys);
+                keep.children.extend(gone.children);
+                parent.children.remove(sep_idx + 1);
+                self.store_branch(keep_pid, &keep)?;
+                self.free_page(gone_pid)?;
+                self.store_branch(pid, &parent)
+            }
+        }
+    }
+
+    // ---------- validation ----------
+
+    pub fn check_invariants(&self) -> bool {
+        self.check_invariants_detailed().is_ok()
+    }
+
+    /// Walk every page reachable from the root and verify ordering, bounds,
+    /// occupancy, uniform leaf depth, sibling links and the stored length.
+    /// A page that cannot be read or decoded is reported as
+    /// [`InvariantViolation::UnreadableNode`].
+    pub fn check_invariants_detailed(&self) -> Result<(), InvariantViolation> {
+        if self.meta.root == NO_PAGE {
+            return if self.meta.len == 0 {
+                Ok(())
+            } else {
+                Err(InvariantViolation::CountMismatch {
+                    counted: 0,
+                    len: self.meta.len as usize,
+                })
+            };
+        }
+        let mut state = DiskValidation {
+            path: Vec::new(),
+            leaf_depth: None,
+            prev_leaf: NO_PAGE,
+            prev_leaf_path: Vec::new(),
+            expected_next: None,
+            total: 0,
+        };
+        self.validate_page(self.meta.root, None, None, 0, true, &mut state)?;
+        if state.expected_next != Some(NO_PAGE) {
+            return Err(InvariantViolation::BrokenLink {
+                path: state.prev_leaf_path,
+                link: LeafLink::Next,
+            });
+        }
+        if state.total != self.meta.len {
+            return Err(InvariantViolation::CountMismatch {
+                counted: state.total as usize,
+                len: self.meta.len as usize,
+            });
+        }
+        Ok(())
+    }
+
+    fn validate_page(
+        &self,
+        pid: PageId,
+        lower: Option<K>,
+        upper: Option<K>,
+        depth: usize,
+        is_root: bool,
+        state: &mut DiskValidation,
+    ) -> Result<(), InvariantViolation> {
+        if depth >= self.meta.page_count as usize {
+            return Err(InvariantViolation::CycleDetected {
+                path: state.path.clone(),
+            });
+        }
+        let path = || state.path.clone();
+        let node = self
+            .load(pid)
+            .map_err(|_| InvariantViolation::UnreadableNode { path: path() })?;
+        let keys = match &node {
+            Node::Leaf(leaf) => &leaf.keys,
+            Node::Branch(branch) => &branch.keys,
+        };
+        let len = keys.len();
+        if let Some(i) = keys.windows(2).position(|w| w[0] >= w[1]) {
+            return Err(InvariantViolation::UnsortedKeys {
+                path: path(),
+                index: i + 1,
+            });
+        }
+        if let Some(lo) = lower {
+            if let Some(i) = keys.iter().position(|k| *k < lo) {
+                return Err(InvariantViolation::SeparatorBound {
+                    path: path(),
+                    index: i,
+                });
+            }
+        }
+        if let Some(hi) = upper {
+            if let Some(i) = keys.iter().position(|k| *k >= hi) {
+                return Err(InvariantViolation::SeparatorBound {
+                    path: path(),
+                    index: i,
+                });
+            }
+        }
+        match node {
+            Node::Leaf(leaf) => {
+                let min = self.layout.min_leaf_len();
+                if !is_root && len < min {
+                    return Err(InvariantViolation::Underfull {
+                        path: path(),
+                        len,
+                        min,
+                    });
+                }
+                match state.leaf_depth {
+                    None => state.leaf_depth = Some(depth),
+                    Some(expected) if expected != depth => {
+                        return Err(InvariantViolation::UnequalLeafDepth {
+                            path: path(),
+                            depth,
+                            expected,
+                        })
+                    }
+                    _ => {}
+                }
+                if leaf.prev != state.prev_leaf {
+                    return Err(InvariantViolation::BrokenLink {
+                        path: path(),
+                        link: LeafLink::Prev,
+                    });
+                }
+                if let Some(expected) = state.expected_next {
+                    if expected != pid {
+                        return Err(InvariantViolation::BrokenLink {
+                            path: state.prev_leaf_path.clone(),
+                            link: LeafLink::Next,
+                        });
+                    }
+                }
+                state.prev_leaf = pid;
+                state.prev_leaf_path.clone_from(&state.path);
+                state.expected_next = Some(leaf.next);
+                state.total += len as u64;
+                Ok(())
+            }
+            Node::Branch(branch) => {
+                if len == 0 {
+                    return Err(InvariantViolation::EmptyNode { path: path() });
+                }
+                let min = self.layout.min_branch_len();
+                if !is_root && len < min {
+                    return Err(InvariantViolation::Underfull {
+                        path: path(),
+                        len,
+                        min,
+                    });
+                }
+                for (i, &child) in branch.children.iter().enumerate() {
+                    let lo = if i == 0 {
+                        lower
+                    } else {
+                        Some(branch.keys[i - 1])
+                    };
+                    let hi = if i == len {
+                        upper
+                    } else {
+                        Some(branch.keys[i])
+                    };
+                    state.path.push(i);
+                    self.validate_page(child, lo, hi, depth + 1, false, state)?;
+                    state.path.pop();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+struct DiskValidation {
+    /// Child indices from the root to the page being checked.
+    path: Vec<usize>,
+    leaf_depth: Option<usize>,
+    prev_leaf: PageId,
+    prev_leaf_path: Vec<usize>,
+    expected_next: Option<PageId>,
+    total: u64,
+}
+
+impl<K, V, S: Storage> Drop for DiskBPlusTree<K, V, S> {
+    fn drop(&mut self) {
+        if self.poisoned {
+            return;
+        }
+        let mut page = vec![0u8; self.layout.bytes];
+        self.meta.encode(&mut page);
+        let pool = self.pool.get_mut();
+        let _ = pool.write_page(META_PAGE, &page);
+        let _ = pool.flush();
+    }
+}
+
+/// Iterator over a key range of a [`DiskBPlusTree`].
+///
+/// Each leaf is decoded once when the iterator reaches it, and the next leaf
+/// is found through the sibling link, so a scan costs one descent plus one
+/// page fetch per leaf.
+pub struct DiskRange<'a, K, V, S: Storage> {
+    tree: &'a DiskBPlusTree<K, V, S>,
+    start: Option<Bound<K>>,
+    end: Bound<K>,
+    buf: Vec<(K, V)>,
+    pos: usize,
+    next_leaf: PageId,
+    /// Leaves loaded so far, bounded by the page count.
+    leaves: u32,
+    done: bool,
+}
+
+impl<K: Pod + Ord, V: Pod, S: Storage> DiskRange<'_, K, V, S> {
+    fn load_leaf(&mut self, pid: PageId) -> BTreeResult<()> {
+        self.tree.check_steps(self.leaves)?;
+        self.leaves += 1;
+        let leaf = self.tree.load_leaf(pid)?;
+        self.next_leaf = leaf.next;
+        self.buf.clear();
+        self.buf.extend(leaf.keys.into_iter().zip(leaf.vals));
+        self.pos = 0;
+        Ok(())
+    }
+
+    fn seek_start(&mut self, start: Bound<K>) -> BTreeResult<()> {
+        let key = match &start {
+            Bound::Included(k) | Bound::Excluded(k) => Some(k),
+            Bound::Unbounded => None,
+        };
+        let leaf = self.tree.find_leaf(key)?;
+        if leaf == NO_PAGE {
+            self.done = true;
+            return Ok(());
+        }
+        self.load_leaf(leaf)?;
+        self.pos = match start {
+            Bound::Included(k) => self.buf.partition_point(|(x, _)| *x < k),
+            Bound::Excluded(k) => self.buf.partition_point(|(x, _)| *x <= k),
+            Bound::Unbounded => 0,
+        };
+        Ok(())
+    }
+
+    fn step(&mut self) -> BTreeResult<Option<(K, V)>> {
+        if let Some(start) = self.start.take() {
+            self.seek_start(start)?;
+        }
+        loop {
+            if self.done {
+                return Ok(None);
+            }
+            if let Some(&(k, v)) = self.buf.get(self.pos) {
+                let in_range = match &self.end {
+                    Bound::Included(e) => k <= *e,
+                    Bound::Excluded(e) => k < *e,
+                    Bound::Unbounded => true,
+                };
+                if !in_range {
+                    self.done = true;
+                    return Ok(None);
+                }
+                self.pos += 1;
+                return Ok(Some((k, v)));
+            }
+            if self.next_leaf == NO_PAGE {
+                self.done = true;
+                return Ok(None);
+            }
+            let next = self.next_leaf;
+            self.load_leaf(next)?;
+        }
+    }
+}
+
+impl<K: Pod + Ord, V: Pod, S: Storage> Iterator for DiskRange<'_, K, V, S> {
+    type Item = BTreeResult<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step() {
+            Ok(Some(kv)) => Some(Ok(kv)),
+            Ok(None) => None,
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use std::io;
+
+use super::storage::Storage;
+use super::{pod_read, pod_write, PageId};
+use crate::checksum::Crc32;
+
+const LOG_MAGIC: &[u8; 8] = b"BPTWAL01";
+const LOG_HDR: usize = 16;
+const REC_HDR: usize = 24;
+
+const REC_PAGE: u8 = 1;
+const REC_COMMIT: u8 = 2;
+
+/// Log sequence number; 0 means "never logged".
+pub(crate) type Lsn = u64;
+
+/// Outcome of replaying a log during open.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecoveryStats {
+    /// Committed operations whose page images were written to the data file.
+    pub replayed_commits: u64,
+    /// Records after the last commit that were dropped as torn or uncommitted.
+    pub discarded_records: u64,
+}
+
+/// Redo log of full page after-images.
+///
+/// Log file: `[magic 8][page_size u32][reserved u32]` followed by records
+/// `[kind u8][pad 3][pid u32][lsn u64][crc u32][len u32][payload; len]`.
+/// A `PAGE` record carries one page image; a `COMMIT` record ends the group
+/// of page records written by one tree operation. The CRC covers the record
+/// header (minus the CRC itself) and the payload, so a torn append is detected
+/// and everything from it onward is discarded.
+///
+/// Records are buffered in memory and only become durable on [`Wal::force`].
+pub(crate) struct Wal<S> {
+    storage: S,
+    page_size: usize,
+    buf: Vec<u8>,
+    end: u64,
+    next_lsn: Lsn,
+    buffered_lsn: Lsn,
+    durable_lsn: Lsn,
+}
+
+impl<S: Storage> Wal<S> {
+    /// Start an empty log on `storage`, discarding any previous contents.
+    pub(crate) fn create(mut storage: S, page_size: usize) -> io::Result<Self> {
+        storage.set_len(0)?;
+        let mut hdr = [0u8; LOG_HDR];
+        hdr[..8].copy_from_slice(LOG_MAGIC);
+        pod_write(&mut hdr[8..], page_size as u32);
+        storage.write_at(0, &hdr)?;
+        storage.sync()?;
+        Ok(Self {
+            storage,
+            page_size,
+            buf: Vec::new(),
+            end: LOG_HDR as u64,
+            next_lsn: 1,
+            buffered_lsn: 0,
+            durable_lsn: 0,
+        })
+    }
+
+    /// Page size recorded in the log header, or `None` for an empty log.
+    pub(crate) fn read_page_size(storage: &mut S) -> io::Result<Option<usize>> {
+        if storage.len()? < LOG_HDR as u64 {
+            return Ok(None);
+        }
+        let mut hdr = [0u8; LOG_HDR];
+        storage.read_at(0, &mut hdr)?;
+        if &hdr[..8] != LOG_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "write-ahead log has a bad header",
+            ));
+        }
+        Ok(Some(pod_read::<u32>(&hdr[8..]) as usize))
+    }
+
+    /// Replay every committed group in the log onto `data`, sync it, and
+    /// return a fresh empty log.
+    pub(crate) fn recover(
+        mut storage: S,
+        data: &mut S,
+        page_size: usize,
+    ) -> io::Result<(Self, RecoveryStats)> {
+        let mut stats = RecoveryStats::default();
+        let log_len = storage.len()?;
+        let mut off = LOG_HDR as u64;
+        let mut last_lsn = 0;
+        let mut pending: Vec<(PageId, Vec<u8>)> = Vec::new();
+        let mut hdr = [0u8; REC_HDR];
+
+        while off + REC_HDR as u64 <= log_len {
+            storage.read_at(off, &mut hdr)?;
+            let kind = hdr[0];
+            let pid: PageId = pod_read(&hdr[4..]);
+            let lsn: Lsn = pod_read(&hdr[8..]);
+            let crc: u32 = pod_read(&hdr[16..]);
+            let len = pod_read::<u32>(&hdr[20..]) as usize;
+            let well_formed = lsn > last_lsn
+                && match kind {
+                    REC_PAGE => len == page_size,
+                    REC_COMMIT => len == 0,
+                    _ => false,
+                };
+            if !well_formed || off + (REC_HDR + len) as u64 > log_len {
+                break;
+            }
+            let mut payload = vec![0u8; len];
+            storage.read_at(off + REC_HDR as u64, &mut payload)?;
+            if record_crc(&hdr, &payload) != crc {
+                break;
+            }
+            off += (REC_HDR + len) as u64;
+            last_lsn = lsn;
+            if kind == REC_PAGE {
+                pending.push((pid, payload));
+            } else {
+                for (pid, image) in pending.drain(..) {
+                    data.write_at(pid as u64 * page_size as u64, &image)?;
+                }
+                stats.replayed_commits += 1;
+            }
+        }
+        stats.discarded_records = pending.len() as u64 + u64::from(off < log_len);
+        data.sync()?;
+        let mut wal = Self::create(storage, page_size)?;
+        wal.next_lsn = last_lsn + 1;
+        Ok((wal, stats))
+    }
+
+    pub(crate) fn durable_lsn(&self) -> Lsn {
+        self.durable_lsn
+    }
+
+    /// Bytes of log beyond the header, including records not yet forced.
+    pub(crate) fn len(&self) -> u64 {
+        self.end - LOG_HDR as u64 + self.buf.len() as u64
+    }
+
+    pub(crate) fn append_page(&mut self, pid: PageId, image: &[u8]) -> Lsn {
+        debug_assert_eq!(image.len(), self.page_size);
+        self.append(REC_PAGE, pid, image)
+    }
+
+    pub(crate) fn append_commit(&mut self) -> Lsn {
+        self.append(REC_COMMIT, 0, &[])
+    }
+
+    fn append(&mut self, kind: u8, pid: PageId, payload: &[u8]) -> Lsn {
+        let lsn = self.next_lsn;
+        self.next_lsn += 1;
+        let mut hdr = [0u8; REC_HDR];
+        hdr[0] = kind;
+        pod_write(&mut hdr[4..], pid);
+        pod_write(&mut hdr[8..], lsn);
+        pod_write(&mut hdr[20..], payload.len() as u32);
+        let crc = record_crc(&hdr, payload);
+        pod_write(&mut hdr[16..], crc);
+        self.buf.extend_from_slice(&hdr);
+        self.buf.extend_from_slice(payload);
+        self.buffered_lsn = lsn;
+        lsn
+    }
+
+    /// Write all buffered records and make them durable (group commit).
+    pub(crate) fn force(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        self.storage.write_at(self.end, &self.buf)?;
+        self.storage.sync()?;
+        self.end += self.buf.len() as u64;
+        self.buf.clear();
+        self.durable_lsn = self.buffered_lsn;
+        Ok(())
+    }
+
+    /// Drop all records; only valid once every logged page is in the data file.
+    pub(crate) fn truncate(&mut self) -> io::Result<()> {
+        self.storage.set_len(LOG_HDR as u64)?;
+        self.storage.sync()?;
+        self.buf.clear();
+        self.end = LOG_HDR as u64;
+        self.durable_lsn = self.buffered_lsn;
+        Ok(())
+    }
+}
+
+fn record_crc(hdr: &[u8; REC_HDR], payload: &[u8]) -> u32 {
+    let mut h = Crc32::new();
+    h.update(&hdr[..16]);
+    h.update(&hdr[20..]);
+    h.update(payload);
+    h.finish()
+}
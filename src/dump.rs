@@ -0,0 +1,291 @@
+//! Human-readable views of a tree's structure, for debugging.
+//!
+//! [`BPlusTreeMap::to_dot`] writes a Graphviz graph: one record per node,
+//! solid edges from branch separators to children and dashed edges along the
+//! leaf chain. [`BPlusTreeMap::dump_ascii`] lists the nodes level by level,
+//! each under its path, the child indices from the root as in
+//! [`crate::InvariantViolation`]. Both follow the raw pointers as they are,
+//! so a corrupted tree is drawn rather than rejected; a node reached twice
+//! is shown once and not descended into again.
+
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Write};
+use core::ptr::NonNull;
+
+use crate::layout;
+use crate::{BPlusTreeMap, Comparator, NodeHdr, NodeTag};
+
+/// How [`BPlusTreeMap::to_dot_with`] and [`BPlusTreeMap::dump_ascii_with`]
+/// render nodes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DumpOptions {
+    /// Entries or separators shown per node before eliding the rest.
+    pub max_entries: Option<usize>,
+    /// Mark nodes other than the root that hold fewer entries than the
+    /// merge threshold asks for, or that a deferred delete left underfull.
+    pub highlight_underfull: bool,
+}
+
+impl DumpOptions {
+    /// Every entry shown, underfull nodes highlighted.
+    pub const fn new() -> Self {
+        Self {
+            max_entries: None,
+            highlight_underfull: true,
+        }
+    }
+
+    pub fn max_entries(mut self, n: usize) -> Self {
+        self.max_entries = Some(n);
+        self
+    }
+
+    pub fn highlight_underfull(mut self, on: bool) -> Self {
+        self.highlight_underfull = on;
+        self
+    }
+
+    fn shown(&self, len: usize) -> usize {
+        self.max_entries.map_or(len, |n| n.min(len))
+    }
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A node's contents, read once.
+struct NodeView<'a, K, V> {
+    tag: NodeTag,
+    keys: &'a [K],
+    /// Leaf values; empty for branches.
+    vals: &'a [V],
+    /// Branch children; empty for leaves.
+    children: &'a [*mut u8],
+    next: *mut u8,
+    prev: *mut u8,
+    underfull: bool,
+}
+
+impl<K: Debug, V: Debug, C: Comparator<K>> BPlusTreeMap<K, V, C> {
+    /// Write the tree as a Graphviz `digraph`.
+    pub fn to_dot<W: Write>(&self, out: &mut W) -> fmt::Result {
+        self.to_dot_with(out, &DumpOptions::new())
+    }
+
+    pub fn to_dot_with<W: Write>(&self, out: &mut W, opts: &DumpOptions) -> fmt::Result {
+        writeln!(out, "digraph bplustree {{")?;
+        writeln!(out, "  node [shape=record, fontname=\"monospace\"];")?;
+        let mut seen = BTreeSet::new();
+        if let Some(root) = self.root {
+            unsafe { self.dot_node(out, root, true, opts, &mut seen)? };
+        }
+        writeln!(out, "}}")
+    }
+
+    /// The nodes level by level, one per line under its path.
+    pub fn dump_ascii(&self) -> String {
+        self.dump_ascii_with(&DumpOptions::new())
+    }
+
+    pub fn dump_ascii_with(&self, opts: &DumpOptions) -> String {
+        let mut out = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = unsafe { self.write_ascii(&mut out, opts) };
+        out
+    }
+
+    unsafe fn dot_node<W: Write>(
+        &self,
+        out: &mut W,
+        node: NonNull<u8>,
+        is_root: bool,
+        opts: &DumpOptions,
+        seen: &mut BTreeSet<usize>,
+    ) -> fmt::Result {
+        if !seen.insert(node.as_ptr() as usize) {
+            return Ok(());
+        }
+        let view = self.view(node, is_root);
+        let name = node.as_ptr() as usize;
+        let shown = opts.shown(view.keys.len());
+        write!(out, "  n{:x} [label=\"", name)?;
+        match view.tag {
+            NodeTag::Branch => {
+                for i in 0..shown {
+                    write!(out, "<c{}>|", i)?;
+                    write!(Escaped(out), "{:?}", view.keys[i])?;
+                    write!(out, "|")?;
+                }
+                if shown < view.keys.len() {
+                    write!(out, "<more>... {} more|", view.keys.len() - shown)?;
+                }
+                write!(out, "<c{}>", view.keys.len())?;
+            }
+            NodeTag::Leaf => {
+                if view.keys.is_empty() {
+                    write!(out, "(empty)")?;
+                }
+                for i in 0..shown {
+                    if i > 0 {
+                        write!(out, "|")?;
+                    }
+                    write!(Escaped(out), "{:?}: {:?}", view.keys[i], view.vals[i])?;
+                }
+                if shown < view.keys.len() {
+                    write!(out, "|... {} more", view.keys.len() - shown)?;
+                }
+            }
+        }
+        write!(out, "\"")?;
+        if opts.highlight_underfull && view.underfull {
+            write!(out, ", style=filled, fillcolor=\"#f4cccc\"")?;
+        }
+        writeln!(out, "];")?;
+
+        match view.tag {
+            NodeTag::Branch => {
+                let last = view.keys.len();
+                for (i, &child) in view.children.iter().enumerate() {
+                    let Some(child) = NonNull::new(child) else {
+                        continue;
+                    };
+                    let child_name = child.as_ptr() as usize;
+                    if i < shown || i == last {
+                        writeln!(out, "  n{:x}:c{} -> n{:x};", name, i, child_name)?;
+                    } else {
+                        // Children of elided separators hang off the "more" cell.
+                        writeln!(out, "  n{:x}:more -> n{:x};", name, child_name)?;
+                    }
+                    self.dot_node(out, child, false, opts, seen)?;
+                }
+            }
+            NodeTag::Leaf => {
+                if !view.next.is_null() {
+                    writeln!(
+                        out,
+                        "  n{:x} -> n{:x} [style=dashed, constraint=false];",
+                        name, view.next as usize
+                    )?;
+                }
+                if !view.prev.is_null() {
+                    writeln!(
+                        out,
+                        "  n{:x} -> n{:x} [style=dashed, color=gray, constraint=false];",
+                        name, view.prev as usize
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    unsafe fn write_ascii<W: Write>(&self, out: &mut W, opts: &DumpOptions) -> fmt::Result {
+        let Some(root) = self.root else {
+            return writeln!(out, "(empty tree)");
+        };
+        let mut seen = BTreeSet::new();
+        let mut level = alloc::vec![(root, Vec::new())];
+        let mut depth = 0;
+        while !level.is_empty() {
+            let plural = if level.len() == 1 { "" } else { "s" };
+            writeln!(out, "level {} ({} node{})", depth, level.len(), plural)?;
+            let mut below = Vec::new();
+            for (node, path) in level {
+                write!(out, "  {:?} ", path)?;
+                if !seen.insert(node.as_ptr() as usize) {
+                    writeln!(out, "seen already")?;
+                    continue;
+                }
+                let view = self.view(node, depth == 0);
+                let shown = opts.shown(view.keys.len());
+                match view.tag {
+                    NodeTag::Branch => {
+                        write!(out, "branch")?;
+                        for (i, k) in view.keys[..shown].iter().enumerate() {
+                            write!(out, "{}{:?}", if i == 0 { " " } else { " | " }, k)?;
+                        }
+                        for (i, &child) in view.children.iter().enumerate() {
+                            if let Some(child) = NonNull::new(child) {
+                                let mut child_path = path.clone();
+                                child_path.push(i);
+                                below.push((child, child_path));
+                            }
+                        }
+                    }
+                    NodeTag::Leaf => {
+                        write!(out, "leaf")?;
+                        for i in 0..shown {
+                            let sep = if i == 0 { " " } else { ", " };
+                            write!(out, "{}{:?}: {:?}", sep, view.keys[i], view.vals[i])?;
+                        }
+                    }
+                }
+                if shown < view.keys.len() {
+                    write!(out, " ... {} more", view.keys.len() - shown)?;
+                }
+                if opts.highlight_underfull && view.underfull {
+                    write!(out, "  <- underfull ({} keys)", view.keys.len())?;
+                }
+                writeln!(out)?;
+            }
+            level = below;
+            depth += 1;
+        }
+        Ok(())
+    }
+
+    unsafe fn view(&self, node: NonNull<u8>, is_root: bool) -> NodeView<'_, K, V> {
+        let hdr = &*(node.as_ptr() as *const NodeHdr);
+        let len = hdr.len as usize;
+        let flagged = hdr.flags & NodeHdr::UNDERFULL != 0;
+        match hdr.tag {
+            NodeTag::Leaf => {
+                let parts = layout::carve_leaf::<K, V>(node, &self.leaf_layout);
+                NodeView {
+                    tag: NodeTag::Leaf,
+                    keys: core::slice::from_raw_parts(parts.keys_ptr as *const K, len),
+                    vals: core::slice::from_raw_parts(parts.vals_ptr as *const V, len),
+                    children: &[],
+                    next: *parts.next_ptr,
+                    prev: parts.prev_ptr.map_or(core::ptr::null_mut(), |p| *p),
+                    underfull: !is_root && (flagged || len < self.min_leaf_len()),
+                }
+            }
+            NodeTag::Branch => {
+                let parts = layout::carve_branch::<K>(node, &self.branch_layout);
+                NodeView {
+                    tag: NodeTag::Branch,
+                    keys: core::slice::from_raw_parts(parts.keys_ptr as *const K, len),
+                    vals: &[],
+                    children: core::slice::from_raw_parts(
+                        parts.children_ptr as *const *mut u8,
+                        len + 1,
+                    ),
+                    next: core::ptr::null_mut(),
+                    prev: core::ptr::null_mut(),
+                    underfull: !is_root && (flagged || len < self.min_branch_len()),
+                }
+            }
+        }
+    }
+}
+
+/// Escapes what Graphviz record labels and quoted strings treat as syntax.
+struct Escaped<'a, W>(&'a mut W);
+
+impl<W: Write> Write for Escaped<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if matches!(c, '"' | '\\' | '{' | '}' | '|' | '<' | '>') {
+                self.0.write_char('\\')?;
+            }
+            self.0.write_char(c)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! The finger: the last leaf a lookup or insert reached, with its bounds.
+//!
+//! Keys arriving in (nearly) ascending order, or clustered around a recent
+//! key, land in the same leaf as the previous operation. The map caches that
+//! leaf together with the branch separators bounding it, so `get` and
+//! `insert` can check two keys instead of descending from the root.
+//!
+//! The bounds point into branch nodes and stay valid until a branch changes,
+//! which only happens when a node splits, borrows or merges; those paths,
+//! `clear` and every rebuild drop the finger.
+
+use core::cell::Cell;
+use core::ptr::{self, NonNull};
+
+use crate::layout;
+use crate::{BPlusTreeMap, Comparator, NodeHdr, NodeTag};
+
+/// A leaf and the separators bounding it: it holds the keys in
+/// `[lower, upper)`, where a null bound is unbounded.
+pub(crate) struct Finger<K> {
+    leaf: NonNull<u8>,
+    lower: *const K,
+    upper: *const K,
+}
+
+impl<K> Clone for Finger<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for Finger<K> {}
+
+pub(crate) type FingerCell<K> = Cell<Option<Finger<K>>>;
+
+impl<K, V, C> BPlusTreeMap<K, V, C> {
+    /// Forget the cached leaf; call before any branch or leaf is moved,
+    /// split, merged or freed.
+    #[inline(always)]
+    pub(crate) fn drop_finger(&self) {
+        self.finger.set(None);
+    }
+
+    /// Cache `leaf`, which holds the keys in `[lower, upper)`; the bounds
+    /// point at separators in its ancestors, or are null when unbounded.
+    #[inline(always)]
+    pub(crate) fn aim_finger(&self, leaf: NonNull<u8>, lower: *const K, upper: *const K) {
+        self.finger.set(Some(Finger { leaf, lower, upper }));
+    }
+}
+
+impl<K, V, C: Comparator<K>> BPlusTreeMap<K, V, C> {
+    /// The cached leaf, if its bounds cover `key`, and whether it is the
+    /// leftmost leaf.
+    #[inline(always)]
+    pub(crate) fn finger_leaf(&self, key: &K) -> Option<(NonNull<u8>, bool)> {
+        let f = self.finger.get()?;
+        unsafe {
+            if !f.lower.is_null() && self.cmp.compare(key, &*f.lower).is_lt() {
+                return None;
+            }
+            if !f.upper.is_null() && self.cmp.compare(key, &*f.upper).is_ge() {
+                return None;
+            }
+        }
+        Some((f.leaf, f.lower.is_null()))
+    }
+
+    /// The leaf for `key`: the finger's when it covers `key`, otherwise
+    /// found by a descent that re-aims the finger at it.
+    #[inline]
+    pub(crate) fn leaf_for_key_fingered(&self, key: &K) -> Option<NonNull<u8>> {
+        if let Some((leaf, _)) = self.finger_leaf(key) {
+            self.visit(leaf);
+            return Some(leaf);
+        }
+        let mut cur = self.root?;
+        let mut lower: *const K = ptr::null();
+        let mut upper: *const K = ptr::null();
+        unsafe {
+            while (*(cur.as_ptr() as *const NodeHdr)).tag == NodeTag::Branch {
+                self.visit(cur);
+                let (child, idx) = self.child_for_key(cur, key)?;
+                let parts = layout::carve_branch::<K>(cur, &self.branch_layout);
+                let keys = parts.keys_ptr as *const K;
+                if idx > 0 {
+                    lower = keys.add(idx - 1);
+                }
+                if idx < (*parts.hdr).len as usize {
+                    upper = keys.add(idx);
+                }
+                cur = child;
+            }
+        }
+        self.visit(cur);
+        self.aim_finger(cur, lower, upper);
+        Some(cur)
+    }
+}
@@ -0,0 +1,634 @@
+//! Read-only, position-independent images of a [`BPlusTreeMap`].
+//!
+//! [`BPlusTreeMap::freeze`] writes the whole tree into one contiguous buffer
+//! with nodes addressed by byte offsets and every leaf packed full (only the
+//! last may be short). The image can be saved as-is and later opened from a
+//! `&[u8]`, e.g. an mmap, without deserializing: lookups read keys and values
+//! in place. Integers are stored in native byte order; the header records the
+//! endianness, word size and key/value layouts so a foreign image is rejected.
+//!
+//! ```text
+//! header (64 bytes): magic "BPTFROZ\0" | version u16 | endian marker u16
+//!         | word size u8 | reserved u8 | node align u16 | key size u32
+//!         | key align u32 | value size u32 | value align u32 | leaf cap u32
+//!         | branch cap u32 | entry count u64 | root offset u64 | image length u64
+//! leaf:   tag u8 | reserved u8 | len u16 | reserved u32 | next u64 | prev u64
+//!         | keys (aligned) | values (aligned)
+//! branch: tag u8 | reserved u8 | len u16 | reserved u32 | children u64 x (len + 1)
+//!         | keys (aligned)
+//! ```
+//!
+//! Every node starts on a multiple of the node alignment; offset 0 (the
+//! header) doubles as the "no sibling" link.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+use core::ops::{Bound, RangeBounds};
+use core::ptr::NonNull;
+
+use crate::node_alloc::{alloc_raw, dealloc_raw};
+use crate::pod::{pod_read, pod_write, Pod};
+use crate::{align_up, BPlusTreeError, BPlusTreeMap, BTreeResult, InvariantViolation};
+
+const FROZEN_MAGIC: &[u8; 8] = b"BPTFROZ\0";
+const FROZEN_VERSION: u16 = 1;
+const ENDIAN_MARKER: u16 = 0x0102;
+const HEADER_LEN: usize = 64;
+const LEAF_HDR: usize = 24;
+const BRANCH_HDR: usize = 8;
+const TAG_LEAF: u8 = 1;
+const TAG_BRANCH: u8 = 2;
+const NO_NODE: usize = 0;
+/// Deeper than any real tree with fanout >= 5; bounds the walk on open.
+const MAX_DEPTH: usize = 64;
+const END: (usize, usize) = (usize::MAX, 0);
+
+fn node_align<K, V>() -> usize {
+    8.max(align_of::<K>()).max(align_of::<V>())
+}
+
+fn leaf_keys_off<K>() -> usize {
+    align_up(LEAF_HDR, align_of::<K>())
+}
+
+fn leaf_vals_off<K, V>(len: usize) -> usize {
+    align_up(leaf_keys_off::<K>() + len * size_of::<K>(), align_of::<V>())
+}
+
+fn leaf_bytes<K, V>(len: usize) -> usize {
+    leaf_vals_off::<K, V>(len) + len * size_of::<V>()
+}
+
+fn branch_keys_off<K>(len: usize) -> usize {
+    align_up(BRANCH_HDR + 8 * (len + 1), align_of::<K>())
+}
+
+fn branch_bytes<K>(len: usize) -> usize {
+    branch_keys_off::<K>(len) + len * size_of::<K>()
+}
+
+fn corrupted(why: &str) -> BPlusTreeError {
+    BPlusTreeError::corrupted_tree("frozen image", why)
+}
+
+/// Heap buffer aligned for the nodes of an owned image.
+struct AlignedBytes {
+    ptr: NonNull<u8>,
+    len: usize,
+    align: usize,
+}
+
+impl AlignedBytes {
+    fn copy_from(bytes: &[u8], align: usize) -> Self {
+        // Never allocate zero bytes; an empty image fails validation anyway.
+        let size = bytes.len().max(1);
+        let ptr = unsafe { alloc_raw(size, align) }.expect("alloc frozen image");
+        unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.as_ptr(), bytes.len()) };
+        Self {
+            ptr,
+            len: bytes.len(),
+            align,
+        }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for AlignedBytes {
+    fn drop(&mut self) {
+        unsafe { dealloc_raw(self.ptr, self.len.max(1), self.align) }
+    }
+}
+
+// SAFETY: the buffer is uniquely owned and never mutated after construction.
+unsafe impl Send for AlignedBytes {}
+unsafe impl Sync for AlignedBytes {}
+
+enum FrozenBytes<'a> {
+    Owned(AlignedBytes),
+    Borrowed(&'a [u8]),
+}
+
+/// A read-only B+ tree over a frozen byte image.
+///
+/// Built by [`BPlusTreeMap::freeze`] or opened over existing bytes with
+/// [`FrozenBPlusTree::open`]. `get` and `range` behave like their
+/// [`BPlusTreeMap`] counterparts and return references into the image.
+pub struct FrozenBPlusTree<'a, K, V> {
+    bytes: FrozenBytes<'a>,
+    len: usize,
+    root: usize,
+    first_leaf: usize,
+    last_leaf: usize,
+    leaf_cap: usize,
+    branch_cap: usize,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K: Pod + Ord, V: Pod> BPlusTreeMap<K, V> {
+    /// Write the tree into a frozen image with the same node capacities.
+    ///
+    /// Leaves are packed full left to right; branch levels spread their
+    /// children evenly, as in [`BPlusTreeMap::from_sorted_iter`].
+    pub fn freeze(&self) -> FrozenBPlusTree<'static, K, V> {
+        let align = node_align::<K, V>();
+        let leaf_cap = self.leaf_layout.cap as usize;
+        let branch_cap = self.branch_layout.cap as usize;
+        let mut img = vec![0u8; HEADER_LEN];
+
+        let mut level: Vec<usize> = Vec::new();
+        let mut seps: Vec<K> = Vec::new();
+        let mut run: Vec<(K, V)> = Vec::with_capacity(leaf_cap);
+        let mut items = self.items().map(|(k, v)| (*k, *v)).peekable();
+        loop {
+            run.extend(items.by_ref().take(leaf_cap));
+            let prev = level.last().copied().unwrap_or(NO_NODE);
+            let off = write_leaf(&mut img, align, &run, prev);
+            if let Some(first) = run.first() {
+                if !level.is_empty() {
+                    seps.push(first.0);
+                }
+            }
+            level.push(off);
+            run.clear();
+            if items.peek().is_none() {
+                break;
+            }
+        }
+
+        let fanout = branch_cap + 1;
+        while level.len() > 1 {
+            let n = level.len();
+            let groups = n.div_ceil(fanout);
+            let (base, extra) = (n / groups, n % groups);
+            let mut children = level.iter().copied();
+            let mut keys = seps.into_iter();
+            let mut next_level = Vec::with_capacity(groups);
+            let mut next_seps = Vec::with_capacity(groups - 1);
+            for g in 0..groups {
+                let count = base + usize::from(g < extra);
+                let group_children: Vec<usize> = children.by_ref().take(count).collect();
+                let group_keys: Vec<K> = keys.by_ref().take(count - 1).collect();
+                next_level.push(write_branch(&mut img, align, &group_children, &group_keys));
+                if g + 1 < groups {
+                    next_seps.push(keys.next().expect("separator"));
+                }
+            }
+            level = next_level;
+            seps = next_seps;
+        }
+
+        write_header::<K, V>(&mut img, leaf_cap, branch_cap, self.len(), level[0]);
+        FrozenBPlusTree::from_bytes(FrozenBytes::Owned(AlignedBytes::copy_from(&img, align)))
+            .expect("freshly frozen image is valid")
+    }
+}
+
+fn put_u16(img: &mut [u8], off: usize, v: u16) {
+    img[off..off + 2].copy_from_slice(&v.to_ne_bytes());
+}
+
+fn put_u32(img: &mut [u8], off: usize, v: usize) {
+    img[off..off + 4].copy_from_slice(&(v as u32).to_ne_bytes());
+}
+
+fn put_u64(img: &mut [u8], off: usize, v: usize) {
+    img[off..off + 8].copy_from_slice(&(v as u64).to_ne_bytes());
+}
+
+fn write_leaf<K: Pod, V: Pod>(
+    img: &mut Vec<u8>,
+    align: usize,
+    run: &[(K, V)],
+    prev: usize,
+) -> usize {
+    let off = align_up(img.len(), align);
+    img.resize(off + leaf_bytes::<K, V>(run.len()), 0);
+    img[off] = TAG_LEAF;
+    put_u16(img, off + 2, run.len() as u16);
+    put_u64(img, off + 16, prev);
+    if prev != NO_NODE {
+        put_u64(img, prev + 8, off);
+    }
+    let (keys, vals) = (
+        off + leaf_keys_off::<K>(),
+        off + leaf_vals_off::<K, V>(run.len()),
+    );
+    for (i, (k, v)) in run.iter().enumerate() {
+        pod_write(&mut img[keys + i * size_of::<K>()..], *k);
+        pod_write(&mut img[vals + i * size_of::<V>()..], *v);
+    }
+    off
+}
+
+fn write_branch<K: Pod>(img: &mut Vec<u8>, align: usize, children: &[usize], keys: &[K]) -> usize {
+    let off = align_up(img.len(), align);
+    img.resize(off + branch_bytes::<K>(keys.len()), 0);
+    img[off] = TAG_BRANCH;
+    put_u16(img, off + 2, keys.len() as u16);
+    for (i, &child) in children.iter().enumerate() {
+        put_u64(img, off + BRANCH_HDR + 8 * i, child);
+    }
+    let base = off + branch_keys_off::<K>(keys.len());
+    for (i, k) in keys.iter().enumerate() {
+        pod_write(&mut img[base + i * size_of::<K>()..], *k);
+    }
+    off
+}
+
+fn write_header<K, V>(img: &mut [u8], leaf_cap: usize, branch_cap: usize, len: usize, root: usize) {
+    let image_len = img.len();
+    img[..8].copy_from_slice(FROZEN_MAGIC);
+    put_u16(img, 8, FROZEN_VERSION);
+    put_u16(img, 10, ENDIAN_MARKER);
+    img[12] = size_of::<usize>() as u8;
+    put_u16(img, 14, node_align::<K, V>() as u16);
+    put_u32(img, 16, size_of::<K>());
+    put_u32(img, 20, align_of::<K>());
+    put_u32(img, 24, size_of::<V>());
+    put_u32(img, 28, align_of::<V>());
+    put_u32(img, 32, leaf_cap);
+    put_u32(img, 36, branch_cap);
+    put_u64(img, 40, len);
+    put_u64(img, 48, root);
+    put_u64(img, 56, image_len);
+}
+
+impl<'a, K: Pod + Ord, V: Pod> FrozenBPlusTree<'a, K, V> {
+    /// Open an image in place, without copying.
+    ///
+    /// Validates the header against `K`, `V` and this platform, then walks
+    /// every node checking offsets, alignment, tags, lengths and the leaf
+    /// chain; keys are not compared (see
+    /// [`FrozenBPlusTree::check_invariants_detailed`]). `bytes` must start on
+    /// a multiple of the node alignment, as an mmap or the buffer returned by
+    /// [`FrozenBPlusTree::as_bytes`] does; otherwise `InvalidState` is
+    /// returned. Any other mismatch yields `CorruptedTree`.
+    pub fn open(bytes: &'a [u8]) -> BTreeResult<Self> {
+        Self::from_bytes(FrozenBytes::Borrowed(bytes))
+    }
+
+    /// Copy an image into an owned, suitably aligned buffer and open it.
+    pub fn load(bytes: &[u8]) -> BTreeResult<FrozenBPlusTree<'static, K, V>> {
+        FrozenBPlusTree::from_bytes(FrozenBytes::Owned(AlignedBytes::copy_from(
+            bytes,
+            node_align::<K, V>(),
+        )))
+    }
+
+    fn from_bytes(bytes: FrozenBytes<'a>) -> BTreeResult<Self> {
+        let mut tree = Self {
+            bytes,
+            len: 0,
+            root: NO_NODE,
+            first_leaf: NO_NODE,
+            last_leaf: NO_NODE,
+            leaf_cap: 0,
+            branch_cap: 0,
+            _marker: PhantomData,
+        };
+        tree.read_header()?;
+        let mut walk = Walk::default();
+        tree.walk_node(&mut walk, tree.root, 0)?;
+        if walk.entries != tree.len {
+            return Err(corrupted("entry count mismatch"));
+        }
+        if tree.next_leaf(walk.prev_leaf) != NO_NODE {
+            return Err(corrupted("last leaf has a successor"));
+        }
+        tree.first_leaf = walk.first_leaf;
+        tree.last_leaf = walk.prev_leaf;
+        Ok(tree)
+    }
+
+    fn read_header(&mut self) -> BTreeResult<()> {
+        let data = self.as_bytes();
+        if data.len() < HEADER_LEN {
+            return Err(corrupted("truncated header"));
+        }
+        let u16_at = |o: usize| pod_read::<u16>(&data[o..]) as usize;
+        let u32_at = |o: usize| pod_read::<u32>(&data[o..]) as usize;
+        let u64_at = |o: usize| usize::try_from(pod_read::<u64>(&data[o..]));
+        if &data[..8] != FROZEN_MAGIC {
+            return Err(corrupted("bad magic"));
+        }
+        if u16_at(8) != FROZEN_VERSION as usize {
+            return Err(corrupted("unsupported version"));
+        }
+        if u16_at(10) != ENDIAN_MARKER as usize {
+            return Err(corrupted("endianness mismatch"));
+        }
+        if data[12] as usize != size_of::<usize>() {
+            return Err(corrupted("word size mismatch"));
+        }
+        let align = node_align::<K, V>();
+        if u16_at(14) != align
+            || (u32_at(16), u32_at(20)) != (size_of::<K>(), align_of::<K>())
+            || (u32_at(24), u32_at(28)) != (size_of::<V>(), align_of::<V>())
+        {
+            return Err(corrupted("key/value layout mismatch"));
+        }
+        let (leaf_cap, branch_cap) = (u32_at(32), u32_at(36));
+        if !(4..=u16::MAX as usize).contains(&leaf_cap)
+            || !(4..=u16::MAX as usize).contains(&branch_cap)
+        {
+            return Err(corrupted("invalid node capacity"));
+        }
+        let (Ok(len), Ok(root), Ok(image_len)) = (u64_at(40), u64_at(48), u64_at(56)) else {
+            return Err(corrupted("offset out of range"));
+        };
+        if image_len != data.len() {
+            return Err(corrupted("image length mismatch"));
+        }
+        if !(data.as_ptr() as usize).is_multiple_of(align) {
+            return Err(BPlusTreeError::invalid_state(
+                "open frozen image",
+                "buffer is not aligned to the node alignment",
+            ));
+        }
+        self.len = len;
+        self.root = root;
+        self.leaf_cap = leaf_cap;
+        self.branch_cap = branch_cap;
+        Ok(())
+    }
+
+    /// Check that the node at `off` and its subtree are well-formed.
+    fn walk_node(&self, walk: &mut Walk, off: usize, depth: usize) -> BTreeResult<()> {
+        let data = self.as_bytes();
+        if depth >= MAX_DEPTH {
+            return Err(corrupted("tree too deep"));
+        }
+        if off < HEADER_LEN
+            || !off.is_multiple_of(node_align::<K, V>())
+            || off
+                .checked_add(LEAF_HDR.max(BRANCH_HDR))
+                .is_none_or(|e| e > data.len())
+        {
+            return Err(corrupted("node offset out of bounds"));
+        }
+        let len = self.node_len(off);
+        match data[off] {
+            TAG_LEAF => {
+                if len > self.leaf_cap || off + leaf_bytes::<K, V>(len) > data.len() {
+                    return Err(corrupted("leaf length out of bounds"));
+                }
+                if len == 0 && (off != self.root || self.len != 0) {
+                    return Err(corrupted("empty leaf"));
+                }
+                if *walk.leaf_depth.get_or_insert(depth) != depth {
+                    return Err(corrupted("leaves at different depths"));
+                }
+                // Leaves must be visited at strictly increasing offsets, so a
+                // shared or cyclic subtree fails on its first repeated leaf.
+                if off <= walk.prev_leaf || self.prev_leaf(off) != walk.prev_leaf {
+                    return Err(corrupted("broken leaf chain"));
+                }
+                if walk.prev_leaf == NO_NODE {
+                    walk.first_leaf = off;
+                } else if self.next_leaf(walk.prev_leaf) != off {
+                    return Err(corrupted("broken leaf chain"));
+                }
+                walk.prev_leaf = off;
+                walk.entries += len;
+                Ok(())
+            }
+            TAG_BRANCH => {
+                if len == 0 || len > self.branch_cap || off + branch_bytes::<K>(len) > data.len() {
+                    return Err(corrupted("branch length out of bounds"));
+                }
+                for i in 0..=len {
+                    self.walk_node(walk, self.child(off, i), depth + 1)?;
+                }
+                Ok(())
+            }
+            _ => Err(corrupted("bad node tag")),
+        }
+    }
+
+    /// Key order within nodes and against every separator; `open` has
+    /// already checked everything else.
+    pub fn check_invariants_detailed(&self) -> Result<(), InvariantViolation> {
+        self.check_keys(self.root, &mut Vec::new(), None, None)
+    }
+
+    fn check_keys(
+        &self,
+        off: usize,
+        path: &mut Vec<usize>,
+        lo: Option<&K>,
+        hi: Option<&K>,
+    ) -> Result<(), InvariantViolation> {
+        if self.as_bytes()[off] == TAG_LEAF {
+            return check_run(self.leaf_entries(off).0, lo, hi, path);
+        }
+        let keys = self.branch_keys(off);
+        check_run(keys, lo, hi, path)?;
+        for i in 0..=keys.len() {
+            let child_lo = if i == 0 { lo } else { Some(&keys[i - 1]) };
+            let child_hi = if i == keys.len() { hi } else { Some(&keys[i]) };
+            path.push(i);
+            self.check_keys(self.child(off, i), path, child_lo, child_hi)?;
+            path.pop();
+        }
+        Ok(())
+    }
+
+    /// The image bytes, suitable for writing to a file and reopening.
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.bytes {
+            FrozenBytes::Owned(b) => b.as_slice(),
+            FrozenBytes::Borrowed(b) => b,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let (keys, vals) = self.leaf_entries(self.leaf_for(key));
+        keys.binary_search(key).ok().map(|i| &vals[i])
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn iter(&self) -> FrozenRange<'_, K, V> {
+        self.range(..)
+    }
+
+    pub fn range<R: RangeBounds<K>>(&self, r: R) -> FrozenRange<'_, K, V> {
+        let front = match r.start_bound() {
+            Bound::Unbounded => self.canonical(self.first_leaf, 0),
+            Bound::Included(k) => self.position(k, |x| x < k),
+            Bound::Excluded(k) => self.position(k, |x| x <= k),
+        };
+        let back = match r.end_bound() {
+            Bound::Unbounded => END,
+            Bound::Included(k) => self.position(k, |x| x <= k),
+            Bound::Excluded(k) => self.position(k, |x| x < k),
+        };
+        FrozenRange {
+            tree: self,
+            front,
+            back,
+        }
+    }
+
+    /// Position of the first entry in `key`'s leaf not matching `before`.
+    fn position(&self, key: &K, before: impl Fn(&K) -> bool) -> (usize, usize) {
+        let leaf = self.leaf_for(key);
+        let idx = self.leaf_entries(leaf).0.partition_point(before);
+        self.canonical(leaf, idx)
+    }
+
+    /// Normalize a position so that it names an entry, or is `END`.
+    fn canonical(&self, leaf: usize, idx: usize) -> (usize, usize) {
+        if idx < self.node_len(leaf) {
+            return (leaf, idx);
+        }
+        match self.next_leaf(leaf) {
+            NO_NODE => END,
+            next => (next, 0),
+        }
+    }
+
+    fn leaf_for(&self, key: &K) -> usize {
+        let mut off = self.root;
+        while self.as_bytes()[off] == TAG_BRANCH {
+            let i = self.branch_keys(off).partition_point(|k| k <= key);
+            off = self.child(off, i);
+        }
+        off
+    }
+
+    fn node_len(&self, off: usize) -> usize {
+        pod_read::<u16>(&self.as_bytes()[off + 2..]) as usize
+    }
+
+    fn read_link(&self, at: usize) -> usize {
+        usize::try_from(pod_read::<u64>(&self.as_bytes()[at..])).unwrap_or(usize::MAX)
+    }
+
+    fn next_leaf(&self, leaf: usize) -> usize {
+        self.read_link(leaf + 8)
+    }
+
+    fn prev_leaf(&self, leaf: usize) -> usize {
+        self.read_link(leaf + 16)
+    }
+
+    fn child(&self, branch: usize, i: usize) -> usize {
+        self.read_link(branch + BRANCH_HDR + 8 * i)
+    }
+
+    /// Cast `len` values of `T` at byte offset `off`.
+    ///
+    /// Only called on node offsets checked by `walk_node`, which guarantees
+    /// bounds and alignment given the aligned base checked in `read_header`.
+    fn slice_at<T: Pod>(&self, off: usize, len: usize) -> &[T] {
+        let data = self.as_bytes();
+        debug_assert!(off + len * size_of::<T>() <= data.len());
+        unsafe { core::slice::from_raw_parts(data.as_ptr().add(off) as *const T, len) }
+    }
+
+    fn leaf_entries(&self, leaf: usize) -> (&[K], &[V]) {
+        let len = self.node_len(leaf);
+        (
+            self.slice_at(leaf + leaf_keys_off::<K>(), len),
+            self.slice_at(leaf + leaf_vals_off::<K, V>(len), len),
+        )
+    }
+
+    fn branch_keys(&self, branch: usize) -> &[K] {
+        let len = self.node_len(branch);
+        self.slice_at(branch + branch_keys_off::<K>(len), len)
+    }
+}
+
+/// Keys of the node at `path` must be strictly increasing and within
+/// `[lo, hi)`.
+fn check_run<K: Ord>(
+    keys: &[K],
+    lo: Option<&K>,
+    hi: Option<&K>,
+    path: &[usize],
+) -> Result<(), InvariantViolation> {
+    if let Some(i) = keys.windows(2).position(|w| w[0] >= w[1]) {
+        return Err(InvariantViolation::UnsortedKeys {
+            path: path.to_vec(),
+            index: i + 1,
+        });
+    }
+    let outside = |k: &K| lo.is_some_and(|lo| k < lo) || hi.is_some_and(|hi| k >= hi);
+    if let Some(i) = keys.iter().position(outside) {
+        return Err(InvariantViolation::SeparatorBound {
+            path: path.to_vec(),
+            index: i,
+        });
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Walk {
+    leaf_depth: Option<usize>,
+    first_leaf: usize,
+    prev_leaf: usize,
+    entries: usize,
+}
+
+/// Iterator over a key range of a [`FrozenBPlusTree`].
+pub struct FrozenRange<'t, K, V> {
+    tree: &'t FrozenBPlusTree<'t, K, V>,
+    /// Next entry to yield from the front, or `END`.
+    front: (usize, usize),
+    /// One past the next entry to yield from the back, or `END`.
+    back: (usize, usize),
+}
+
+impl<'t, K: Pod + Ord, V: Pod> Iterator for FrozenRange<'t, K, V> {
+    type Item = (&'t K, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Leaf offsets increase along the chain, so positions order lexically.
+        if self.front >= self.back {
+            return None;
+        }
+        let (leaf, idx) = self.front;
+        let (keys, vals) = self.tree.leaf_entries(leaf);
+        self.front = self.tree.canonical(leaf, idx + 1);
+        Some((&keys[idx], &vals[idx]))
+    }
+}
+
+impl<'t, K: Pod + Ord, V: Pod> DoubleEndedIterator for FrozenRange<'t, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        let leaf = match self.back {
+            END => self.tree.last_leaf,
+            (leaf, 0) => self.tree.prev_leaf(leaf),
+            (leaf, _) => leaf,
+        };
+        let idx = if self.back.0 == leaf {
+            self.back.1 - 1
+        } else {
+            self.tree.node_len(leaf) - 1
+        };
+        self.back = (leaf, idx);
+        let (keys, vals) = self.tree.leaf_entries(leaf);
+        Some((&keys[idx], &vals[idx]))
+    }
+}
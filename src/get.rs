@@ -1,9 +1,14 @@
 use alloc::vec::Vec;
+use core::ptr::NonNull;
 
 use crate::layout;
-use crate::{BPlusTreeError, BPlusTreeMap, BTreeResult};
+use crate::prefetch;
+use crate::{BPlusTreeError, BPlusTreeMap, BTreeResult, Comparator, NodeHdr, NodeTag};
 
-impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
+/// A node and the exclusive upper bound of its subtree (`None` is unbounded).
+type Bounded<'a, K> = (NonNull<u8>, Option<&'a K>);
+
+impl<K, V, C: Comparator<K>> BPlusTreeMap<K, V, C> {
     pub fn get(&self, key: &K) -> Option<&V> {
         let (parts, idx) = self.leaf_search(key)?;
         unsafe { Some(&*(parts.vals_ptr.add(idx) as *const V)) }
@@ -26,7 +31,17 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         self.get(key).unwrap_or(default)
     }
 
+    /// Values for all `keys`, or `KeyNotFound` if any is missing. With a
+    /// nonzero prefetch distance the descents are interleaved as in
+    /// [`BPlusTreeMap::get_batch`].
     pub fn get_many<'a>(&'a self, keys: &'a [K]) -> BTreeResult<Vec<&'a V>> {
+        if self.prefetch_distance > 0 {
+            return self
+                .get_batch(keys)
+                .into_iter()
+                .collect::<Option<Vec<_>>>()
+                .ok_or(BPlusTreeError::KeyNotFound);
+        }
         let mut out = Vec::with_capacity(keys.len());
         for k in keys {
             match self.get(k) {
@@ -37,8 +52,108 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         Ok(out)
     }
 
+    /// The value for each of `keys`, which should be in ascending order.
+    /// Each descent resumes from the lowest branch on the previous key's
+    /// path whose subtree still covers the key, so nearby keys share most
+    /// of their path and keys in the same leaf need no descent at all. Keys
+    /// out of order are still found, by restarting from the root.
+    pub fn get_many_sorted<'a>(&'a self, keys: &[K]) -> Vec<Option<&'a V>> {
+        let Some(root) = self.root else {
+            return keys.iter().map(|_| None).collect();
+        };
+        let mut out = Vec::with_capacity(keys.len());
+        let mut path = Vec::new();
+        let mut leaf: Option<Bounded<'a, K>> = None;
+        let mut prev: Option<&K> = None;
+        for key in keys {
+            let ascending = prev.is_some_and(|p| self.cmp.compare(key, p).is_ge());
+            let start = match leaf {
+                Some((_, upper)) if ascending && self.below(key, upper) => None,
+                Some(_) if ascending => {
+                    // Every branch on the path covers `key` from below; pop
+                    // those it lies beyond. The root is unbounded above.
+                    while path
+                        .last()
+                        .is_some_and(|&(_, upper)| !self.below(key, upper))
+                    {
+                        path.pop();
+                    }
+                    Some(path.pop().unwrap_or((root, None)))
+                }
+                _ => {
+                    path.clear();
+                    Some((root, None))
+                }
+            };
+            if let Some((node, upper)) = start {
+                leaf = self.descend_bounded(node, upper, key, &mut path);
+            }
+            prev = Some(key);
+            out.push(leaf.and_then(|(leaf, _)| self.value_in_leaf(leaf, key)));
+        }
+        out
+    }
+
+    /// The value for each of `keys`, in any order. Descents run in groups
+    /// that advance one level at a time, prefetching every child before
+    /// reading any, so the cache misses of a group overlap.
+    pub fn get_batch<'a>(&'a self, keys: &[K]) -> Vec<Option<&'a V>> {
+        let mut out = Vec::with_capacity(keys.len());
+        let mut leaves = [None; prefetch::BATCH];
+        for batch in keys.chunks(prefetch::BATCH) {
+            let leaves = &mut leaves[..batch.len()];
+            self.leaves_for_keys(batch, leaves);
+            out.extend(
+                batch
+                    .iter()
+                    .zip(leaves.iter())
+                    .map(|(k, leaf)| self.value_in_leaf((*leaf)?, k)),
+            );
+        }
+        out
+    }
+
+    /// Whether `key` sorts below the exclusive bound `upper`.
+    #[inline(always)]
+    fn below(&self, key: &K, upper: Option<&K>) -> bool {
+        upper.is_none_or(|u| self.cmp.compare(key, u).is_lt())
+    }
+
+    /// Descend from `node`, whose subtree lies below `upper`, to the leaf
+    /// for `key`, pushing each branch passed with its own upper bound.
+    fn descend_bounded<'a>(
+        &'a self,
+        mut node: NonNull<u8>,
+        mut upper: Option<&'a K>,
+        key: &K,
+        path: &mut Vec<Bounded<'a, K>>,
+    ) -> Option<Bounded<'a, K>> {
+        unsafe {
+            while (*(node.as_ptr() as *const NodeHdr)).tag == NodeTag::Branch {
+                path.push((node, upper));
+                let (child, idx) = self.child_for_key(node, key)?;
+                let parts = layout::carve_branch::<K>(node, &self.branch_layout);
+                if idx < (*parts.hdr).len as usize {
+                    upper = Some(&*(parts.keys_ptr.add(idx) as *const K));
+                }
+                node = child;
+            }
+        }
+        Some((node, upper))
+    }
+
+    #[inline(always)]
+    fn value_in_leaf(&self, leaf: NonNull<u8>, key: &K) -> Option<&V> {
+        let (parts, idx) = self.search_leaf(leaf, key)?;
+        unsafe { Some(&*(parts.vals_ptr.add(idx) as *const V)) }
+    }
+
     pub(crate) fn leaf_search(&self, key: &K) -> Option<(layout::LeafParts<K, V>, usize)> {
-        let leaf = self.leaf_for_key(key)?;
+        self.search_leaf(self.leaf_for_key_fingered(key)?, key)
+    }
+
+    #[inline(always)]
+    fn search_leaf(&self, leaf: NonNull<u8>, key: &K) -> Option<(layout::LeafParts<K, V>, usize)> {
         unsafe {
             let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
             let len = (*parts.hdr).len as usize;
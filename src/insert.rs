@@ -1,9 +1,13 @@
 use alloc::vec::Vec;
 
-use core::ptr::NonNull;
+use core::ptr::{self, NonNull};
 
 use crate::layout;
-use crate::{alloc_branch_block, alloc_leaf_block, BPlusTreeMap, BTreeResult, NodeHdr, NodeTag};
+use crate::split::{self, Edge};
+use crate::{
+    alloc_branch_block, alloc_leaf_block, BPlusTreeMap, BTreeResult, DeriveSeparator, NodeHdr,
+    NodeTag,
+};
 
 pub(crate) enum InsertResult<K, V> {
     NoSplit(Option<V>),
@@ -11,11 +15,26 @@ pub(crate) enum InsertResult<K, V> {
         sep_key: K,
         right: NonNull<u8>,
         old_value: Option<V>,
+        /// The edge the split was skewed toward, if any.
+        edge: Option<Edge>,
     },
 }
 
-impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
+impl<K, V, C: DeriveSeparator<K>> BPlusTreeMap<K, V, C> {
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        // A leaf with room takes the entry without touching any branch, so
+        // it can be reached through the finger. Otherwise one descent from
+        // the root both finds the leaf and keeps the path a split needs.
+        if let Some((leaf, leftmost)) = self.finger_leaf(&key) {
+            self.visit(leaf);
+            let len = unsafe { (*(leaf.as_ptr() as *const NodeHdr)).len };
+            if len < self.leaf_layout.cap {
+                match unsafe { self.leaf_insert_or_split(leaf, key, value, leftmost) } {
+                    InsertResult::NoSplit(old) => return old,
+                    InsertResult::Split { .. } => unreachable!("leaf with room split"),
+                }
+            }
+        }
         let root = match self.root {
             Some(p) => p,
             None => unsafe { alloc_leaf_block(&self.leaf_layout).expect("alloc leaf") },
@@ -23,13 +42,14 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         if self.root.is_none() {
             self.root = Some(root);
         }
-        let res = unsafe { self.insert_rec(root, key, value) };
+        let res = unsafe { self.insert_rec(root, key, value, ptr::null(), ptr::null()) };
         match res {
             InsertResult::NoSplit(old) => old,
             InsertResult::Split {
                 sep_key,
                 right,
                 old_value,
+                ..
             } => {
                 unsafe {
                     let branch =
@@ -57,18 +77,47 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         Ok(old_vals)
     }
 
-    unsafe fn insert_rec(&mut self, node: NonNull<u8>, key: K, value: V) -> InsertResult<K, V> {
+    /// Insert below `node`, whose keys lie in `[lower, upper)`. A leaf that
+    /// takes the entry without splitting becomes the finger.
+    unsafe fn insert_rec(
+        &mut self,
+        node: NonNull<u8>,
+        key: K,
+        value: V,
+        lower: *const K,
+        upper: *const K,
+    ) -> InsertResult<K, V> {
+        self.visit(node);
         let hdr = &*(node.as_ptr() as *const NodeHdr);
         match hdr.tag {
-            NodeTag::Leaf => self.leaf_insert_or_split(node, key, value),
+            NodeTag::Leaf => {
+                let res = self.leaf_insert_or_split(node, key, value, lower.is_null());
+                if let InsertResult::NoSplit(_) = res {
+                    self.aim_finger(node, lower, upper);
+                }
+                res
+            }
             NodeTag::Branch => {
                 let (child, child_idx) = self.child_for_key(node, &key).expect("child must exist");
-                match self.insert_rec(child, key, value) {
+                let b = layout::carve_branch::<K>(node, &self.branch_layout);
+                let keys = b.keys_ptr as *const K;
+                let lower = if child_idx > 0 {
+                    keys.add(child_idx - 1)
+                } else {
+                    lower
+                };
+                let upper = if child_idx < (*b.hdr).len as usize {
+                    keys.add(child_idx)
+                } else {
+                    upper
+                };
+                match self.insert_rec(child, key, value, lower, upper) {
                     InsertResult::NoSplit(old) => InsertResult::NoSplit(old),
                     InsertResult::Split {
                         sep_key,
                         right,
                         old_value,
+                        edge,
                     } => {
                         let b = layout::carve_branch::<K>(node, &self.branch_layout);
                         let cur_len = (*b.hdr).len as usize;
@@ -88,9 +137,24 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                             );
                             *cbase.add(child_idx + 1) = right.as_ptr();
                             (*b.hdr).len = (cur_len + 1) as u16;
+                            self.settle_skewed(node);
                             InsertResult::NoSplit(old_value)
                         } else {
-                            self.branch_insert_and_split(node, child_idx, sep_key, right, old_value)
+                            let res = self.branch_insert_and_split(
+                                node, child_idx, sep_key, right, old_value, edge,
+                            );
+                            if let InsertResult::Split {
+                                right,
+                                edge: Some(edge),
+                                ..
+                            } = res
+                            {
+                                self.mark_skewed(match edge {
+                                    Edge::Left => node,
+                                    Edge::Right => right,
+                                });
+                            }
+                            res
                         }
                     }
                 }
@@ -105,11 +169,12 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         ins_key: K,
         ins_right: NonNull<u8>,
         old_value: Option<V>,
+        edge: Option<Edge>,
     ) -> InsertResult<K, V> {
         let b = layout::carve_branch::<K>(node, &self.branch_layout);
         let len = (*b.hdr).len as usize;
-        let total_keys = len + 1;
-        let pm = total_keys / 2; // number of keys that remain on the left after split
+        // number of keys that remain on the left after split
+        let (pm, edge) = split::branch_split_point(edge, insert_idx, len);
 
         // Allocate the new right branch
         let right_node = alloc_branch_block(&self.branch_layout).expect("alloc right branch");
@@ -167,6 +232,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                 sep_key: promote,
                 right: right_node,
                 old_value,
+                edge,
             }
         } else if insert_idx == pm {
             // Promote the inserted key; do not store it in either child
@@ -197,6 +263,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                 sep_key: promote,
                 right: right_node,
                 old_value,
+                edge,
             }
         } else {
             // insert_idx > pm
@@ -260,6 +327,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                 sep_key: promote,
                 right: right_node,
                 old_value,
+                edge,
             }
         }
     }
@@ -287,6 +355,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
             value,
         );
         (*parts.hdr).len = (cur_len + 1) as u16;
+        self.settle_skewed(NonNull::new_unchecked(parts.hdr as *mut u8));
     }
     #[inline(always)]
     unsafe fn shift_and_write(
@@ -307,12 +376,18 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         leaf: NonNull<u8>,
         key: K,
         value: V,
+        leftmost: bool,
     ) -> InsertResult<K, V> {
         let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
         let hdr = &mut *parts.hdr;
         let len = hdr.len as usize;
         let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
-        match self.binary_search_keys(keys, &key) {
+        let found = if self.duplicates {
+            Err(self.partition_keys(keys, &key, true))
+        } else {
+            self.binary_search_keys(keys, &key)
+        };
+        match found {
             Ok(idx) => {
                 let vptr = parts.vals_ptr.add(idx) as *mut V;
                 let old = core::ptr::read(vptr);
@@ -320,13 +395,15 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                 InsertResult::NoSplit(Some(old))
             }
             Err(idx) => {
+                let edge = self.note_leaf_insert(&parts, idx, len, leftmost);
                 if len < self.leaf_layout.cap as usize {
                     self.insert_into_leaf_slot(parts, idx, len, key, value);
                     InsertResult::NoSplit(None)
                 } else {
+                    self.drop_finger();
                     // Zero-allocation in-place split: move upper half to right, insert new item, clear moved slots
                     let total_items = len + 1;
-                    let left_count = total_items / 2;
+                    let left_count = split::leaf_split_point(edge, len);
                     let right_count = total_items - left_count;
 
                     // Determine insertion position (idx from Err was computed above as `idx`)
@@ -417,11 +494,21 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                         }
                     }
 
-                    let sep = self.key_clone_at(r.keys_ptr as *const K, 0);
+                    match edge {
+                        Some(Edge::Left) => self.mark_skewed(leaf),
+                        Some(Edge::Right) => self.mark_skewed(right),
+                        None => {}
+                    }
+
+                    let sep = self.cmp.separator(
+                        &*(parts.keys_ptr as *const K).add(hdr.len as usize - 1),
+                        &*(r.keys_ptr as *const K),
+                    );
                     InsertResult::Split {
                         sep_key: sep,
                         right,
                         old_value: None,
+                        edge,
                     }
                 }
             }
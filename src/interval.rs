@@ -0,0 +1,198 @@
+//! Map from half-open key ranges to values.
+//!
+//! A [`BPlusIntervalMap`] stores disjoint intervals as `start -> (end, value)`
+//! entries of a [`BPlusTreeMap`]. The interval holding a point is the last
+//! one starting at or before it, found in one descent. Inserting a range cuts
+//! back or splits whatever it overlaps, and merges with neighbours that touch
+//! it and carry an equal value, so each run of one value is a single entry.
+
+use core::ops::{Bound, Range};
+
+use crate::{BPlusTreeError, BPlusTreeMap, InvariantViolation, Items};
+
+/// Ordered map from disjoint half-open ranges of `K` to values.
+pub struct BPlusIntervalMap<K, V> {
+    map: BPlusTreeMap<K, (K, V)>,
+}
+
+impl<K: Ord + Clone, V> BPlusIntervalMap<K, V> {
+    /// An empty interval map with `capacity` for both node kinds.
+    pub fn new(capacity: usize) -> Result<Self, BPlusTreeError> {
+        Ok(Self {
+            map: BPlusTreeMap::new(capacity)?,
+        })
+    }
+
+    /// The underlying tree of `start -> (end, value)` entries.
+    pub fn as_map(&self) -> &BPlusTreeMap<K, (K, V)> {
+        &self.map
+    }
+
+    /// The value of the interval holding `key`.
+    pub fn get_point(&self, key: &K) -> Option<&V> {
+        let (_, (end, v)) = self.map.floor(key)?;
+        (key < end).then_some(v)
+    }
+
+    /// The stored intervals that overlap `r`, in order, whole.
+    pub fn overlapping(&self, r: Range<K>) -> Overlapping<'_, K, V> {
+        if r.start >= r.end {
+            return Overlapping {
+                head: None,
+                rest: None,
+            };
+        }
+        let head = self
+            .map
+            .predecessor(&r.start)
+            .filter(|(_, (end, _))| *end > r.start);
+        Overlapping {
+            head,
+            rest: Some(self.map.range(r)),
+        }
+    }
+
+    /// Every interval, in order.
+    pub fn iter(&self) -> Overlapping<'_, K, V> {
+        Overlapping {
+            head: None,
+            rest: Some(self.map.items()),
+        }
+    }
+
+    /// Number of stored intervals.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// The tree's invariants, plus: intervals are non-empty and disjoint,
+    /// and touching intervals hold different values.
+    pub fn check_invariants_detailed(&self) -> Result<(), InvariantViolation>
+    where
+        V: PartialEq,
+    {
+        self.map.check_invariants_detailed()?;
+        let mut prev: Option<(&K, &V)> = None;
+        for (i, (start, (end, v))) in self.map.items().enumerate() {
+            if start >= end {
+                return Err(InvariantViolation::EmptyInterval { index: i });
+            }
+            if let Some((prev_end, prev_v)) = prev {
+                if prev_end > start {
+                    return Err(InvariantViolation::OverlappingIntervals { index: i });
+                }
+                if prev_end == start && prev_v == v {
+                    return Err(InvariantViolation::UncoalescedIntervals { index: i });
+                }
+            }
+            prev = Some((end, v));
+        }
+        Ok(())
+    }
+
+    /// The first interval starting within `lo..=hi`, as `(start, end)`.
+    fn first_starting_in(&self, lo: &K, hi: Bound<&K>) -> Option<(K, K)> {
+        let (start, (end, _)) = self.map.range((Bound::Included(lo), hi)).next()?;
+        Some((start.clone(), end.clone()))
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + PartialEq> BPlusIntervalMap<K, V> {
+    /// Map every key in `r` to `value`, overwriting what was there. An empty
+    /// range changes nothing.
+    pub fn insert_range(&mut self, r: Range<K>, value: V) {
+        if r.start >= r.end {
+            return;
+        }
+        let Range {
+            start: mut lo,
+            end: mut hi,
+        } = r;
+        if let Some((start, (end, v))) = self.map.predecessor(&lo) {
+            let (start, end, same) = (start.clone(), end.clone(), *v == value);
+            if same && end >= lo {
+                self.map.remove(&start);
+                lo = start;
+                hi = hi.max(end);
+            } else if !same && end > lo {
+                self.cut(&start, lo.clone(), &hi, end);
+            }
+        }
+        while let Some((start, end)) = self.first_starting_in(&lo, Bound::Included(&hi)) {
+            let same = self.map.get(&start).is_some_and(|(_, v)| *v == value);
+            if start == hi && !same {
+                break;
+            }
+            let (_, v) = self.map.remove(&start).unwrap();
+            if end > hi {
+                if same {
+                    hi = end;
+                } else {
+                    self.map.insert(hi.clone(), (end, v));
+                }
+                break;
+            }
+        }
+        self.map.insert(lo, (hi, value));
+    }
+
+    /// Unmap every key in `r`, cutting back or splitting the intervals it
+    /// overlaps.
+    pub fn remove_range(&mut self, r: Range<K>) {
+        if r.start >= r.end {
+            return;
+        }
+        if let Some((start, (end, _))) = self.map.predecessor(&r.start) {
+            if *end > r.start {
+                let (start, end) = (start.clone(), end.clone());
+                self.cut(&start, r.start.clone(), &r.end, end);
+            }
+        }
+        while let Some((start, end)) = self.first_starting_in(&r.start, Bound::Excluded(&r.end)) {
+            let (_, v) = self.map.remove(&start).unwrap();
+            if end > r.end {
+                self.map.insert(r.end, (end, v));
+                break;
+            }
+        }
+    }
+
+    /// End the interval at `start`, which runs to `end`, at `at`; if it also
+    /// runs past `resume`, keep that part as its own interval.
+    fn cut(&mut self, start: &K, at: K, resume: &K, end: K) {
+        let entry = self.map.get_mut(start).unwrap();
+        entry.0 = at;
+        if end > *resume {
+            let v = entry.1.clone();
+            self.map.insert(resume.clone(), (end, v));
+        }
+    }
+}
+
+/// Iterator over intervals as `(range, value)`, from
+/// [`BPlusIntervalMap::overlapping`] and [`BPlusIntervalMap::iter`].
+pub struct Overlapping<'a, K, V> {
+    /// An interval starting before the queried range but reaching into it.
+    head: Option<(&'a K, &'a (K, V))>,
+    rest: Option<Items<'a, K, (K, V)>>,
+}
+
+impl<'a, K: Ord, V> Iterator for Overlapping<'a, K, V> {
+    type Item = (Range<&'a K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start, (end, v)) = match self.head.take() {
+            Some(head) => head,
+            None => self.rest.as_mut()?.next()?,
+        };
+        Some((start..end, v))
+    }
+}
@@ -0,0 +1,177 @@
+//! What [`crate::BPlusTreeMap::check_invariants_detailed`] and the other
+//! maps' validations report.
+//!
+//! Nodes are named by their path: the child index taken at each branch on
+//! the way down from the root, so the root is `[]` and its second child is
+//! `[1]`. Key and child indices are positions within that node; interval
+//! indices count stored intervals in order.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Which sibling link of a leaf.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeafLink {
+    Next,
+    Prev,
+}
+
+/// The first broken invariant a validation found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// The node holds more keys than its capacity.
+    Overfull {
+        path: Vec<usize>,
+        len: usize,
+        cap: usize,
+    },
+    /// A node other than the root has no keys.
+    EmptyNode { path: Vec<usize> },
+    /// The node holds fewer keys than the merge threshold allows, and is not
+    /// exempt as a deferred or skewed-split node.
+    Underfull {
+        path: Vec<usize>,
+        len: usize,
+        min: usize,
+    },
+    /// Key `index` does not come after the key before it; for key 0 of a
+    /// leaf, the last key of the previous leaf.
+    UnsortedKeys { path: Vec<usize>, index: usize },
+    /// Key `index` lies outside the bounds the separators above give the
+    /// node.
+    SeparatorBound { path: Vec<usize>, index: usize },
+    /// Child pointer `index` of the branch is null.
+    NullChild { path: Vec<usize>, index: usize },
+    /// The leaf is `depth` levels down while earlier leaves are at
+    /// `expected`.
+    UnequalLeafDepth {
+        path: Vec<usize>,
+        depth: usize,
+        expected: usize,
+    },
+    /// The leaf's `link` does not point at its neighbour in key order.
+    BrokenLink { path: Vec<usize>, link: LeafLink },
+    /// The leaves reachable from the root hold `tree` entries, the leaf
+    /// chain `chain`.
+    LengthMismatch { tree: usize, chain: usize },
+    /// The node was already reached through another child pointer.
+    CycleDetected { path: Vec<usize> },
+    /// The tree holds `counted` entries but the map's length is `len`.
+    CountMismatch { counted: usize, len: usize },
+    /// The slotted node's cells, free space and garbage do not account for
+    /// its bytes.
+    CorruptHeap { path: Vec<usize> },
+    /// The on-disk node could not be read or decoded.
+    UnreadableNode { path: Vec<usize> },
+    /// Interval `index` does not end after it starts.
+    EmptyInterval { index: usize },
+    /// Interval `index` starts before the one before it ends.
+    OverlappingIntervals { index: usize },
+    /// Interval `index` touches the one before it and holds an equal value.
+    UncoalescedIntervals { index: usize },
+}
+
+impl InvariantViolation {
+    /// Path of the node at fault; empty for the root and for violations not
+    /// tied to one node, such as [`InvariantViolation::LengthMismatch`] or
+    /// the interval ones.
+    pub fn path(&self) -> &[usize] {
+        match self {
+            InvariantViolation::Overfull { path, .. }
+            | InvariantViolation::EmptyNode { path }
+            | InvariantViolation::Underfull { path, .. }
+            | InvariantViolation::UnsortedKeys { path, .. }
+            | InvariantViolation::SeparatorBound { path, .. }
+            | InvariantViolation::NullChild { path, .. }
+            | InvariantViolation::UnequalLeafDepth { path, .. }
+            | InvariantViolation::BrokenLink { path, .. }
+            | InvariantViolation::CycleDetected { path }
+            | InvariantViolation::CorruptHeap { path }
+            | InvariantViolation::UnreadableNode { path } => path,
+            InvariantViolation::LengthMismatch { .. }
+            | InvariantViolation::CountMismatch { .. }
+            | InvariantViolation::EmptyInterval { .. }
+            | InvariantViolation::OverlappingIntervals { .. }
+            | InvariantViolation::UncoalescedIntervals { .. } => &[],
+        }
+    }
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::Overfull { path, len, cap } => {
+                write!(
+                    f,
+                    "node {:?} has {} keys but capacity is {}",
+                    path, len, cap
+                )
+            }
+            InvariantViolation::EmptyNode { path } => {
+                write!(f, "non-root node {:?} is empty", path)
+            }
+            InvariantViolation::Underfull { path, len, min } => write!(
+                f,
+                "node {:?} underfull: has {} keys, minimum is {}",
+                path, len, min
+            ),
+            InvariantViolation::UnsortedKeys { path, index } => write!(
+                f,
+                "node {:?} key {} not after the key before it",
+                path, index
+            ),
+            InvariantViolation::SeparatorBound { path, index } => write!(
+                f,
+                "node {:?} key {} outside its separator bounds",
+                path, index
+            ),
+            InvariantViolation::NullChild { path, index } => {
+                write!(f, "branch {:?} child {} is null", path, index)
+            }
+            InvariantViolation::UnequalLeafDepth {
+                path,
+                depth,
+                expected,
+            } => write!(
+                f,
+                "leaf {:?} at depth {}, other leaves at {}",
+                path, depth, expected
+            ),
+            InvariantViolation::BrokenLink { path, link } => {
+                let which = match link {
+                    LeafLink::Next => "next",
+                    LeafLink::Prev => "prev",
+                };
+                write!(f, "leaf {:?} {} pointer mismatch", path, which)
+            }
+            InvariantViolation::LengthMismatch { tree, chain } => write!(
+                f,
+                "{} entries under the root but {} along the leaf chain",
+                tree, chain
+            ),
+            InvariantViolation::CycleDetected { path } => {
+                write!(f, "node {:?} reached twice", path)
+            }
+            InvariantViolation::CountMismatch { counted, len } => {
+                write!(f, "{} entries but len is {}", counted, len)
+            }
+            InvariantViolation::CorruptHeap { path } => {
+                write!(f, "node {:?} heap does not account for its bytes", path)
+            }
+            InvariantViolation::UnreadableNode { path } => {
+                write!(f, "node {:?} could not be read", path)
+            }
+            InvariantViolation::EmptyInterval { index } => {
+                write!(f, "interval {} is empty", index)
+            }
+            InvariantViolation::OverlappingIntervals { index } => {
+                write!(f, "interval {} overlaps the one before", index)
+            }
+            InvariantViolation::UncoalescedIntervals { index } => {
+                write!(f, "interval {} was not coalesced", index)
+            }
+        }
+    }
+}
+
+impl core::error::Error for InvariantViolation {}
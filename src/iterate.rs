@@ -4,141 +4,64 @@ use core::ops::{Bound, RangeBounds};
 use core::ptr::NonNull;
 
 use crate::layout;
-use crate::{BPlusTreeMap, NodeHdr, NodeTag};
+use crate::prefetch::Lookahead;
+use crate::{BPlusTreeMap, Comparator, NodeHdr, NodeTag, OrdComparator};
 
-pub enum ItemsInner<'a, K, V> {
+/// An entry slot: a leaf and an index below its length.
+pub(crate) type Position = (NonNull<u8>, usize);
+
+pub enum ItemsInner<'a, K, V, C = OrdComparator> {
     Lazy {
-        tree: &'a BPlusTreeMap<K, V>,
-        front_leaf: Option<NonNull<u8>>,
-        front_idx: usize,
-        back_leaf: Option<NonNull<u8>>,
-        back_idx: usize,
-        remaining: usize,
-        start_bound: Bound<K>,
-        end_bound: Bound<K>,
-        initialized: bool,
+        tree: &'a BPlusTreeMap<K, V, C>,
+        /// Next entry to yield from the front; `None` is past the last entry.
+        front: Option<Position>,
+        /// Entry just after the next one to yield from the back; `None` is
+        /// past the last entry. The iterator is exhausted once `front == back`.
+        back: Option<Position>,
+        /// Entries left, when known up front.
+        remaining: Option<usize>,
+        /// Leaves prefetched past the front and before the back.
+        ahead: Lookahead,
+        behind: Lookahead,
     },
     Vec {
         inner: IntoIter<(&'a K, &'a V)>,
     },
 }
 
-pub struct Items<'a, K, V> {
-    pub(crate) inner: ItemsInner<'a, K, V>,
+pub struct Items<'a, K, V, C = OrdComparator> {
+    pub(crate) inner: ItemsInner<'a, K, V, C>,
 }
 
-impl<'a, K: Ord, V> Iterator for Items<'a, K, V> {
+impl<'a, K, V, C: Comparator<K>> Iterator for Items<'a, K, V, C> {
     type Item = (&'a K, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
         match &mut self.inner {
             ItemsInner::Lazy {
                 tree,
-                front_leaf,
-                front_idx,
+                front,
+                back,
                 remaining,
-                start_bound,
-                end_bound,
-                initialized,
+                ahead,
                 ..
             } => {
-                // Lazy initialization on first call
-                if !*initialized {
-                    *initialized = true;
-                    let is_excluded = matches!(start_bound, Bound::Excluded(_));
-                    match start_bound {
-                        Bound::Unbounded => {
-                            *front_leaf = tree.leftmost_leaf();
-                            *front_idx = 0;
-                        }
-                        Bound::Included(k) | Bound::Excluded(k) => {
-                            let leaf_opt = tree.leaf_for_key(k);
-                            if let Some(leaf) = leaf_opt {
-                                unsafe {
-                                    let parts = layout::carve_leaf::<K, V>(leaf, &tree.leaf_layout);
-                                    let len = (*parts.hdr).len as usize;
-                                    let keys = core::slice::from_raw_parts(
-                                        parts.keys_ptr as *const K,
-                                        len,
-                                    );
-
-                                    match keys.binary_search(k) {
-                                        Ok(i) => {
-                                            let idx = if is_excluded { i + 1 } else { i };
-                                            if idx >= len {
-                                                // Move to next leaf
-                                                let next_ptr = *parts.next_ptr;
-                                                *front_leaf = NonNull::new(next_ptr);
-                                                *front_idx = 0;
-                                            } else {
-                                                *front_leaf = Some(leaf);
-                                                *front_idx = idx;
-                                            }
-                                        }
-                                        Err(i) => {
-                                            if i >= len {
-                                                // Move to next leaf
-                                                let next_ptr = *parts.next_ptr;
-                                                *front_leaf = NonNull::new(next_ptr);
-                                                *front_idx = 0;
-                                            } else {
-                                                *front_leaf = Some(leaf);
-                                                *front_idx = i;
-                                            }
-                                        }
-                                    }
-                                }
-                            } else {
-                                *front_leaf = None;
-                                *front_idx = 0;
-                            }
-                        }
-                    }
+                if *front == *back {
+                    return None;
                 }
-
-                // Loop to handle leaf boundary crossing without recursion
-                loop {
-                    let leaf = (*front_leaf)?;
-                    unsafe {
-                        let parts = layout::carve_leaf::<K, V>(leaf, &tree.leaf_layout);
-                        let len = (*parts.hdr).len as usize;
-
-                        if *front_idx < len {
-                            let k = &*(parts.keys_ptr.add(*front_idx) as *const K);
-
-                            // Check end bound
-                            let within_bound = match end_bound {
-                                Bound::Unbounded => true,
-                                Bound::Included(e) => k <= e,
-                                Bound::Excluded(e) => k < e,
-                            };
-
-                            if !within_bound {
-                                *front_leaf = None;
-                                *remaining = 0;
-                                return None;
-                            }
-
-                            let v = &*(parts.vals_ptr.add(*front_idx) as *const V);
-                            *front_idx += 1;
-                            if *remaining > 0 {
-                                *remaining -= 1;
-                            }
-                            return Some((k, v));
-                        }
-
-                        // Move to next leaf
-                        let next_ptr = *parts.next_ptr;
-                        if next_ptr.is_null() {
-                            *front_leaf = None;
-                            *remaining = 0;
-                            return None;
-                        }
-
-                        *front_leaf = NonNull::new(next_ptr);
-                        *front_idx = 0;
-                        // Continue loop instead of recursive call
+                let (leaf, idx) = (*front)?;
+                unsafe {
+                    let parts = layout::carve_leaf::<K, V>(leaf, &tree.leaf_layout);
+                    let k = &*(parts.keys_ptr.add(idx) as *const K);
+                    let v = &*(parts.vals_ptr.add(idx) as *const V);
+                    *front = tree.canonical_position(leaf, idx + 1);
+                    if let Some((next, 0)) = *front {
+                        tree.prefetch_ahead(ahead, next, tree.leaf_layout.next_off);
                     }
+                    if let Some(n) = remaining {
+                        *n -= 1;
+                    }
+                    Some((k, v))
                 }
             }
             ItemsInner::Vec { inner } => inner.next(),
@@ -148,77 +71,57 @@ impl<'a, K: Ord, V> Iterator for Items<'a, K, V> {
     fn size_hint(&self) -> (usize, Option<usize>) {
         match &self.inner {
             ItemsInner::Lazy {
-                remaining,
-                initialized,
-                ..
-            } => {
-                if *initialized && *remaining > 0 {
-                    (*remaining, Some(*remaining))
-                } else {
-                    // Uninitialized or unknown size
-                    (0, None)
-                }
-            }
+                remaining: Some(n), ..
+            } => (*n, Some(*n)),
+            ItemsInner::Lazy { .. } => (0, None),
             ItemsInner::Vec { inner } => inner.size_hint(),
         }
     }
 }
 
-impl<'a, K: Ord, V> DoubleEndedIterator for Items<'a, K, V> {
+impl<'a, K, V, C: Comparator<K>> DoubleEndedIterator for Items<'a, K, V, C> {
     fn next_back(&mut self) -> Option<<Self as Iterator>::Item> {
         match &mut self.inner {
             ItemsInner::Lazy {
                 tree,
-                back_leaf,
-                back_idx,
+                front,
+                back,
                 remaining,
-                start_bound,
+                behind,
                 ..
             } => {
-                let leaf = (*back_leaf)?;
+                if *front == *back {
+                    return None;
+                }
                 unsafe {
-                    let parts = layout::carve_leaf::<K, V>(leaf, &tree.leaf_layout);
-
-                    if *back_idx > 0 {
-                        *back_idx -= 1;
-                        let k = &*(parts.keys_ptr.add(*back_idx) as *const K);
-
-                        // Check start bound
-                        let within_bound = match start_bound {
-                            Bound::Unbounded => true,
-                            Bound::Included(s) => k >= s,
-                            Bound::Excluded(s) => k > s,
-                        };
-
-                        if !within_bound {
-                            *back_leaf = None;
-                            *remaining = 0;
-                            return None;
+                    let (leaf, idx) = match *back {
+                        None => {
+                            let leaf = tree.rightmost_leaf()?;
+                            if let Some(off) = tree.leaf_layout.prev_off {
+                                tree.prefetch_ahead(behind, leaf, off);
+                            }
+                            let parts = layout::carve_leaf::<K, V>(leaf, &tree.leaf_layout);
+                            (leaf, ((*parts.hdr).len as usize).checked_sub(1)?)
                         }
-
-                        let v = &*(parts.vals_ptr.add(*back_idx) as *const V);
-                        if *remaining > 0 {
-                            *remaining -= 1;
+                        Some((leaf, 0)) => {
+                            let prev = tree.prev_leaf(leaf)?;
+                            if let Some(off) = tree.leaf_layout.prev_off {
+                                tree.prefetch_ahead(behind, prev, off);
+                            }
+                            let prev_parts = layout::carve_leaf::<K, V>(prev, &tree.leaf_layout);
+                            (prev, (*prev_parts.hdr).len as usize - 1)
                         }
-                        return Some((k, v));
-                    }
-
-                    // Move to previous leaf
-                    let prev_ptr = match parts.prev_ptr {
-                        Some(p) => *p,
-                        None => core::ptr::null_mut(),
+                        Some((leaf, idx)) => (leaf, idx - 1),
                     };
-                    if prev_ptr.is_null() {
-                        *back_leaf = None;
-                        *remaining = 0;
-                        return None;
+                    *back = Some((leaf, idx));
+                    let parts = layout::carve_leaf::<K, V>(leaf, &tree.leaf_layout);
+                    if let Some(n) = remaining {
+                        *n -= 1;
                     }
-
-                    *back_leaf = NonNull::new(prev_ptr);
-                    let prev_parts =
-                        layout::carve_leaf::<K, V>(back_leaf.unwrap(), &tree.leaf_layout);
-                    *back_idx = (*prev_parts.hdr).len as usize;
-                    self.next_back()
+                    Some((
+                        &*(parts.keys_ptr.add(idx) as *const K),
+                        &*(parts.vals_ptr.add(idx) as *const V),
+                    ))
                 }
             }
             ItemsInner::Vec { inner } => inner.next_back(),
@@ -226,11 +129,11 @@ impl<'a, K: Ord, V> DoubleEndedIterator for Items<'a, K, V> {
     }
 }
 
-pub struct Keys<'a, K, V> {
-    pub(crate) inner: Items<'a, K, V>,
+pub struct Keys<'a, K, V, C = OrdComparator> {
+    pub(crate) inner: Items<'a, K, V, C>,
 }
 
-impl<'a, K: Ord, V> Iterator for Keys<'a, K, V> {
+impl<'a, K, V, C: Comparator<K>> Iterator for Keys<'a, K, V, C> {
     type Item = &'a K;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -242,17 +145,17 @@ impl<'a, K: Ord, V> Iterator for Keys<'a, K, V> {
     }
 }
 
-impl<'a, K: Ord, V> DoubleEndedIterator for Keys<'a, K, V> {
+impl<'a, K, V, C: Comparator<K>> DoubleEndedIterator for Keys<'a, K, V, C> {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|(k, _)| k)
     }
 }
 
-pub struct Values<'a, K, V> {
-    pub(crate) inner: Items<'a, K, V>,
+pub struct Values<'a, K, V, C = OrdComparator> {
+    pub(crate) inner: Items<'a, K, V, C>,
 }
 
-impl<'a, K: Ord, V> Iterator for Values<'a, K, V> {
+impl<'a, K, V, C: Comparator<K>> Iterator for Values<'a, K, V, C> {
     type Item = &'a V;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -264,70 +167,46 @@ impl<'a, K: Ord, V> Iterator for Values<'a, K, V> {
     }
 }
 
-impl<'a, K: Ord, V> DoubleEndedIterator for Values<'a, K, V> {
+impl<'a, K, V, C: Comparator<K>> DoubleEndedIterator for Values<'a, K, V, C> {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|(_, v)| v)
     }
 }
 
-impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
-    pub fn items(&self) -> Items<'_, K, V> {
-        let len = self.len();
-        if len == 0 {
-            return Items {
-                inner: ItemsInner::Lazy {
-                    tree: self,
-                    front_leaf: None,
-                    front_idx: 0,
-                    back_leaf: None,
-                    back_idx: 0,
-                    remaining: 0,
-                    start_bound: Bound::Unbounded,
-                    end_bound: Bound::Unbounded,
-                    initialized: true,
-                },
-            };
+impl<K, V, C: Comparator<K>> BPlusTreeMap<K, V, C> {
+    pub fn items(&self) -> Items<'_, K, V, C> {
+        let front = self
+            .leftmost_leaf()
+            .and_then(|leaf| self.canonical_position(leaf, 0));
+        let mut ahead = Lookahead::Idle;
+        if let Some((leaf, _)) = front {
+            self.prefetch_ahead(&mut ahead, leaf, self.leaf_layout.next_off);
         }
-
-        let front_leaf = self.leftmost_leaf();
-        let back_leaf = self.rightmost_leaf();
-        let back_idx = if let Some(leaf) = back_leaf {
-            unsafe {
-                let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
-                (*parts.hdr).len as usize
-            }
-        } else {
-            0
-        };
-
         Items {
             inner: ItemsInner::Lazy {
                 tree: self,
-                front_leaf,
-                front_idx: 0,
-                back_leaf,
-                back_idx,
-                remaining: len,
-                start_bound: Bound::Unbounded,
-                end_bound: Bound::Unbounded,
-                initialized: true,
+                front,
+                back: None,
+                remaining: Some(self.len()),
+                ahead,
+                behind: Lookahead::Idle,
             },
         }
     }
 
-    pub fn keys(&self) -> Keys<'_, K, V> {
+    pub fn keys(&self) -> Keys<'_, K, V, C> {
         Keys {
             inner: self.items(),
         }
     }
 
-    pub fn values(&self) -> Values<'_, K, V> {
+    pub fn values(&self) -> Values<'_, K, V, C> {
         Values {
             inner: self.items(),
         }
     }
 
-    pub fn items_range(&self, start: Option<&K>, end: Option<&K>) -> Items<'_, K, V> {
+    pub fn items_range(&self, start: Option<&K>, end: Option<&K>) -> Items<'_, K, V, C> {
         // TODO: Implement lazy range iteration
         // For now, collect into Vec (old implementation)
         let sb = start.map_or(Bound::Unbounded, Bound::Included);
@@ -339,39 +218,152 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
         }
     }
 
-    pub fn range<R: RangeBounds<K>>(&self, r: R) -> Items<'_, K, V> {
-        let start_bound = r.start_bound();
-        let end_bound = r.end_bound();
-
+    /// Both ends are located up front, so iterating from either side costs
+    /// one descent plus the entries yielded.
+    pub fn range<R: RangeBounds<K>>(&self, r: R) -> Items<'_, K, V, C> {
+        let mut front = self.bound_position(r.start_bound(), true);
+        let mut back = self.bound_position(r.end_bound(), false);
+        // A start past the end bound (including start > end) is empty.
+        if let Some((leaf, idx)) = front {
+            let k = unsafe {
+                let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
+                &*(parts.keys_ptr.add(idx) as *const K)
+            };
+            let within = match r.end_bound() {
+                Bound::Unbounded => true,
+                Bound::Included(e) => self.cmp.compare(k, e).is_le(),
+                Bound::Excluded(e) => self.cmp.compare(k, e).is_lt(),
+            };
+            if !within {
+                front = None;
+            }
+        }
+        if front.is_none() {
+            back = None;
+        }
+        let mut ahead = Lookahead::Idle;
+        if let Some((leaf, _)) = front {
+            self.prefetch_ahead(&mut ahead, leaf, self.leaf_layout.next_off);
+        }
         Items {
             inner: ItemsInner::Lazy {
                 tree: self,
-                front_leaf: None,
-                front_idx: 0,
-                back_leaf: None,
-                back_idx: 0,
-                remaining: 0, // Unknown for ranges, size_hint will return (0, None)
-                start_bound: Self::clone_bound(start_bound),
-                end_bound: Self::clone_bound(end_bound),
-                initialized: false,
+                front,
+                back,
+                remaining: None,
+                ahead,
+                behind: Lookahead::Idle,
             },
         }
     }
 
-    fn clone_bound(bound: Bound<&K>) -> Bound<K> {
-        match bound {
-            Bound::Unbounded => Bound::Unbounded,
-            Bound::Included(k) => Bound::Included(k.clone()),
-            Bound::Excluded(k) => Bound::Excluded(k.clone()),
+    /// First entry at or after a range bound: the first entry inside it for
+    /// a start bound, the first entry past it for an end bound.
+    pub(crate) fn bound_position(&self, bound: Bound<&K>, is_start: bool) -> Option<Position> {
+        let (key, skip_equal) = match bound {
+            Bound::Unbounded if is_start => {
+                return self.canonical_position(self.leftmost_leaf()?, 0)
+            }
+            Bound::Unbounded => return None,
+            Bound::Included(k) => (k, !is_start),
+            Bound::Excluded(k) => (k, is_start),
+        };
+        let leaf = self.leaf_for_key(key)?;
+        let idx = unsafe {
+            let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
+            let len = (*parts.hdr).len as usize;
+            let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
+            match self.binary_search_keys(keys, key) {
+                Ok(i) if skip_equal => i + 1,
+                Ok(i) | Err(i) => i,
+            }
+        };
+        self.canonical_position(leaf, idx)
+    }
+
+    /// Last entry before `key`, or at it if `inclusive`.
+    pub(crate) fn position_before(&self, key: &K, inclusive: bool) -> Option<Position> {
+        let leaf = self.leaf_for_key(key)?;
+        unsafe {
+            let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
+            let len = (*parts.hdr).len as usize;
+            if len == 0 {
+                return None;
+            }
+            let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
+            let idx = match self.binary_search_keys(keys, key) {
+                Ok(i) if inclusive => i + 1,
+                Ok(i) | Err(i) => i,
+            };
+            if idx > 0 {
+                return Some((leaf, idx - 1));
+            }
+            // Every key here is past the bound, so the entry ends the
+            // previous leaf.
+            let prev = self.prev_leaf(leaf)?;
+            let prev_len = (*(prev.as_ptr() as *const NodeHdr)).len as usize;
+            Some((prev, prev_len - 1))
+        }
+    }
+
+    pub(crate) fn entry_at(&self, (leaf, idx): Position) -> (&K, &V) {
+        unsafe {
+            let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
+            (
+                &*(parts.keys_ptr.add(idx) as *const K),
+                &*(parts.vals_ptr.add(idx) as *const V),
+            )
+        }
+    }
+
+    /// Normalize `(leaf, idx)` to a real entry, moving to the next leaf when
+    /// `idx` is at the end; `None` past the last leaf.
+    pub(crate) fn canonical_position(&self, leaf: NonNull<u8>, idx: usize) -> Option<Position> {
+        unsafe {
+            let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
+            if idx < (*parts.hdr).len as usize {
+                return Some((leaf, idx));
+            }
+            // Only an empty root leaf has no entries, and it has no successor.
+            let next = NonNull::new(*parts.next_ptr)?;
+            self.visit(next);
+            Some((next, 0))
         }
     }
 
     pub fn first(&self) -> Option<(&K, &V)> {
-        self.items().next()
+        Some(self.entry_at(self.canonical_position(self.leftmost_leaf()?, 0)?))
     }
 
     pub fn last(&self) -> Option<(&K, &V)> {
-        self.items().last()
+        let leaf = self.rightmost_leaf()?;
+        let len = unsafe { (*(leaf.as_ptr() as *const NodeHdr)).len as usize };
+        Some(self.entry_at((leaf, len.checked_sub(1)?)))
+    }
+
+    // The neighbour queries take one descent to the leaf that would hold
+    // `key`; when the answer is past either end of that leaf it is at the
+    // near end of the sibling. Without back links (`doubly_linked(false)`)
+    // reaching the previous leaf costs a second descent.
+
+    /// The entry with the greatest key `<= key`.
+    pub fn floor(&self, key: &K) -> Option<(&K, &V)> {
+        Some(self.entry_at(self.position_before(key, true)?))
+    }
+
+    /// The entry with the least key `>= key`.
+    pub fn ceiling(&self, key: &K) -> Option<(&K, &V)> {
+        Some(self.entry_at(self.bound_position(Bound::Included(key), true)?))
+    }
+
+    /// The entry with the greatest key `< key`.
+    pub fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        Some(self.entry_at(self.position_before(key, false)?))
+    }
+
+    /// The entry with the least key `> key`.
+    pub fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        Some(self.entry_at(self.bound_position(Bound::Excluded(key), true)?))
     }
 
     pub(crate) fn collect_range_bounds<'a>(
@@ -423,8 +415,8 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
                     let kref = &*keys_ptr.add(i);
                     let end_ok = match end {
                         Bound::Unbounded => true,
-                        Bound::Included(e) => kref <= e,
-                        Bound::Excluded(e) => kref < e,
+                        Bound::Included(e) => self.cmp.compare(kref, e).is_le(),
+                        Bound::Excluded(e) => self.cmp.compare(kref, e).is_lt(),
                     };
                     if !end_ok {
                         return out;
@@ -8,6 +8,18 @@ pub const fn align_up(x: usize, a: usize) -> usize {
     (x + (a - 1)) & !(a - 1)
 }
 
+/// Offset and end of an array of `len` elements of size `size` and alignment
+/// `align` placed at or after `at`. An array of a zero-sized type takes no
+/// space and points at the node base, which is aligned for any member.
+#[inline]
+const fn place_array(at: usize, align: usize, size: usize, len: usize) -> (usize, usize) {
+    if size == 0 {
+        return (0, at);
+    }
+    let off = align_up(at, align);
+    (off, off + len * size)
+}
+
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum NodeTag {
@@ -20,7 +32,16 @@ pub enum NodeTag {
 pub struct NodeHdr {
     pub tag: NodeTag, // 1 byte
     pub len: u16,     // number of initialized keys in this node
-    pub flags: u8,    // reserved
+    pub flags: u8,    // see NodeHdr::UNDERFULL
+}
+
+impl NodeHdr {
+    /// Flag on a node a deferred delete left underfull, awaiting
+    /// `BPlusTreeMap::compact`.
+    pub const UNDERFULL: u8 = 1;
+    /// Flag on an edge node a skewed split left underfull, cleared once
+    /// inserts or borrows fill it to the merge threshold or it merges.
+    pub const SKEWED: u8 = 2;
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -65,8 +86,12 @@ impl LeafLayout {
         let sib_off = align_up(hdr_size, a_ptr);
         let after_sib = sib_off + sib_bytes;
 
-        // quick upper bound, ignoring alignment between arrays
-        let mut cap_guess = bytes.saturating_sub(after_sib) / (s_k + s_v);
+        // quick upper bound, ignoring alignment between arrays; any number
+        // of zero-sized entries fits
+        let mut cap_guess = match s_k + s_v {
+            0 => u16::MAX as usize,
+            entry => bytes.saturating_sub(after_sib) / entry,
+        };
         if cap_guess > u16::MAX as usize {
             cap_guess = u16::MAX as usize;
         }
@@ -94,9 +119,8 @@ impl LeafLayout {
                 (a_v, s_v, a_k, s_k)
             };
 
-            let first_off = align_up(after_sib, a1);
-            let second_off = align_up(first_off + cap_guess * s1, a2);
-            let end = second_off + cap_guess * s2;
+            let (first_off, first_end) = place_array(after_sib, a1, s1, cap_guess);
+            let (second_off, end) = place_array(first_end, a2, s2, cap_guess);
             let end_aligned = align_up(end, max_align);
 
             if end_aligned <= bytes {
@@ -140,9 +164,8 @@ impl LeafLayout {
             (a_v, s_v, a_k, s_k)
         };
 
-        let first_off = align_up(after_sib, a1);
-        let second_off = align_up(first_off + cap_usize * s1, a2);
-        let end = second_off + cap_usize * s2;
+        let (first_off, first_end) = place_array(after_sib, a1, s1, cap_usize);
+        let (second_off, end) = place_array(first_end, a2, s2, cap_usize);
         let end_aligned = align_up(end, max_align);
 
         let (keys_off, vals_off) = if first_is_keys {
@@ -219,9 +242,8 @@ impl BranchLayout {
                 cap_guess + 1
             };
 
-            let first_off = align_up(hdr_size, first_a);
-            let second_off = align_up(first_off + first_len * first_s, second_a);
-            let end = second_off + second_len * second_s;
+            let (first_off, first_end) = place_array(hdr_size, first_a, first_s, first_len);
+            let (second_off, end) = place_array(first_end, second_a, second_s, second_len);
             let end_aligned = align_up(end, max_align);
 
             if end_aligned <= bytes {
@@ -267,9 +289,8 @@ impl BranchLayout {
             cap as usize + 1
         };
 
-        let first_off = align_up(hdr_size, first_a);
-        let second_off = align_up(first_off + first_len * first_s, second_a);
-        let end = second_off + second_len * second_s;
+        let (first_off, first_end) = place_array(hdr_size, first_a, first_s, first_len);
+        let (second_off, end) = place_array(first_end, second_a, second_s, second_len);
         let end_aligned = align_up(end, max_align);
 
         let (children_off, keys_off) = if children_first {
@@ -314,6 +335,10 @@ pub struct BranchParts<K> {
 impl<K> BranchParts<K> {}
 
 /// Carve a leaf node's header, sibling pointers, and arrays from a raw base pointer.
+///
+/// # Safety
+/// `base` must point to a block of at least `layout.bytes` bytes aligned to
+/// `layout.max_align`.
 #[inline(always)]
 pub unsafe fn carve_leaf<K, V>(base: NonNull<u8>, layout: &LeafLayout) -> LeafParts<K, V> {
     let p = base.as_ptr();
@@ -332,6 +357,9 @@ pub unsafe fn carve_leaf<K, V>(base: NonNull<u8>, layout: &LeafLayout) -> LeafPa
 }
 
 /// Carve a branch node's header, children pointers, and keys array from a raw base pointer.
+///
+/// # Safety
+/// As for [`carve_leaf`], with a branch layout.
 #[inline(always)]
 pub unsafe fn carve_branch<K>(base: NonNull<u8>, layout: &BranchLayout) -> BranchParts<K> {
     let p = base.as_ptr();
@@ -1,30 +1,83 @@
 #![no_std]
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+use core::cell::Cell;
 use core::marker::PhantomData;
 use core::ptr::{self, NonNull};
 
+mod build;
+mod bytes_map;
+pub mod checksum;
 mod common;
+mod compare;
+mod config;
 mod delete;
+#[cfg(feature = "std")]
+pub mod disk;
+mod dump;
+mod finger;
+mod frozen;
 mod get;
 mod insert;
+mod interval;
+mod invariant;
 mod iterate;
 mod layout;
+mod multimap;
 mod node_alloc;
-
+mod pod;
+mod prefetch;
+mod rebalance;
+mod search;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod set;
+#[cfg(feature = "std")]
+mod snapshot;
+mod split;
+mod stats;
+mod var_map;
+mod versioned;
+
+pub use bytes_map::{BytesBPlusTreeMap, BytesRange};
+pub use compare::{Comparator, DeriveSeparator, OrdComparator, SeparatorKey, SeparatorOrd};
+pub use config::{NodeSize, TreeConfig};
+pub use dump::DumpOptions;
+pub use frozen::{FrozenBPlusTree, FrozenRange};
+pub use interval::{BPlusIntervalMap, Overlapping};
+pub use invariant::{InvariantViolation, LeafLink};
 pub use iterate::{Items, Keys, Values};
-pub use layout::{align_up, BranchLayout, LeafLayout, NodeHdr, NodeTag};
+pub use layout::{
+    align_up, carve_branch, carve_leaf, BranchLayout, BranchParts, LeafLayout, LeafParts, NodeHdr,
+    NodeTag,
+};
+pub use multimap::{BPlusTreeMultiMap, GetAll};
 pub use node_alloc::{
     alloc_branch_block, alloc_leaf_block, alloc_raw, dealloc_raw, init_branch_block,
     init_leaf_block,
 };
+pub use pod::Pod;
+pub use rebalance::MergeThreshold;
+pub use search::{branchless_search, SearchKey};
+#[cfg(feature = "serde")]
+pub use serde_impl::BPlusTreeMapSeed;
+pub use set::{BPlusTreeSet, Difference, Intersection, SymmetricDifference, Union};
+#[cfg(feature = "std")]
+pub use snapshot::SnapshotCodec;
+pub use split::SplitPolicy;
+pub use stats::TreeStats;
+pub use var_map::{VarBPlusTreeMap, VarCodec, VarKey, VarRange};
+pub use versioned::{ReadView, Version, VersionChain, VersionedBPlusTreeMap, VersionedItems};
 
 /// Raw-memory B+ tree map with fixed-size leaf and branch nodes.
 ///
 /// This type only defines the top-level container and precomputed layouts.
 /// Nodes are single raw allocations carved according to these layouts.
-pub struct BPlusTreeMap<K, V> {
+/// Keys are ordered by the comparator `C`, by default their `Ord` order.
+pub struct BPlusTreeMap<K, V, C = OrdComparator> {
     /// Root node (points to a node header at offset 0), or None if empty.
     root: Option<NonNull<u8>>,
 
@@ -32,10 +85,40 @@ pub struct BPlusTreeMap<K, V> {
     leaf_layout: LeafLayout,
     branch_layout: BranchLayout,
 
+    /// Key order used by every search, bound check and validation.
+    cmp: C,
+
+    /// Specialized in-node search, if enabled via `enable_key_search`.
+    search: Option<SearchFn<K>>,
+
+    /// Leaves range iterators prefetch ahead; 0 disables prefetching.
+    prefetch_distance: usize,
+
+    /// Called for each node a descent or sibling hop reaches.
+    visit_hook: Option<fn(NodeTag)>,
+
+    /// Last leaf reached by `get` or `insert`, with its bounds.
+    finger: finger::FingerCell<K>,
+
+    /// Split policy, and the state it tracks across inserts.
+    split: split::SplitState,
+
+    /// When deletes rebalance underfull nodes.
+    underflow: rebalance::Underflow,
+
+    /// Whether equal keys may repeat, as in a [`BPlusTreeMultiMap`].
+    duplicates: bool,
+
+    /// Options the tree was built with; the split policy and merge
+    /// threshold in effect are kept in `split` and `underflow`.
+    config: TreeConfig,
+
     _marker: PhantomData<(K, V)>,
 }
 
-impl<K, V> Drop for BPlusTreeMap<K, V> {
+type SearchFn<K> = fn(&[K], &K) -> Result<usize, usize>;
+
+impl<K, V, C> Drop for BPlusTreeMap<K, V, C> {
     fn drop(&mut self) {
         if let Some(root) = self.root.take() {
             unsafe {
@@ -51,15 +134,15 @@ impl<K, V> BPlusTreeMap<K, V> {
 
     /// Construct with explicit byte budgets for leaves and branches.
     /// Doubly-linked leaves are used to support reverse iteration efficiently.
+    /// The budgets are not checked; [`TreeConfig::build`] rejects budgets
+    /// that hold too few or too many entries.
     pub fn with_budgets(leaf_bytes: usize, branch_bytes: usize) -> Self {
+        let config = TreeConfig::new()
+            .leaf_bytes(leaf_bytes)
+            .branch_bytes(branch_bytes);
         let leaf_layout = LeafLayout::compute::<K, V>(leaf_bytes, true);
         let branch_layout = BranchLayout::compute::<K>(branch_bytes);
-        Self {
-            root: None,
-            leaf_layout,
-            branch_layout,
-            _marker: PhantomData,
-        }
+        Self::from_layouts(config, leaf_layout, branch_layout, OrdComparator)
     }
 
     /// Construct using cache-line counts for leaf and branch nodes.
@@ -70,6 +153,76 @@ impl<K, V> BPlusTreeMap<K, V> {
         Self::with_budgets(lb, bb)
     }
 
+    /// Route in-node key search through [`SearchKey::search`] instead of the
+    /// generic `slice::binary_search`.
+    pub fn enable_key_search(&mut self)
+    where
+        K: SearchKey,
+    {
+        self.search = Some(K::searcher());
+    }
+}
+
+impl<K, V, C> BPlusTreeMap<K, V, C> {
+    /// An empty tree without a root, with layouts already computed from
+    /// `config`.
+    pub(crate) fn from_layouts(
+        config: TreeConfig,
+        leaf_layout: LeafLayout,
+        branch_layout: BranchLayout,
+        cmp: C,
+    ) -> Self {
+        let mut split = split::SplitState::new();
+        split.policy = config.split_policy;
+        let mut underflow = rebalance::Underflow::new();
+        underflow.threshold = config.merge_threshold;
+        Self {
+            root: None,
+            leaf_layout,
+            branch_layout,
+            cmp,
+            search: None,
+            prefetch_distance: 0,
+            visit_hook: None,
+            finger: Cell::new(None),
+            split,
+            underflow,
+            duplicates: false,
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The comparator ordering this map's keys.
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    /// Whether [`BPlusTreeMap::enable_key_search`] is in effect.
+    pub fn uses_key_search(&self) -> bool {
+        self.search.is_some()
+    }
+
+    /// Prefetch `leaves` leaves ahead along the sibling chain in range
+    /// iterators, and interleave the descents of [`BPlusTreeMap::get_many`].
+    /// 0, the default, disables software prefetching.
+    pub fn set_prefetch_distance(&mut self, leaves: usize) {
+        self.prefetch_distance = leaves;
+    }
+
+    /// The distance set by [`BPlusTreeMap::set_prefetch_distance`].
+    pub fn prefetch_distance(&self) -> usize {
+        self.prefetch_distance
+    }
+
+    /// Call `hook` with the kind of each node that `get`, neighbour queries
+    /// and iterators step into, for counting the nodes an operation touches.
+    /// Batched lookups, inserts and removes are not reported. `None` removes
+    /// the hook.
+    pub fn set_visit_hook(&mut self, hook: Option<fn(NodeTag)>) {
+        self.visit_hook = hook;
+    }
+
     /// Returns the configured layout for leaf nodes.
     pub fn leaf_layout(&self) -> &LeafLayout {
         &self.leaf_layout
@@ -143,6 +296,7 @@ pub enum BPlusTreeError {
     CorruptedTree(String),
     InvalidState(String),
     AllocationError(String),
+    IoError(String),
 }
 
 impl fmt::Display for BPlusTreeError {
@@ -156,12 +310,20 @@ impl fmt::Display for BPlusTreeError {
             BPlusTreeError::CorruptedTree(s) => write!(f, "CorruptedTree: {}", s),
             BPlusTreeError::InvalidState(s) => write!(f, "InvalidState: {}", s),
             BPlusTreeError::AllocationError(s) => write!(f, "AllocationError: {}", s),
+            BPlusTreeError::IoError(s) => write!(f, "IoError: {}", s),
         }
     }
 }
 
 impl core::error::Error for BPlusTreeError {}
 
+#[cfg(feature = "std")]
+impl From<std::io::Error> for BPlusTreeError {
+    fn from(e: std::io::Error) -> Self {
+        BPlusTreeError::IoError(format!("{}", e))
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum NodeRef<K, V> {
     Leaf(u32, PhantomData<(K, V)>),
@@ -179,27 +341,22 @@ impl<K, V> NodeRef<K, V> {
     }
 }
 
-impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
+impl<K: Ord, V> BPlusTreeMap<K, V> {
     // ===== Compatibility constructors =====
+    /// An empty tree with `capacity` for both node kinds and its root leaf
+    /// already allocated; see [`TreeConfig`] for other options.
     pub fn new(capacity: usize) -> Result<Self, BPlusTreeError> {
-        if capacity < 4 {
-            return Err(BPlusTreeError::InvalidCapacity("capacity too small".into()));
-        }
-        let cap_u16 = core::cmp::min(capacity as u16, u16::MAX);
-        let leaf_layout = LeafLayout::compute_for_cap::<K, V>(cap_u16, true);
-        let branch_layout = BranchLayout::compute_for_cap::<K>(cap_u16);
-        let mut tree = Self {
-            root: None,
-            leaf_layout,
-            branch_layout,
-            _marker: PhantomData,
-        };
-        unsafe {
-            let leaf = alloc_leaf_block(&tree.leaf_layout)
-                .ok_or_else(|| BPlusTreeError::AllocationError("leaf root".into()))?;
-            tree.root = Some(leaf);
-        }
-        Ok(tree)
+        Self::with_comparator(capacity, OrdComparator)
+    }
+}
+
+impl<K, V, C: Comparator<K>> BPlusTreeMap<K, V, C> {
+    /// Like [`BPlusTreeMap::new`], ordering keys with `cmp` instead of `Ord`.
+    pub fn with_comparator(capacity: usize, cmp: C) -> Result<Self, BPlusTreeError> {
+        TreeConfig::new()
+            .capacity(capacity)
+            .eager_root(true)
+            .build_with_comparator(cmp)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -229,6 +386,7 @@ impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
     }
 
     pub fn clear(&mut self) {
+        self.drop_finger();
         if let Some(root) = self.root.take() {
             unsafe {
                 self.free_tree_no_drop(root);
@@ -339,6 +497,9 @@ impl BPlusTreeError {
     pub fn allocation_error(what: &str, why: &str) -> Self {
         BPlusTreeError::AllocationError(format!("Failed to allocate {}: {}", what, why))
     }
+    pub fn io_error(op: &str, why: &str) -> Self {
+        BPlusTreeError::IoError(format!("{} failed: {}", op, why))
+    }
 }
 
 impl core::cmp::PartialEq for BPlusTreeError {
@@ -350,7 +511,7 @@ impl Eq for BPlusTreeError {}
 
 // Extra convenience/debug API stubs used in tests
 #[cfg(feature = "compat_test_api")]
-impl<K: Ord + Clone, V> BPlusTreeMap<K, V> {
+impl<K, V, C: DeriveSeparator<K>> BPlusTreeMap<K, V, C> {
     pub fn validate(&self) -> BTreeResult<()> {
         Ok(())
     }
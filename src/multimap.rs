@@ -0,0 +1,149 @@
+//! Map from keys to any number of values, stored as duplicate keys.
+//!
+//! A [`BPlusTreeMultiMap`] keeps one leaf entry per value, with equal keys
+//! adjacent in insertion order, so a key with many values costs no separate
+//! allocation. Equal keys may fill several leaves: separators then bound
+//! their subtrees inclusively on both sides, inserts descend past every
+//! separator equal to the key to append after its last value, and lookups
+//! descend to the first.
+
+use alloc::vec::Vec;
+
+use crate::iterate::Position;
+use crate::layout;
+use crate::{
+    BPlusTreeError, BPlusTreeMap, InvariantViolation, Items, NodeHdr, NodeTag, OrdComparator,
+};
+
+/// Ordered multimap: each key maps to its values in insertion order.
+pub struct BPlusTreeMultiMap<K, V> {
+    map: BPlusTreeMap<K, V>,
+}
+
+impl<K: Ord, V> BPlusTreeMultiMap<K, V> {
+    /// An empty multimap with `capacity` for both node kinds.
+    pub fn new(capacity: usize) -> Result<Self, BPlusTreeError> {
+        let mut map = BPlusTreeMap::with_comparator(capacity, OrdComparator)?;
+        map.duplicates = true;
+        Ok(Self { map })
+    }
+
+    /// The underlying tree, for its configuration, statistics and validation.
+    pub fn as_map(&self) -> &BPlusTreeMap<K, V> {
+        &self.map
+    }
+
+    /// The values of `key`, oldest first.
+    pub fn get_all(&self, key: &K) -> GetAll<'_, K, V> {
+        let pos = self.first_position(key);
+        GetAll {
+            map: &self.map,
+            key: pos.map(|p| self.key_at(p)),
+            pos,
+        }
+    }
+
+    /// Number of values `key` has.
+    pub fn count(&self, key: &K) -> usize {
+        self.get_all(key).count()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.first_position(key).is_some()
+    }
+
+    /// Number of values across all keys.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Every `(key, value)` pair in key order, each key's values oldest
+    /// first.
+    pub fn iter(&self) -> Items<'_, K, V> {
+        self.map.items()
+    }
+
+    pub fn check_invariants_detailed(&self) -> Result<(), InvariantViolation> {
+        self.map.check_invariants_detailed()
+    }
+
+    /// Position of the first entry for `key`, if it has any.
+    fn first_position(&self, key: &K) -> Option<Position> {
+        let map = &self.map;
+        let mut cur = map.root?;
+        unsafe {
+            while (*(cur.as_ptr() as *const NodeHdr)).tag == NodeTag::Branch {
+                cur = map.first_child_for_key(cur, key)?.0;
+            }
+            let parts = layout::carve_leaf::<K, V>(cur, &map.leaf_layout);
+            let len = (*parts.hdr).len as usize;
+            let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
+            // Past this leaf's keys, the first equal key can only open the
+            // next leaf.
+            let pos = map.canonical_position(cur, map.partition_keys(keys, key, false))?;
+            (self.key_at(pos) == key).then_some(pos)
+        }
+    }
+
+    fn key_at(&self, (leaf, idx): Position) -> &K {
+        unsafe {
+            let parts = layout::carve_leaf::<K, V>(leaf, &self.map.leaf_layout);
+            &*(parts.keys_ptr.add(idx) as *const K)
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> BPlusTreeMultiMap<K, V> {
+    /// Add `value` after any values `key` already has.
+    pub fn insert(&mut self, key: K, value: V) {
+        let replaced = self.map.insert(key, value);
+        debug_assert!(replaced.is_none());
+    }
+
+    /// Remove and return the oldest value of `key`.
+    pub fn remove_one(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    /// Remove every value of `key`, returning them oldest first.
+    pub fn remove_all(&mut self, key: &K) -> Vec<V> {
+        let mut out = Vec::new();
+        while let Some(v) = self.map.remove(key) {
+            out.push(v);
+        }
+        out
+    }
+}
+
+/// Iterator over the values of one key, from [`BPlusTreeMultiMap::get_all`].
+pub struct GetAll<'a, K, V> {
+    map: &'a BPlusTreeMap<K, V>,
+    /// The key, as stored in the tree.
+    key: Option<&'a K>,
+    pos: Option<Position>,
+}
+
+impl<'a, K: Ord, V> Iterator for GetAll<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        let (leaf, idx) = self.pos?;
+        unsafe {
+            let parts = layout::carve_leaf::<K, V>(leaf, &self.map.leaf_layout);
+            if Some(&*(parts.keys_ptr.add(idx) as *const K)) != self.key {
+                self.pos = None;
+                return None;
+            }
+            self.pos = self.map.canonical_position(leaf, idx + 1);
+            Some(&*(parts.vals_ptr.add(idx) as *const V))
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Plain-old-data marker for types stored as raw byte images.
+
+use core::mem::size_of;
+
+/// Plain-old-data types with a fixed-width, pointer-free byte image.
+///
+/// # Safety
+/// Implementors must have no padding, no pointers or references, and must be
+/// valid for every bit pattern of `size_of::<Self>()` bytes, since values are
+/// read back from untrusted page images.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => { $(unsafe impl Pod for $t {})* };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Read a `T` from the start of `bytes` (no alignment requirement).
+#[inline(always)]
+pub(crate) fn pod_read<T: Pod>(bytes: &[u8]) -> T {
+    assert!(bytes.len() >= size_of::<T>());
+    // SAFETY: length checked above; Pod guarantees any bit pattern is valid.
+    unsafe { core::ptr::read_unaligned(bytes.as_ptr() as *const T) }
+}
+
+/// Write `value` to the start of `bytes` (no alignment requirement).
+#[inline(always)]
+pub(crate) fn pod_write<T: Pod>(bytes: &mut [u8], value: T) {
+    assert!(bytes.len() >= size_of::<T>());
+    // SAFETY: length checked above.
+    unsafe { core::ptr::write_unaligned(bytes.as_mut_ptr() as *mut T, value) }
+}
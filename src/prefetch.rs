@@ -0,0 +1,144 @@
+//! Software prefetching for the pointer hops between nodes.
+//!
+//! A range scan follows `next_ptr` links and a descent follows child
+//! pointers; on a tree larger than the cache each hop is a miss the CPU
+//! cannot predict. With a nonzero [`BPlusTreeMap::prefetch_distance`],
+//! range iterators keep that many leaves requested ahead along the sibling
+//! chain, one more link each time they enter a leaf, and [`BPlusTreeMap::get_many`] descends a batch of
+//! keys one level at a time, prefetching every child before reading any.
+//!
+//! The hints use `core::arch` on x86_64 and aarch64 and compile to nothing
+//! elsewhere. Compare with the `bench_range` and `bench_iterate` bins before
+//! enabling prefetching: nodes allocated in key order are often already
+//! covered by the hardware prefetcher.
+
+use core::ptr::NonNull;
+
+use crate::{BPlusTreeMap, Comparator, NodeHdr, NodeTag};
+
+/// Keys whose descents [`BPlusTreeMap::get_many`] interleaves.
+pub(crate) const BATCH: usize = 16;
+
+/// Hint that the cache line holding `p` will be read soon. Never faults,
+/// even for dangling pointers.
+#[inline(always)]
+pub(crate) fn prefetch_read(p: *const u8) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(p as *const i8);
+    }
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        core::arch::asm!(
+            "prfm pldl1keep, [{0}]",
+            in(reg) p,
+            options(nostack, readonly, preserves_flags)
+        );
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let _ = p;
+}
+
+impl<K, V, C> BPlusTreeMap<K, V, C> {
+    /// Prefetch the lines of `leaf` a scan touches first: the header with
+    /// the sibling links, and the starts of the key and value arrays.
+    #[inline(always)]
+    pub(crate) fn prefetch_leaf(&self, leaf: NonNull<u8>) {
+        let p = leaf.as_ptr() as *const u8;
+        prefetch_read(p);
+        prefetch_read(p.wrapping_add(self.leaf_layout.keys_off));
+        prefetch_read(p.wrapping_add(self.leaf_layout.vals_off));
+    }
+
+    /// Prefetch the lines of `branch` a descent touches first: the header,
+    /// the middle key probed by the search, and the children array.
+    #[inline(always)]
+    pub(crate) fn prefetch_branch(&self, branch: NonNull<u8>) {
+        let p = branch.as_ptr() as *const u8;
+        let l = &self.branch_layout;
+        let mid = l.keys_off + (l.cap as usize / 2) * core::mem::size_of::<K>();
+        prefetch_read(p);
+        prefetch_read(p.wrapping_add(mid));
+        prefetch_read(p.wrapping_add(l.children_off));
+    }
+
+    /// Keep `ahead` `prefetch_distance` leaves past `leaf`, which an
+    /// iterator is entering by following the link at `link_off` (`next_off`
+    /// or `prev_off`). The first call requests every leaf up to that
+    /// distance; later ones only the leaf past the furthest requested, whose
+    /// link was prefetched along with it.
+    #[inline]
+    pub(crate) fn prefetch_ahead(&self, ahead: &mut Lookahead, leaf: NonNull<u8>, link_off: usize) {
+        let link = |node: NonNull<u8>| unsafe {
+            NonNull::new(*(node.as_ptr().add(link_off) as *const *mut u8))
+        };
+        *ahead = match *ahead {
+            Lookahead::Done => return,
+            Lookahead::Idle if self.prefetch_distance == 0 => Lookahead::Done,
+            Lookahead::Idle => {
+                let mut far = leaf;
+                for _ in 0..self.prefetch_distance {
+                    match link(far) {
+                        Some(next) => {
+                            self.prefetch_leaf(next);
+                            far = next;
+                        }
+                        None => {
+                            *ahead = Lookahead::Done;
+                            return;
+                        }
+                    }
+                }
+                Lookahead::At(far)
+            }
+            Lookahead::At(far) => match link(far) {
+                Some(next) => {
+                    self.prefetch_leaf(next);
+                    Lookahead::At(next)
+                }
+                None => Lookahead::Done,
+            },
+        };
+    }
+}
+
+/// How far an iterator has prefetched along one direction of the leaf
+/// chain.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Lookahead {
+    /// No leaf entered yet.
+    Idle,
+    /// The furthest leaf requested.
+    At(NonNull<u8>),
+    /// The chain ended before it, or prefetching is off.
+    Done,
+}
+
+impl<K, V, C: Comparator<K>> BPlusTreeMap<K, V, C> {
+    /// The leaf that may hold each of `keys`, written to the same index of
+    /// `leaves`. Every leaf sits at the same depth, so the descents advance
+    /// in lockstep: each level computes all children first and prefetches
+    /// them, overlapping the misses instead of taking them one at a time.
+    pub(crate) fn leaves_for_keys(&self, keys: &[K], leaves: &mut [Option<NonNull<u8>>]) {
+        debug_assert_eq!(keys.len(), leaves.len());
+        leaves.fill(self.root);
+        let Some(root) = self.root else { return };
+        let mut is_branch = unsafe { (*(root.as_ptr() as *const NodeHdr)).tag == NodeTag::Branch };
+        while is_branch {
+            for (key, slot) in keys.iter().zip(leaves.iter_mut()) {
+                let Some(node) = *slot else { continue };
+                *slot = unsafe { self.child_for_key(node, key) }.map(|(child, _)| child);
+                if let Some(child) = *slot {
+                    self.prefetch_branch(child);
+                }
+            }
+            is_branch = match leaves.iter().flatten().next() {
+                Some(node) => unsafe {
+                    (*(node.as_ptr() as *const NodeHdr)).tag == NodeTag::Branch
+                },
+                None => false,
+            };
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! When deletes rebalance underfull nodes.
+//!
+//! By default a delete that leaves a node below half full immediately
+//! borrows from or merges with a sibling. Alternating inserts and deletes
+//! around that boundary then split and merge the same nodes over and over.
+//! A lower [`MergeThreshold`] widens the gap between the split point (a full
+//! node) and the merge point. In deferred mode deletes only mark underfull
+//! nodes with [`NodeHdr::UNDERFULL`] and [`BPlusTreeMap::compact`] fixes them
+//! later; nodes that become empty are still removed right away, since
+//! iteration relies on every non-root leaf holding an entry.
+
+use core::ptr::NonNull;
+
+use crate::layout;
+use crate::{BPlusTreeMap, DeriveSeparator, NodeHdr, NodeTag};
+
+/// Occupancy below which a delete rebalances a node; see
+/// [`BPlusTreeMap::set_merge_threshold`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergeThreshold {
+    /// Below half full.
+    #[default]
+    Half,
+    /// Below this percentage of capacity, clamped to 50. Nodes keep at
+    /// least one entry.
+    Percent(u8),
+    /// Only once a leaf has no entries or a branch has no separators.
+    Empty,
+}
+
+pub(crate) struct Underflow {
+    pub(crate) threshold: MergeThreshold,
+    pub(crate) deferred: bool,
+}
+
+impl Underflow {
+    pub(crate) const fn new() -> Self {
+        Self {
+            threshold: MergeThreshold::Half,
+            deferred: false,
+        }
+    }
+
+    /// Minimum entries for a non-root node of capacity `cap`.
+    #[inline(always)]
+    pub(crate) fn min_len(&self, cap: usize) -> usize {
+        match self.threshold {
+            MergeThreshold::Half => (cap / 2).max(1),
+            MergeThreshold::Percent(p) => (cap * p.min(50) as usize / 100).max(1),
+            MergeThreshold::Empty => 1,
+        }
+    }
+}
+
+impl<K, V, C> BPlusTreeMap<K, V, C> {
+    pub fn merge_threshold(&self) -> MergeThreshold {
+        self.underflow.threshold
+    }
+
+    /// Whether deletes defer rebalancing to [`BPlusTreeMap::compact`].
+    pub fn deferred_rebalance(&self) -> bool {
+        self.underflow.deferred
+    }
+
+    /// Defer rebalancing of nodes that deletes leave underfull but not empty
+    /// until [`BPlusTreeMap::compact`]. Turning it off leaves the nodes
+    /// already marked for the next `compact`.
+    pub fn set_deferred_rebalance(&mut self, deferred: bool) {
+        self.underflow.deferred = deferred;
+    }
+}
+
+impl<K, V, C: DeriveSeparator<K>> BPlusTreeMap<K, V, C> {
+    /// Whether `node` holds fewer entries than the threshold allows.
+    pub(crate) unsafe fn is_underfull(&self, node: NonNull<u8>) -> bool {
+        let hdr = &*(node.as_ptr() as *const NodeHdr);
+        let min = match hdr.tag {
+            NodeTag::Leaf => self.min_leaf_len(),
+            NodeTag::Branch => self.min_branch_len(),
+        };
+        (hdr.len as usize) < min
+    }
+
+    /// In deferred mode, mark `child` instead of rebalancing it if it is
+    /// underfull but not empty. Returns whether the rebalance was deferred.
+    #[inline]
+    pub(crate) unsafe fn defer_underflow(&self, child: NonNull<u8>) -> bool {
+        let hdr = &mut *(child.as_ptr() as *mut NodeHdr);
+        if !self.underflow.deferred || hdr.len == 0 || !self.is_underfull(child) {
+            return false;
+        }
+        hdr.flags |= NodeHdr::UNDERFULL;
+        true
+    }
+
+    /// Rebalance with `threshold` from now on. Raising it compacts the tree,
+    /// so nodes already below the new threshold are fixed right away.
+    pub fn set_merge_threshold(&mut self, threshold: MergeThreshold) {
+        let before = (self.min_leaf_len(), self.min_branch_len());
+        self.underflow.threshold = threshold;
+        if self.min_leaf_len() > before.0 || self.min_branch_len() > before.1 {
+            self.compact();
+        }
+    }
+
+    /// Borrow for or merge every node below the merge threshold, including
+    /// those marked by deferred deletes or skewed splits, and clear the
+    /// marks. Returns the number of rebalancing steps taken.
+    pub fn compact(&mut self) -> usize {
+        let Some(mut root) = self.root else { return 0 };
+        let mut steps = 0;
+        unsafe {
+            loop {
+                self.compact_below(root, &mut steps);
+                // Merges may leave the root with a single child, or with two
+                // leaves that now fit in one. The children of a new root had
+                // no siblings to rebalance with until now.
+                self.collapse_root();
+                match self.root {
+                    Some(new_root) if new_root != root => root = new_root,
+                    _ => break,
+                }
+            }
+            if let Some(root) = self.root {
+                (*(root.as_ptr() as *mut NodeHdr)).flags &= !(NodeHdr::UNDERFULL | NodeHdr::SKEWED);
+            }
+        }
+        steps
+    }
+
+    /// Compact the subtrees under `node`, then its children themselves. A
+    /// branch with a single child leaves that child to the pass above it.
+    unsafe fn compact_below(&mut self, node: NonNull<u8>, steps: &mut usize) {
+        if (*(node.as_ptr() as *const NodeHdr)).tag == NodeTag::Leaf {
+            return;
+        }
+        let parts = layout::carve_branch::<K>(node, &self.branch_layout);
+        let children = parts.children_ptr as *const *mut u8;
+        let child = |i: usize| NonNull::new_unchecked(*children.add(i));
+        for i in 0..=(*parts.hdr).len as usize {
+            self.compact_below(child(i), steps);
+        }
+        // Each step either merges two children or moves an entry into an
+        // underfull one from a sibling above the threshold, so this ends.
+        loop {
+            let len = (*parts.hdr).len as usize;
+            if len == 0 {
+                break;
+            }
+            let Some(idx) = (0..=len).find(|&i| self.is_underfull(child(i))) else {
+                break;
+            };
+            *steps += 1;
+            match (*(child(idx).as_ptr() as *const NodeHdr)).tag {
+                NodeTag::Leaf => self.rebalance_leaf_child(node, idx, len),
+                NodeTag::Branch => {
+                    self.rebalance_branch_child(node, idx, len);
+                    // Grandchildren left underfull as only children now
+                    // have siblings in the merged or refilled branch.
+                    let last = (*parts.hdr).len as usize;
+                    for i in idx.saturating_sub(1)..=(idx + 1).min(last) {
+                        self.compact_below(child(i), steps);
+                    }
+                }
+            }
+        }
+        for i in 0..=(*parts.hdr).len as usize {
+            (*(child(i).as_ptr() as *mut NodeHdr)).flags &= !(NodeHdr::UNDERFULL | NodeHdr::SKEWED);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Specialized in-node key search for primitive integer keys.
+//!
+//! The generic path is `slice::binary_search`, whose data-dependent branches
+//! mispredict on random lookups. [`SearchKey`] implementations instead narrow
+//! the node with a branchless binary search and then count the keys below the
+//! target in one linear pass over a small window. On x86_64 that pass uses
+//! SSE2 for 32-bit keys and AVX2 for 64-bit keys (detected at runtime with
+//! `std`, at compile time without it); elsewhere it is a scalar count.
+//!
+//! Recent `slice::binary_search` is itself branchless, and a map reaches the
+//! specialized search through a function pointer that cannot be inlined, so
+//! the gain depends on the CPU and node capacity. Compare with the
+//! `bench_search` bin before enabling it.
+
+/// Keys with a specialized in-node search.
+///
+/// Enable it on a map with [`crate::BPlusTreeMap::enable_key_search`].
+pub trait SearchKey: Ord + Copy + 'static {
+    /// Same contract as `slice::binary_search` on sorted, distinct keys.
+    fn search(keys: &[Self], target: &Self) -> Result<usize, usize>;
+
+    /// The best implementation of [`SearchKey::search`] for this CPU, chosen
+    /// once so the per-node call does not repeat feature detection.
+    fn searcher() -> fn(&[Self], &Self) -> Result<usize, usize> {
+        Self::search
+    }
+}
+
+/// Largest window left for the linear counting pass: one 64-byte cache line
+/// of 64-bit keys.
+const LINEAR_WINDOW: usize = 8;
+
+/// Narrow `keys` to a window `[base, base + size)` whose start or end is the
+/// lower bound of `target`, using conditional moves instead of branches.
+#[inline(always)]
+fn narrow<T: Ord>(keys: &[T], target: &T, window: usize) -> (usize, usize) {
+    let mut base = 0;
+    let mut size = keys.len();
+    while size > window {
+        let half = size / 2;
+        // SAFETY: base + size <= keys.len() holds throughout, and half >= 1.
+        let probe = unsafe { keys.get_unchecked(base + half - 1) };
+        base = core::hint::select_unpredictable(probe < target, base + half, base);
+        size -= half;
+    }
+    (base, size)
+}
+
+#[inline(always)]
+fn search_with<T: Ord>(
+    keys: &[T],
+    target: &T,
+    count_below: impl Fn(&[T], &T) -> usize,
+) -> Result<usize, usize> {
+    let (base, size) = narrow(keys, target, LINEAR_WINDOW);
+    // SAFETY: `narrow` returns a window inside `keys`.
+    let window = unsafe { keys.get_unchecked(base..base + size) };
+    let idx = base + count_below(window, target);
+    if idx < keys.len() && keys[idx] == *target {
+        Ok(idx)
+    } else {
+        Err(idx)
+    }
+}
+
+#[inline(always)]
+fn count_below_scalar<T: Ord>(keys: &[T], target: &T) -> usize {
+    keys.iter().map(|k| usize::from(k < target)).sum()
+}
+
+/// Branchless lower-bound search for any ordered keys, with no SIMD pass.
+pub fn branchless_search<T: Ord>(keys: &[T], target: &T) -> Result<usize, usize> {
+    let (base, size) = narrow(keys, target, 1);
+    let idx = base + usize::from(size == 1 && keys[base] < *target);
+    if idx < keys.len() && keys[idx] == *target {
+        Ok(idx)
+    } else {
+        Err(idx)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use core::arch::x86_64::*;
+
+    #[inline(always)]
+    pub(super) fn has_avx2() -> bool {
+        #[cfg(feature = "std")]
+        {
+            std::is_x86_feature_detected!("avx2")
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            cfg!(target_feature = "avx2")
+        }
+    }
+
+    /// Count 32-bit lanes below `target`; `flip` maps the key order onto
+    /// signed order (the sign bit for unsigned keys, zero for signed ones).
+    #[inline(always)]
+    pub(super) fn count_below_32(keys: &[u32], target: u32, flip: u32) -> usize {
+        // SAFETY: SSE2 is part of the x86_64 baseline; loads are unaligned.
+        unsafe {
+            let t = _mm_set1_epi32((target ^ flip) as i32);
+            let f = _mm_set1_epi32(flip as i32);
+            let chunks = keys.chunks_exact(4);
+            let rest = chunks.remainder();
+            let mut n = 0;
+            for c in chunks {
+                let v = _mm_xor_si128(_mm_loadu_si128(c.as_ptr() as *const __m128i), f);
+                let lt = _mm_cmplt_epi32(v, t);
+                n += _mm_movemask_ps(_mm_castsi128_ps(lt)).count_ones() as usize;
+            }
+            let t = (target ^ flip) as i32;
+            n + rest.iter().filter(|&&k| ((k ^ flip) as i32) < t).count()
+        }
+    }
+
+    /// Count 64-bit lanes below `target`, as [`count_below_32`].
+    ///
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn count_below_64_avx2(keys: &[u64], target: u64, flip: u64) -> usize {
+        let t = _mm256_set1_epi64x((target ^ flip) as i64);
+        let f = _mm256_set1_epi64x(flip as i64);
+        let chunks = keys.chunks_exact(4);
+        let rest = chunks.remainder();
+        let mut n = 0;
+        for c in chunks {
+            let v = _mm256_xor_si256(_mm256_loadu_si256(c.as_ptr() as *const __m256i), f);
+            let lt = _mm256_cmpgt_epi64(t, v);
+            n += _mm256_movemask_pd(_mm256_castsi256_pd(lt)).count_ones() as usize;
+        }
+        let t = (target ^ flip) as i64;
+        n + rest.iter().filter(|&&k| ((k ^ flip) as i64) < t).count()
+    }
+}
+
+macro_rules! impl_search_32 {
+    ($($t:ty => $flip:expr),*) => {$(
+        impl SearchKey for $t {
+            #[inline]
+            fn search(keys: &[Self], target: &Self) -> Result<usize, usize> {
+                #[cfg(target_arch = "x86_64")]
+                {
+                    search_with(keys, target, |w, t| {
+                        // SAFETY: same size and alignment; only the bits are compared.
+                        let w = unsafe { core::slice::from_raw_parts(w.as_ptr() as *const u32, w.len()) };
+                        x86::count_below_32(w, *t as u32, $flip)
+                    })
+                }
+                #[cfg(not(target_arch = "x86_64"))]
+                {
+                    search_with(keys, target, count_below_scalar)
+                }
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_search_64 {
+    ($($t:ty => $flip:expr, $avx2:ident),*) => {$(
+        impl SearchKey for $t {
+            #[inline]
+            fn search(keys: &[Self], target: &Self) -> Result<usize, usize> {
+                #[cfg(target_arch = "x86_64")]
+                if x86::has_avx2() {
+                    // SAFETY: AVX2 checked above.
+                    return unsafe { $avx2(keys, target) };
+                }
+                search_with(keys, target, count_below_scalar)
+            }
+
+            fn searcher() -> fn(&[Self], &Self) -> Result<usize, usize> {
+                #[cfg(target_arch = "x86_64")]
+                if x86::has_avx2() {
+                    // SAFETY: only handed out after AVX2 was detected.
+                    return |keys, target| unsafe { $avx2(keys, target) };
+                }
+                |keys, target| search_with(keys, target, count_below_scalar)
+            }
+        }
+
+        /// The whole search compiled for AVX2, so the counting pass inlines.
+        ///
+        /// # Safety
+        /// The CPU must support AVX2.
+        #[cfg(target_arch = "x86_64")]
+        #[target_feature(enable = "avx2")]
+        unsafe fn $avx2(keys: &[$t], target: &$t) -> Result<usize, usize> {
+            search_with(keys, target, |w, t| {
+                // SAFETY: same size and alignment; only the bits are compared.
+                unsafe {
+                    let w = core::slice::from_raw_parts(w.as_ptr() as *const u64, w.len());
+                    x86::count_below_64_avx2(w, *t as u64, $flip)
+                }
+            })
+        }
+    )*};
+}
+
+impl_search_32!(u32 => 1 << 31, i32 => 0);
+impl_search_64!(u64 => 1 << 63, search_u64_avx2, i64 => 0, search_i64_avx2);
@@ -0,0 +1,119 @@
+//! `serde` support: a [`BPlusTreeMap`] serializes as a map in key order.
+//!
+//! Node capacity is not part of the data, so deserialization goes through
+//! [`BPlusTreeMapSeed`], which carries it; the plain `Deserialize` impl uses
+//! [`BPlusTreeMapSeed::default`].
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::{BPlusTreeMap, Comparator};
+
+impl<K, V, C> Serialize for BPlusTreeMap<K, V, C>
+where
+    K: Serialize,
+    V: Serialize,
+    C: Comparator<K>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (k, v) in self.items() {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+/// Deserializes a [`BPlusTreeMap`] with a chosen node capacity.
+///
+/// Entries that arrive in strictly increasing key order are buffered and
+/// built bottom-up; from the first out-of-order key on, the tree built so far
+/// takes the remaining entries through ordinary inserts (a repeated key keeps
+/// the last value, as with any map).
+pub struct BPlusTreeMapSeed<K, V> {
+    capacity: usize,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K, V> BPlusTreeMapSeed<K, V> {
+    /// Capacity used by the plain `Deserialize` impl.
+    pub const DEFAULT_CAPACITY: usize = 64;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<K, V> Default for BPlusTreeMapSeed<K, V> {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl<'de, K, V> DeserializeSeed<'de> for BPlusTreeMapSeed<K, V>
+where
+    K: Deserialize<'de> + Ord + Clone,
+    V: Deserialize<'de>,
+{
+    type Value = BPlusTreeMap<K, V>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, K, V> Visitor<'de> for BPlusTreeMapSeed<K, V>
+where
+    K: Deserialize<'de> + Ord + Clone,
+    V: Deserialize<'de>,
+{
+    type Value = BPlusTreeMap<K, V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut tree = BPlusTreeMap::new(self.capacity).map_err(de::Error::custom)?;
+        // The size hint comes from the input; do not trust it for large allocations.
+        let mut sorted: Vec<(K, V)> = Vec::with_capacity(access.size_hint().unwrap_or(0).min(4096));
+        let mut pending = None;
+        while let Some((k, v)) = access.next_entry::<K, V>()? {
+            if sorted.last().is_some_and(|(last, _)| *last >= k) {
+                pending = Some((k, v));
+                break;
+            }
+            sorted.push((k, v));
+        }
+        tree.build_from_sorted(sorted).map_err(de::Error::custom)?;
+        if let Some((k, v)) = pending {
+            tree.insert(k, v);
+            while let Some((k, v)) = access.next_entry::<K, V>()? {
+                tree.insert(k, v);
+            }
+        }
+        Ok(tree)
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for BPlusTreeMap<K, V>
+where
+    K: Deserialize<'de> + Ord + Clone,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        BPlusTreeMapSeed::default().deserialize(deserializer)
+    }
+}
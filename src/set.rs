@@ -0,0 +1,378 @@
+//! Ordered set on the map's node machinery.
+//!
+//! A [`BPlusTreeSet`] is a [`BPlusTreeMap`] with `()` values. Its leaves have
+//! no value array, so they hold as many keys as a map's leaves of the same
+//! size hold entries of twice the width.
+//!
+//! The set operations walk both leaf chains together. Where they skip ahead
+//! in one set to the other's next key, the cursor searches the rest of its
+//! current leaf, then the following leaf, and only then descends from the
+//! root: cheap while the two sets interleave densely, and a logarithmic jump
+//! per key when one set is much smaller than the other.
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+
+use crate::iterate::Position;
+use crate::layout;
+use crate::{
+    BPlusTreeError, BPlusTreeMap, Comparator, DeriveSeparator, InvariantViolation, Keys,
+    OrdComparator, TreeConfig,
+};
+
+/// Ordered set of keys, compared with `C`.
+pub struct BPlusTreeSet<K, C = OrdComparator> {
+    map: BPlusTreeMap<K, (), C>,
+}
+
+impl<K: Ord> BPlusTreeSet<K> {
+    /// An empty set with `capacity` for both node kinds; see
+    /// [`TreeConfig::build_set`] for other options.
+    pub fn new(capacity: usize) -> Result<Self, BPlusTreeError> {
+        Self::with_comparator(capacity, OrdComparator)
+    }
+}
+
+impl<K, C: Comparator<K>> BPlusTreeSet<K, C> {
+    /// Like [`BPlusTreeSet::new`], ordering keys with `cmp` instead of `Ord`.
+    pub fn with_comparator(capacity: usize, cmp: C) -> Result<Self, BPlusTreeError> {
+        Ok(Self {
+            map: BPlusTreeMap::with_comparator(capacity, cmp)?,
+        })
+    }
+
+    /// The underlying map, for its configuration, statistics and validation.
+    pub fn as_map(&self) -> &BPlusTreeMap<K, (), C> {
+        &self.map
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn first(&self) -> Option<&K> {
+        self.map.first().map(|(k, _)| k)
+    }
+
+    pub fn last(&self) -> Option<&K> {
+        self.map.last().map(|(k, _)| k)
+    }
+
+    pub fn iter(&self) -> Keys<'_, K, (), C> {
+        self.map.keys()
+    }
+
+    pub fn range<R: RangeBounds<K>>(&self, r: R) -> Keys<'_, K, (), C> {
+        Keys {
+            inner: self.map.range(r),
+        }
+    }
+
+    /// Keys in `self` or `other`, in order.
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, K, C> {
+        Union {
+            a: Cursor::new(&self.map),
+            b: Cursor::new(&other.map),
+        }
+    }
+
+    /// Keys in both `self` and `other`, in order.
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, K, C> {
+        Intersection {
+            a: Cursor::new(&self.map),
+            b: Cursor::new(&other.map),
+        }
+    }
+
+    /// Keys in `self` but not in `other`, in order.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, K, C> {
+        Difference {
+            a: Cursor::new(&self.map),
+            b: Cursor::new(&other.map),
+        }
+    }
+
+    /// Keys in exactly one of `self` and `other`, in order.
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> SymmetricDifference<'a, K, C> {
+        SymmetricDifference {
+            a: Cursor::new(&self.map),
+            b: Cursor::new(&other.map),
+        }
+    }
+
+    /// Whether every key of `self` is in `other`.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        let mut b = Cursor::new(&other.map);
+        self.iter().all(|k| {
+            b.seek(k);
+            b.peek().is_some_and(|x| b.cmp(x, k).is_eq())
+        })
+    }
+
+    /// Whether every key of `other` is in `self`.
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Whether `self` and `other` have no key in common.
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    pub fn check_invariants_detailed(&self) -> Result<(), InvariantViolation> {
+        self.map.check_invariants_detailed()
+    }
+}
+
+impl<K, C: DeriveSeparator<K>> BPlusTreeSet<K, C> {
+    /// Add `key`. Returns whether it was not already present.
+    pub fn insert(&mut self, key: K) -> bool {
+        self.map.insert(key, ()).is_none()
+    }
+
+    /// Remove `key`. Returns whether it was present.
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.map.remove(key).is_some()
+    }
+}
+
+impl TreeConfig {
+    /// Build an empty set ordering keys by `Ord`.
+    pub fn build_set<K: Ord>(self) -> Result<BPlusTreeSet<K>, BPlusTreeError> {
+        Ok(BPlusTreeSet { map: self.build()? })
+    }
+}
+
+impl<'a, K, C: Comparator<K>> IntoIterator for &'a BPlusTreeSet<K, C> {
+    type Item = &'a K;
+    type IntoIter = Keys<'a, K, (), C>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, C: DeriveSeparator<K>> Extend<K> for BPlusTreeSet<K, C> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for k in iter {
+            self.insert(k);
+        }
+    }
+}
+
+impl<K: fmt::Debug, C: Comparator<K>> fmt::Debug for BPlusTreeSet<K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Forward position in a set that can skip ahead to a key.
+struct Cursor<'a, K, C> {
+    map: &'a BPlusTreeMap<K, (), C>,
+    pos: Option<Position>,
+}
+
+impl<'a, K, C: Comparator<K>> Cursor<'a, K, C> {
+    fn new(map: &'a BPlusTreeMap<K, (), C>) -> Self {
+        let pos = map.bound_position(Bound::Unbounded, true);
+        Self { map, pos }
+    }
+
+    #[inline]
+    fn cmp(&self, a: &K, b: &K) -> Ordering {
+        self.map.cmp.compare(a, b)
+    }
+
+    /// Keys from the current one to the end of its leaf.
+    #[inline]
+    fn rest_of_leaf(&self, (leaf, idx): Position) -> &'a [K] {
+        unsafe {
+            let parts = layout::carve_leaf::<K, ()>(leaf, &self.map.leaf_layout);
+            let len = (*parts.hdr).len as usize;
+            let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
+            &keys[idx..]
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&'a K> {
+        self.pos.map(|pos| &self.rest_of_leaf(pos)[0])
+    }
+
+    #[inline]
+    fn advance(&mut self) {
+        if let Some((leaf, idx)) = self.pos {
+            self.pos = self.map.canonical_position(leaf, idx + 1);
+        }
+    }
+
+    /// Move to the first key at or after `key`, if that is ahead.
+    fn seek(&mut self, key: &K) {
+        let Some((mut leaf, mut idx)) = self.pos else {
+            return;
+        };
+        // This leaf, then the next, before paying for a descent.
+        for _ in 0..2 {
+            let keys = self.rest_of_leaf((leaf, idx));
+            if self.cmp(&keys[keys.len() - 1], key).is_ge() {
+                let skip = match self.map.binary_search_keys(keys, key) {
+                    Ok(i) | Err(i) => i,
+                };
+                self.pos = Some((leaf, idx + skip));
+                return;
+            }
+            let next = unsafe {
+                let parts = layout::carve_leaf::<K, ()>(leaf, &self.map.leaf_layout);
+                *parts.next_ptr
+            };
+            match core::ptr::NonNull::new(next) {
+                Some(next) => (leaf, idx) = (next, 0),
+                None => {
+                    self.pos = None;
+                    return;
+                }
+            }
+        }
+        self.pos = self.map.bound_position(Bound::Included(key), true);
+    }
+}
+
+/// Lazy iterator over [`BPlusTreeSet::union`].
+pub struct Union<'a, K, C = OrdComparator> {
+    a: Cursor<'a, K, C>,
+    b: Cursor<'a, K, C>,
+}
+
+impl<'a, K, C: Comparator<K>> Iterator for Union<'a, K, C> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        match (self.a.peek(), self.b.peek()) {
+            (None, None) => None,
+            (Some(x), None) => {
+                self.a.advance();
+                Some(x)
+            }
+            (None, Some(y)) => {
+                self.b.advance();
+                Some(y)
+            }
+            (Some(x), Some(y)) => match self.a.cmp(x, y) {
+                Ordering::Less => {
+                    self.a.advance();
+                    Some(x)
+                }
+                Ordering::Greater => {
+                    self.b.advance();
+                    Some(y)
+                }
+                Ordering::Equal => {
+                    self.a.advance();
+                    self.b.advance();
+                    Some(x)
+                }
+            },
+        }
+    }
+}
+
+/// Lazy iterator over [`BPlusTreeSet::intersection`].
+pub struct Intersection<'a, K, C = OrdComparator> {
+    a: Cursor<'a, K, C>,
+    b: Cursor<'a, K, C>,
+}
+
+impl<'a, K, C: Comparator<K>> Iterator for Intersection<'a, K, C> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        loop {
+            let x = self.a.peek()?;
+            let y = self.b.peek()?;
+            match self.a.cmp(x, y) {
+                Ordering::Less => self.a.seek(y),
+                Ordering::Greater => self.b.seek(x),
+                Ordering::Equal => {
+                    self.a.advance();
+                    self.b.advance();
+                    return Some(x);
+                }
+            }
+        }
+    }
+}
+
+/// Lazy iterator over [`BPlusTreeSet::difference`].
+pub struct Difference<'a, K, C = OrdComparator> {
+    a: Cursor<'a, K, C>,
+    b: Cursor<'a, K, C>,
+}
+
+impl<'a, K, C: Comparator<K>> Iterator for Difference<'a, K, C> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        loop {
+            let x = self.a.peek()?;
+            self.a.advance();
+            self.b.seek(x);
+            match self.b.peek() {
+                Some(y) if self.b.cmp(y, x).is_eq() => self.b.advance(),
+                _ => return Some(x),
+            }
+        }
+    }
+}
+
+/// Lazy iterator over [`BPlusTreeSet::symmetric_difference`].
+pub struct SymmetricDifference<'a, K, C = OrdComparator> {
+    a: Cursor<'a, K, C>,
+    b: Cursor<'a, K, C>,
+}
+
+impl<'a, K, C: Comparator<K>> Iterator for SymmetricDifference<'a, K, C> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        loop {
+            match (self.a.peek(), self.b.peek()) {
+                (None, None) => return None,
+                (Some(x), None) => {
+                    self.a.advance();
+                    return Some(x);
+                }
+                (None, Some(y)) => {
+                    self.b.advance();
+                    return Some(y);
+                }
+                (Some(x), Some(y)) => match self.a.cmp(x, y) {
+                    Ordering::Less => {
+                        self.a.advance();
+                        return Some(x);
+                    }
+                    Ordering::Greater => {
+                        self.b.advance();
+                        return Some(y);
+                    }
+                    Ordering::Equal => {
+                        self.a.advance();
+                        self.b.advance();
+                    }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! Versioned binary snapshots of a [`BPlusTreeMap`].
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! header: magic "BPTSNAP\0" | version u16 | key codec u16 | value codec u16
+//!         | flags u16 | leaf cap u32 | branch cap u32 | entry count u64
+//!         | crc32 of the preceding header bytes
+//! block*: entry count u32 | payload length u32 | payload | crc32 of all three
+//! end:    a block with zero entries and an empty payload
+//! ```
+//!
+//! Each block holds the sorted run of one leaf, written while walking the
+//! sibling chain. Loading rebuilds the tree bottom-up from those runs. Flag bit
+//! 0 marks singly-linked leaves; node capacities and the flags together give
+//! the loaded tree's [`crate::TreeConfig`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::io::{self, Read, Write};
+
+use crate::checksum::{crc32, Crc32};
+use crate::layout;
+use crate::{BPlusTreeError, BPlusTreeMap, BTreeResult, Comparator, TreeConfig};
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"BPTSNAP\0";
+const SNAPSHOT_VERSION: u16 = 1;
+const HEADER_LEN: usize = 36;
+/// Header flag: leaves carry no back link. Clear in snapshots written
+/// before the flag existed, whose trees were all doubly linked.
+const SINGLY_LINKED: u16 = 1;
+
+/// Stable byte encoding for snapshot keys and values.
+///
+/// `CODEC_ID` is recorded in the snapshot header so a file written for one
+/// key or value type is rejected when loaded as another.
+pub trait SnapshotCodec: Sized {
+    const CODEC_ID: u16;
+
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decode one value from the front of `input`, advancing it.
+    fn decode(input: &mut &[u8]) -> Option<Self>;
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if input.len() < n {
+        return None;
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Some(head)
+}
+
+macro_rules! impl_codec_le {
+    ($($t:ty => $id:expr),* $(,)?) => {$(
+        impl SnapshotCodec for $t {
+            const CODEC_ID: u16 = $id;
+
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                let bytes = take(input, core::mem::size_of::<$t>())?;
+                Some(<$t>::from_le_bytes(bytes.try_into().ok()?))
+            }
+        }
+    )*};
+}
+
+impl_codec_le!(
+    u8 => 1, u16 => 2, u32 => 3, u64 => 4, u128 => 5,
+    i8 => 11, i16 => 12, i32 => 13, i64 => 14, i128 => 15,
+    f32 => 21, f64 => 22,
+);
+
+/// Pointer-sized integers are stored as their 64-bit counterpart so a
+/// snapshot reads the same on every host; values the loading host cannot
+/// represent fail to decode.
+macro_rules! impl_codec_word {
+    ($($t:ty as $wide:ty => $id:expr),* $(,)?) => {$(
+        impl SnapshotCodec for $t {
+            const CODEC_ID: u16 = $id;
+
+            fn encode(&self, out: &mut Vec<u8>) {
+                (*self as $wide).encode(out);
+            }
+
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                <$t>::try_from(<$wide>::decode(input)?).ok()
+            }
+        }
+    )*};
+}
+
+impl_codec_word!(usize as u64 => 6, isize as i64 => 16);
+
+impl SnapshotCodec for () {
+    const CODEC_ID: u16 = 30;
+
+    fn encode(&self, _out: &mut Vec<u8>) {}
+
+    fn decode(_input: &mut &[u8]) -> Option<Self> {
+        Some(())
+    }
+}
+
+impl SnapshotCodec for bool {
+    const CODEC_ID: u16 = 31;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match take(input, 1)?[0] {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl SnapshotCodec for Vec<u8> {
+    const CODEC_ID: u16 = 40;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode(out);
+        out.extend_from_slice(self);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::try_from(u64::decode(input)?).ok()?;
+        Some(take(input, len)?.to_vec())
+    }
+}
+
+impl SnapshotCodec for String {
+    const CODEC_ID: u16 = 41;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::try_from(u64::decode(input)?).ok()?;
+        String::from_utf8(take(input, len)?.to_vec()).ok()
+    }
+}
+
+fn corrupted(why: &str) -> BPlusTreeError {
+    BPlusTreeError::corrupted_tree("snapshot", why)
+}
+
+fn read_exact<R: Read>(r: &mut R, buf: &mut [u8]) -> BTreeResult<()> {
+    r.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => corrupted("truncated"),
+        _ => e.into(),
+    })
+}
+
+fn write_block<W: Write>(w: &mut W, count: u32, payload: &[u8]) -> BTreeResult<()> {
+    let mut head = [0u8; 8];
+    head[..4].copy_from_slice(&count.to_le_bytes());
+    head[4..].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    let mut crc = Crc32::new();
+    crc.update(&head);
+    crc.update(payload);
+    w.write_all(&head)?;
+    w.write_all(payload)?;
+    w.write_all(&crc.finish().to_le_bytes())?;
+    Ok(())
+}
+
+/// Streams `(K, V)` pairs out of snapshot blocks, parking the first error.
+struct BlockReader<'e, R, K, V> {
+    reader: R,
+    run: alloc::vec::IntoIter<(K, V)>,
+    done: bool,
+    seen: u64,
+    error: &'e mut Option<BPlusTreeError>,
+}
+
+impl<R: Read, K: SnapshotCodec, V: SnapshotCodec> BlockReader<'_, R, K, V> {
+    /// Read and verify the next block; `Ok(false)` at the end marker.
+    fn next_block(&mut self) -> BTreeResult<bool> {
+        let mut head = [0u8; 8];
+        read_exact(&mut self.reader, &mut head)?;
+        let count = u32::from_le_bytes(head[..4].try_into().unwrap());
+        let len = u32::from_le_bytes(head[4..].try_into().unwrap()) as usize;
+        let mut payload = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut payload)?;
+        if payload.len() != len {
+            return Err(corrupted("truncated"));
+        }
+        let mut stored = [0u8; 4];
+        read_exact(&mut self.reader, &mut stored)?;
+        let mut crc = Crc32::new();
+        crc.update(&head);
+        crc.update(&payload);
+        if crc.finish() != u32::from_le_bytes(stored) {
+            return Err(corrupted("block checksum mismatch"));
+        }
+        if count == 0 {
+            return if len == 0 {
+                Ok(false)
+            } else {
+                Err(corrupted("non-empty end block"))
+            };
+        }
+        let mut input = &payload[..];
+        let mut run = Vec::with_capacity((count as usize).min(len));
+        for _ in 0..count {
+            let k = K::decode(&mut input).ok_or_else(|| corrupted("undecodable key"))?;
+            let v = V::decode(&mut input).ok_or_else(|| corrupted("undecodable value"))?;
+            run.push((k, v));
+        }
+        if !input.is_empty() {
+            return Err(corrupted("trailing bytes in block"));
+        }
+        self.seen += count as u64;
+        self.run = run.into_iter();
+        Ok(true)
+    }
+}
+
+impl<R: Read, K: SnapshotCodec, V: SnapshotCodec> Iterator for BlockReader<'_, R, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        loop {
+            if let Some(kv) = self.run.next() {
+                return Some(kv);
+            }
+            if self.done {
+                return None;
+            }
+            match self.next_block() {
+                Ok(true) => {}
+                Ok(false) => self.done = true,
+                Err(e) => {
+                    *self.error = Some(e);
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+impl<K: SnapshotCodec, V: SnapshotCodec, C: Comparator<K>> BPlusTreeMap<K, V, C> {
+    /// Write a snapshot of the tree, one checksummed block per leaf.
+    pub fn save_to<W: Write>(&self, w: &mut W) -> BTreeResult<()> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(SNAPSHOT_MAGIC);
+        header.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        header.extend_from_slice(&K::CODEC_ID.to_le_bytes());
+        header.extend_from_slice(&V::CODEC_ID.to_le_bytes());
+        let flags = if self.leaf_layout.prev_off.is_some() {
+            0
+        } else {
+            SINGLY_LINKED
+        };
+        header.extend_from_slice(&flags.to_le_bytes());
+        header.extend_from_slice(&(self.leaf_layout.cap as u32).to_le_bytes());
+        header.extend_from_slice(&(self.branch_layout.cap as u32).to_le_bytes());
+        header.extend_from_slice(&(self.len() as u64).to_le_bytes());
+        let crc = crc32(&header);
+        header.extend_from_slice(&crc.to_le_bytes());
+        w.write_all(&header)?;
+
+        let mut payload = Vec::new();
+        let mut cur = self.leftmost_leaf();
+        while let Some(leaf) = cur {
+            unsafe {
+                let parts = layout::carve_leaf::<K, V>(leaf, &self.leaf_layout);
+                let len = (*parts.hdr).len as usize;
+                let keys = core::slice::from_raw_parts(parts.keys_ptr as *const K, len);
+                let vals = core::slice::from_raw_parts(parts.vals_ptr as *const V, len);
+                payload.clear();
+                for (k, v) in keys.iter().zip(vals) {
+                    k.encode(&mut payload);
+                    v.encode(&mut payload);
+                }
+                if len > 0 {
+                    write_block(w, len as u32, &payload)?;
+                }
+                cur = core::ptr::NonNull::new(*parts.next_ptr);
+            }
+        }
+        write_block(w, 0, &[])?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+impl<K: Ord + Clone + SnapshotCodec, V: SnapshotCodec> BPlusTreeMap<K, V> {
+    /// Rebuild a tree from a snapshot written by [`BPlusTreeMap::save_to`].
+    ///
+    /// Node capacities and leaf linking come from the snapshot header; the
+    /// other [`TreeConfig`] options take their defaults. Any checksum mismatch,
+    /// truncation, codec mismatch or out-of-order key yields `CorruptedTree`.
+    pub fn load_from<R: Read>(mut r: R) -> BTreeResult<Self> {
+        let mut header = [0u8; HEADER_LEN];
+        read_exact(&mut r, &mut header)?;
+        let u16_at = |o: usize| u16::from_le_bytes([header[o], header[o + 1]]);
+        let u32_at = |o: usize| u32::from_le_bytes(header[o..o + 4].try_into().unwrap());
+        if &header[..8] != SNAPSHOT_MAGIC {
+            return Err(corrupted("bad magic"));
+        }
+        if crc32(&header[..HEADER_LEN - 4]) != u32_at(HEADER_LEN - 4) {
+            return Err(corrupted("header checksum mismatch"));
+        }
+        if u16_at(8) != SNAPSHOT_VERSION {
+            return Err(corrupted("unsupported version"));
+        }
+        if u16_at(10) != K::CODEC_ID || u16_at(12) != V::CODEC_ID {
+            return Err(corrupted("key/value codec mismatch"));
+        }
+        let flags = u16_at(14);
+        if flags & !SINGLY_LINKED != 0 {
+            return Err(corrupted("unknown header flags"));
+        }
+        let (leaf_cap, branch_cap) = (u32_at(16), u32_at(20));
+        let count = u64::from_le_bytes(header[24..32].try_into().unwrap());
+
+        let mut tree = TreeConfig::new()
+            .leaf_capacity(leaf_cap as usize)
+            .branch_capacity(branch_cap as usize)
+            .doubly_linked(flags & SINGLY_LINKED == 0)
+            .build()
+            .map_err(|_| corrupted("invalid node capacity"))?;
+
+        let mut error = None;
+        let mut blocks = BlockReader {
+            reader: r,
+            run: Vec::new().into_iter(),
+            done: false,
+            seen: 0,
+            error: &mut error,
+        };
+        let built = tree.build_from_sorted(&mut blocks);
+        let seen = blocks.seen;
+        if let Some(e) = error {
+            return Err(e);
+        }
+        built.map_err(|_| corrupted("keys out of order"))?;
+        if seen != count {
+            return Err(corrupted("entry count mismatch"));
+        }
+        Ok(tree)
+    }
+}
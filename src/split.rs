@@ -0,0 +1,158 @@
+//! Where full nodes split.
+//!
+//! An even split leaves both halves half full. When keys arrive in
+//! ascending order every insert lands in the rightmost leaf, so the left
+//! halves are never touched again and the tree settles at 50% fill. A
+//! skewed split at the tree's edge instead keeps the full node whole and
+//! starts the new one with just the incoming entry. Only nodes on the left
+//! or right edge of each level are ever left underfull this way; they carry
+//! [`NodeHdr::SKEWED`], which validation accepts, until they fill up or
+//! merge.
+
+use core::ptr::NonNull;
+
+use crate::layout::LeafParts;
+use crate::{BPlusTreeMap, Comparator, NodeHdr, NodeTag};
+
+/// How a full node divides its entries when it splits; see
+/// [`BPlusTreeMap::set_split_policy`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplitPolicy {
+    /// Always split 50/50.
+    #[default]
+    Even,
+    /// Inserting past the last key of the rightmost leaf keeps the full
+    /// nodes whole and starts new rightmost nodes; other splits are even.
+    RightMost,
+    /// Like `RightMost` once a run of inserts has appended past the last
+    /// key, and mirrored on the leftmost leaf for runs of descending keys;
+    /// even otherwise, so a random workload that happens to hit an edge
+    /// keeps its headroom.
+    Adaptive,
+}
+
+/// Consecutive edge inserts after which [`SplitPolicy::Adaptive`] skews.
+const ADAPTIVE_RUN: i32 = 4;
+
+/// The edge of the tree a split is skewed toward.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Edge {
+    Left,
+    Right,
+}
+
+pub(crate) struct SplitState {
+    pub(crate) policy: SplitPolicy,
+    /// Length of the current run of inserts at the right (positive) or left
+    /// (negative) edge, tracked for `Adaptive`.
+    run: i32,
+}
+
+impl SplitState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            policy: SplitPolicy::Even,
+            run: 0,
+        }
+    }
+}
+
+/// Entries kept by the left leaf when a leaf holding `len` entries splits
+/// around a new one.
+pub(crate) fn leaf_split_point(edge: Option<Edge>, len: usize) -> usize {
+    match edge {
+        Some(Edge::Right) => len,
+        Some(Edge::Left) => 1,
+        None => len.div_ceil(2),
+    }
+}
+
+/// Keys kept by the left branch when a branch holding `len` keys splits
+/// around a key inserted at `insert_idx`, and whether that split is skewed.
+/// Each half keeps at least one key.
+pub(crate) fn branch_split_point(
+    edge: Option<Edge>,
+    insert_idx: usize,
+    len: usize,
+) -> (usize, Option<Edge>) {
+    match edge {
+        Some(Edge::Right) if insert_idx == len && len >= 2 => (len - 1, edge),
+        Some(Edge::Left) if insert_idx == 0 && len >= 2 => (1, edge),
+        _ => (len.div_ceil(2), None),
+    }
+}
+
+impl<K, V, C> BPlusTreeMap<K, V, C> {
+    /// Choose how full nodes split from now on. Nodes already in the tree
+    /// are not reshaped.
+    pub fn set_split_policy(&mut self, policy: SplitPolicy) {
+        self.split.policy = policy;
+        self.split.run = 0;
+    }
+
+    pub fn split_policy(&self) -> SplitPolicy {
+        self.split.policy
+    }
+}
+
+impl<K, V, C: Comparator<K>> BPlusTreeMap<K, V, C> {
+    /// Record an insert at `idx` of a leaf holding `len` entries, and return
+    /// the edge to skew toward should this leaf have to split. `leftmost`
+    /// says whether the descent to the leaf only took first children.
+    #[inline(always)]
+    pub(crate) unsafe fn note_leaf_insert(
+        &mut self,
+        parts: &LeafParts<K, V>,
+        idx: usize,
+        len: usize,
+        leftmost: bool,
+    ) -> Option<Edge> {
+        let policy = self.split.policy;
+        if policy == SplitPolicy::Even {
+            return None;
+        }
+        let at_right = idx == len && (*parts.next_ptr).is_null();
+        if policy == SplitPolicy::RightMost {
+            return at_right.then_some(Edge::Right);
+        }
+        let at_left = idx == 0 && leftmost;
+        let run = &mut self.split.run;
+        *run = match (at_right, at_left) {
+            // An empty root leaf is at both edges; count it as appending.
+            (true, _) => (*run).max(0).saturating_add(1),
+            (false, true) => (*run).min(0).saturating_sub(1),
+            (false, false) => 0,
+        };
+        if *run >= ADAPTIVE_RUN {
+            Some(Edge::Right)
+        } else if *run <= -ADAPTIVE_RUN {
+            Some(Edge::Left)
+        } else {
+            None
+        }
+    }
+
+    /// Flag `node`, just left by a split toward an edge, if it is underfull.
+    #[inline]
+    pub(crate) unsafe fn mark_skewed(&self, node: NonNull<u8>) {
+        (*(node.as_ptr() as *mut NodeHdr)).flags |= NodeHdr::SKEWED;
+        self.settle_skewed(node);
+    }
+
+    /// Clear [`NodeHdr::SKEWED`] from `node` once it reaches the merge
+    /// threshold.
+    #[inline(always)]
+    pub(crate) unsafe fn settle_skewed(&self, node: NonNull<u8>) {
+        let hdr = &mut *(node.as_ptr() as *mut NodeHdr);
+        if hdr.flags & NodeHdr::SKEWED == 0 {
+            return;
+        }
+        let min = match hdr.tag {
+            NodeTag::Leaf => self.min_leaf_len(),
+            NodeTag::Branch => self.min_branch_len(),
+        };
+        if hdr.len as usize >= min {
+            hdr.flags &= !NodeHdr::SKEWED;
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Shape and occupancy of a [`BPlusTreeMap`].
+
+use core::ptr::NonNull;
+
+use crate::layout;
+use crate::{BPlusTreeMap, NodeHdr, NodeTag};
+
+/// Node counts and fill of a tree, from [`BPlusTreeMap::stats`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    pub len: usize,
+    /// Levels from the root to the leaves; 0 for a tree without a root.
+    pub height: usize,
+    pub leaves: usize,
+    pub branches: usize,
+    /// Entry slots across all leaves.
+    pub leaf_slots: usize,
+    /// Separator keys stored across all branches.
+    pub branch_keys: usize,
+    /// Separator slots across all branches.
+    pub branch_slots: usize,
+}
+
+impl TreeStats {
+    /// Fraction of leaf slots holding an entry.
+    pub fn leaf_fill(&self) -> f64 {
+        ratio(self.len, self.leaf_slots)
+    }
+
+    /// Fraction of branch slots holding a separator.
+    pub fn branch_fill(&self) -> f64 {
+        ratio(self.branch_keys, self.branch_slots)
+    }
+}
+
+fn ratio(used: usize, slots: usize) -> f64 {
+    if slots == 0 {
+        0.0
+    } else {
+        used as f64 / slots as f64
+    }
+}
+
+impl<K, V, C> BPlusTreeMap<K, V, C> {
+    /// Count nodes and occupied slots by walking the whole tree.
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        if let Some(root) = self.root {
+            unsafe { self.collect_stats(root, 1, &mut stats) };
+        }
+        stats
+    }
+
+    unsafe fn collect_stats(&self, node: NonNull<u8>, depth: usize, stats: &mut TreeStats) {
+        let hdr = &*(node.as_ptr() as *const NodeHdr);
+        let len = hdr.len as usize;
+        stats.height = stats.height.max(depth);
+        match hdr.tag {
+            NodeTag::Leaf => {
+                stats.leaves += 1;
+                stats.len += len;
+                stats.leaf_slots += self.leaf_layout.cap as usize;
+            }
+            NodeTag::Branch => {
+                stats.branches += 1;
+                stats.branch_keys += len;
+                stats.branch_slots += self.branch_layout.cap as usize;
+                let parts = layout::carve_branch::<K>(node, &self.branch_layout);
+                for i in 0..=len {
+                    let child = *(parts.children_ptr.add(i) as *const *mut u8);
+                    if let Some(child) = NonNull::new(child) {
+                        self.collect_stats(child, depth + 1, stats);
+                    }
+                }
+            }
+        }
+    }
+}
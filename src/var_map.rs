@@ -0,0 +1,265 @@
+//! Maps with variable-size keys and values stored inline in slotted leaves.
+//!
+//! [`BPlusTreeMap`] lays leaves out as `[K; cap]` and `[V; cap]` arrays, so a
+//! `String` key only stores a pointer and every comparison chases it to the
+//! heap. [`VarBPlusTreeMap`] instead serializes keys and values through
+//! [`VarCodec`] and stores the bytes in the leaf itself, on top of the
+//! prefix-compressed slotted nodes of [`BytesBPlusTreeMap`]. Leaves split and
+//! merge by byte fill rather than entry count.
+//!
+//! A value whose encoding does not fit next to its key in a leaf cell
+//! overflows to a separate heap block, referenced from the entry's slot.
+//!
+//! [`BPlusTreeMap`]: crate::BPlusTreeMap
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::RangeBounds;
+
+use crate::bytes_map::BytesRange;
+use crate::{BPlusTreeError, BTreeResult, BytesBPlusTreeMap, InvariantViolation};
+
+/// Byte encoding for the keys and values of a [`VarBPlusTreeMap`].
+///
+/// The stored length delimits each encoding, so `decode` receives exactly
+/// the bytes `encode` produced.
+pub trait VarCodec: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+/// Keys whose encodings compare bytewise in the same order as `Ord`.
+pub trait VarKey: VarCodec + Ord {}
+
+macro_rules! impl_var_unsigned {
+    ($($t:ty),*) => {$(
+        impl VarCodec for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_be_bytes());
+            }
+
+            fn decode(bytes: &[u8]) -> Option<Self> {
+                Some(<$t>::from_be_bytes(bytes.try_into().ok()?))
+            }
+        }
+
+        impl VarKey for $t {}
+    )*};
+}
+
+macro_rules! impl_var_signed {
+    ($($t:ty => $u:ty),*) => {$(
+        /// Big-endian with the sign bit flipped, so negatives sort first.
+        impl VarCodec for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&((*self as $u) ^ (1 << (<$u>::BITS - 1))).to_be_bytes());
+            }
+
+            fn decode(bytes: &[u8]) -> Option<Self> {
+                let u = <$u>::from_be_bytes(bytes.try_into().ok()?);
+                Some((u ^ (1 << (<$u>::BITS - 1))) as $t)
+            }
+        }
+
+        impl VarKey for $t {}
+    )*};
+}
+
+impl_var_unsigned!(u8, u16, u32, u64, u128);
+impl_var_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+impl VarCodec for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl VarKey for Vec<u8> {}
+
+impl VarCodec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl VarKey for String {}
+
+impl VarCodec for () {
+    fn encode(&self, _out: &mut Vec<u8>) {}
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bytes.is_empty().then_some(())
+    }
+}
+
+impl VarCodec for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl VarCodec for f64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(f64::from_le_bytes(bytes.try_into().ok()?))
+    }
+}
+
+fn encoded<T: VarCodec>(x: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    x.encode(&mut out);
+    out
+}
+
+fn decoded<T: VarCodec>(bytes: &[u8]) -> T {
+    T::decode(bytes).expect("codec failed to decode its own encoding")
+}
+
+/// Slot payload: the encoded value when it overflowed the leaf.
+type Overflow = Option<Box<[u8]>>;
+
+/// Ordered map storing encoded keys and values inline in leaf pages.
+pub struct VarBPlusTreeMap<K, V> {
+    raw: BytesBPlusTreeMap<Overflow>,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K: VarKey, V: VarCodec> VarBPlusTreeMap<K, V> {
+    /// Construct with explicit byte budgets for leaves and branches, as
+    /// [`BytesBPlusTreeMap::with_budgets`].
+    pub fn with_budgets(leaf_bytes: usize, branch_bytes: usize) -> Result<Self, BPlusTreeError> {
+        Ok(Self {
+            raw: BytesBPlusTreeMap::with_budgets(leaf_bytes, branch_bytes)?,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Construct using cache-line counts for leaf and branch nodes.
+    pub fn with_cache_lines(
+        leaf_lines: usize,
+        branch_lines: usize,
+    ) -> Result<Self, BPlusTreeError> {
+        Ok(Self {
+            raw: BytesBPlusTreeMap::with_cache_lines(leaf_lines, branch_lines)?,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Longest encoded key [`VarBPlusTreeMap::insert`] accepts. Keys, unlike
+    /// values, never overflow: separators built from them live in branches.
+    pub fn max_key_len(&self) -> usize {
+        self.raw.max_key_len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Insert or replace the value for `key`, returning the old value.
+    pub fn insert(&mut self, key: K, value: V) -> BTreeResult<Option<V>> {
+        let k = encoded(&key);
+        let v = encoded(&value);
+        let old = if k.len() + v.len() <= self.raw.max_entry_len() {
+            self.raw.insert_entry(&k, &v, None)?
+        } else {
+            self.raw.insert_entry(&k, &[], Some(v.into_boxed_slice()))?
+        };
+        Ok(old.map(|(spill, tail)| decoded(spill.as_deref().unwrap_or(&tail[..]))))
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_bytes(key).map(decoded)
+    }
+
+    /// The encoded value for `key`, read in place.
+    pub fn get_bytes(&self, key: &K) -> Option<&[u8]> {
+        let (spill, tail) = self.raw.get_entry(&encoded(key))?;
+        Some(spill.as_deref().unwrap_or(tail))
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.raw.contains_key(&encoded(key))
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (spill, tail) = self.raw.remove_entry(&encoded(key))?;
+        Some(decoded(spill.as_deref().unwrap_or(&tail[..])))
+    }
+
+    pub fn clear(&mut self) {
+        self.raw.clear();
+    }
+
+    /// All entries in key order, decoded.
+    pub fn iter(&self) -> VarRange<'_, K, V> {
+        self.range::<core::ops::RangeFull>(..)
+    }
+
+    pub fn range<R: RangeBounds<K>>(&self, r: R) -> VarRange<'_, K, V> {
+        let start = r.start_bound().map(encoded);
+        let end = r.end_bound().map(encoded);
+        VarRange {
+            inner: self
+                .raw
+                .range((start.as_ref().map(|k| &k[..]), end.as_ref().map(|k| &k[..]))),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Number of entries whose value overflowed to a separate block.
+    pub fn overflow_count(&self) -> usize {
+        let mut it = self.raw.iter();
+        core::iter::from_fn(|| it.next_entry())
+            .filter(|(_, spill, _)| spill.is_some())
+            .count()
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.raw.leaf_count()
+    }
+
+    pub fn check_invariants_detailed(&self) -> Result<(), InvariantViolation> {
+        self.raw.check_invariants_detailed()
+    }
+}
+
+/// Iterator over a key range of a [`VarBPlusTreeMap`], decoding each entry.
+pub struct VarRange<'a, K, V> {
+    inner: BytesRange<'a, Overflow>,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K: VarKey, V: VarCodec> Iterator for VarRange<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, spill, tail) = self.inner.next_entry()?;
+        Some((decoded(&k), decoded(spill.as_deref().unwrap_or(tail))))
+    }
+}
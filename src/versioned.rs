@@ -0,0 +1,241 @@
+use alloc::vec::Vec;
+use core::ops::RangeBounds;
+
+use crate::{BPlusTreeError, BPlusTreeMap, Items};
+
+/// Monotonically increasing write version.
+pub type Version = u64;
+
+/// Per-key history: `(version, value)` pairs in ascending version order.
+/// A `None` value is a tombstone written by `remove`.
+pub struct VersionChain<V> {
+    entries: Vec<(Version, Option<V>)>,
+}
+
+impl<V> VersionChain<V> {
+    fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, version: Version, value: Option<V>) {
+        debug_assert!(self.entries.last().is_none_or(|(v, _)| *v < version));
+        self.entries.push((version, value));
+    }
+
+    /// Value visible to a reader at `version`, if any.
+    #[inline]
+    pub fn visible_at(&self, version: Version) -> Option<&V> {
+        // Fast rejects: created after the snapshot, or no history at all.
+        let first = self.entries.first()?;
+        if first.0 > version {
+            return None;
+        }
+        let last = self.entries.last()?;
+        if last.0 <= version {
+            return last.1.as_ref();
+        }
+        let idx = self.entries.partition_point(|(v, _)| *v <= version);
+        self.entries[idx - 1].1.as_ref()
+    }
+
+    /// Number of versions (including tombstones) kept for this key.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop every version that no reader at or above `horizon` can observe.
+    /// Returns the number of versions pruned.
+    fn prune(&mut self, horizon: Version) -> usize {
+        // The newest version <= horizon is still visible at horizon; older ones are not.
+        let visible = self.entries.partition_point(|(v, _)| *v <= horizon);
+        if visible == 0 {
+            return 0;
+        }
+        let mut cut = visible - 1;
+        // A tombstone at the horizon hides everything below it and is itself unobservable.
+        if self.entries[cut].1.is_none() {
+            cut += 1;
+        }
+        self.entries.drain(..cut);
+        cut
+    }
+}
+
+/// Multi-version map: every write is tagged with a new version, and readers
+/// observe a consistent snapshot of the map as of any live version.
+///
+/// Leaf values of the underlying tree are [`VersionChain`]s, so a snapshot read
+/// costs one descent plus a search within the key's chain.
+pub struct VersionedBPlusTreeMap<K, V> {
+    tree: BPlusTreeMap<K, VersionChain<V>>,
+    current: Version,
+    oldest_live: Version,
+}
+
+impl<K: Ord + Clone, V> VersionedBPlusTreeMap<K, V> {
+    pub fn new(capacity: usize) -> Result<Self, BPlusTreeError> {
+        Ok(Self {
+            tree: BPlusTreeMap::new(capacity)?,
+            current: 0,
+            oldest_live: 0,
+        })
+    }
+
+    /// Version of the most recent write (0 before any write).
+    pub fn current_version(&self) -> Version {
+        self.current
+    }
+
+    /// Oldest version that can still be read; advanced by [`Self::gc`].
+    pub fn oldest_live_version(&self) -> Version {
+        self.oldest_live
+    }
+
+    /// Insert or overwrite `key`, returning the version of this write.
+    pub fn insert(&mut self, key: K, value: V) -> Version {
+        self.current += 1;
+        let version = self.current;
+        match self.tree.get_mut(&key) {
+            Some(chain) => chain.push(version, Some(value)),
+            None => {
+                let mut chain = VersionChain::new();
+                chain.push(version, Some(value));
+                self.tree.insert(key, chain);
+            }
+        }
+        version
+    }
+
+    /// Remove `key` by writing a tombstone. Returns the version of this write,
+    /// or `None` (without consuming a version) if the key is not currently live.
+    pub fn remove(&mut self, key: &K) -> Option<Version> {
+        let current = self.current;
+        let chain = self.tree.get_mut(key)?;
+        chain.visible_at(current)?;
+        let version = current + 1;
+        chain.push(version, None);
+        self.current = version;
+        Some(version)
+    }
+
+    /// Open a consistent read view as of `version`.
+    pub fn read_at(&self, version: Version) -> Result<ReadView<'_, K, V>, BPlusTreeError> {
+        if version < self.oldest_live {
+            return Err(BPlusTreeError::invalid_state(
+                "read_at",
+                "version was garbage collected",
+            ));
+        }
+        if version > self.current {
+            return Err(BPlusTreeError::invalid_state(
+                "read_at",
+                "version has not been written yet",
+            ));
+        }
+        Ok(ReadView {
+            tree: &self.tree,
+            version,
+        })
+    }
+
+    /// Read view of the latest version.
+    pub fn latest(&self) -> ReadView<'_, K, V> {
+        ReadView {
+            tree: &self.tree,
+            version: self.current,
+        }
+    }
+
+    /// Prune versions older than `oldest_live_version` that no live reader can
+    /// observe. Keys whose chain becomes empty are removed with the tree's
+    /// bulk delete. Returns the number of pruned versions.
+    pub fn gc(&mut self, oldest_live_version: Version) -> usize {
+        let horizon = oldest_live_version.min(self.current).max(self.oldest_live);
+        self.oldest_live = horizon;
+        let mut pruned = 0usize;
+        self.tree.retain(|_, chain| {
+            pruned += chain.prune(horizon);
+            !chain.is_empty()
+        });
+        pruned
+    }
+
+    /// Number of keys with any retained history (live or not).
+    pub fn key_count(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Access the underlying tree of version chains.
+    pub fn tree(&self) -> &BPlusTreeMap<K, VersionChain<V>> {
+        &self.tree
+    }
+}
+
+/// Snapshot of a [`VersionedBPlusTreeMap`] at a fixed version.
+pub struct ReadView<'a, K, V> {
+    tree: &'a BPlusTreeMap<K, VersionChain<V>>,
+    version: Version,
+}
+
+impl<'a, K: Ord, V> ReadView<'a, K, V> {
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn get(&self, key: &K) -> Option<&'a V> {
+        self.tree.get(key)?.visible_at(self.version)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Entries visible at this version whose keys fall in `r`, in key order.
+    pub fn range<R: RangeBounds<K>>(&self, r: R) -> VersionedItems<'a, K, V> {
+        VersionedItems {
+            inner: self.tree.range(r),
+            version: self.version,
+        }
+    }
+
+    /// All entries visible at this version, in key order.
+    pub fn iter(&self) -> VersionedItems<'a, K, V> {
+        VersionedItems {
+            inner: self.tree.items(),
+            version: self.version,
+        }
+    }
+
+    /// Number of entries visible at this version (walks the whole tree).
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+/// Iterator over the entries of a [`ReadView`]. Keys without a version visible
+/// to the reader are skipped in place while walking the leaf chain.
+pub struct VersionedItems<'a, K, V> {
+    inner: Items<'a, K, VersionChain<V>>,
+    version: Version,
+}
+
+impl<'a, K: Ord, V> Iterator for VersionedItems<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let version = self.version;
+        self.inner
+            .by_ref()
+            .find_map(|(k, chain)| chain.visible_at(version).map(|v| (k, v)))
+    }
+}
@@ -0,0 +1,55 @@
+use bplustree::BPlusTreeMap;
+use std::collections::BTreeMap;
+
+fn tree(n: u64, cap: usize) -> (BPlusTreeMap<u64, u64>, BTreeMap<u64, u64>) {
+    let mut t = BPlusTreeMap::new(cap).unwrap();
+    let mut m = BTreeMap::new();
+    for i in 0..n {
+        let k = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) % (3 * n);
+        t.insert(k, i);
+        m.insert(k, i);
+    }
+    (t, m)
+}
+
+#[test]
+fn sorted_and_batched_lookups_match_get() {
+    for cap in [4, 5, 16, 128] {
+        let (t, m) = tree(4000, cap);
+        // Hits and misses, including runs in one leaf and long jumps.
+        let mut probes: Vec<u64> = (0..12_000).step_by(3).collect();
+        probes.extend([0, 0, 11_999, 12_000, u64::MAX]);
+        probes.sort_unstable();
+        let want: Vec<Option<&u64>> = probes.iter().map(|k| m.get(k)).collect();
+        assert_eq!(t.get_many_sorted(&probes), want);
+
+        let mut x = 0x2545_f491_4f6c_dd1du64;
+        let shuffled: Vec<u64> = (0..5000)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x % 12_500
+            })
+            .collect();
+        let want: Vec<Option<&u64>> = shuffled.iter().map(|k| m.get(k)).collect();
+        assert_eq!(t.get_batch(&shuffled), want);
+        // Out-of-order input is slower but still answered correctly.
+        assert_eq!(t.get_many_sorted(&shuffled), want);
+    }
+}
+
+#[test]
+fn batched_lookups_on_small_and_empty_trees() {
+    let mut t: BPlusTreeMap<u64, u64> = BPlusTreeMap::with_cache_lines(2, 2);
+    assert_eq!(t.get_many_sorted(&[1, 2]), vec![None, None]);
+    assert_eq!(t.get_batch(&[1, 2]), vec![None, None]);
+    assert!(t.get_batch(&[]).is_empty());
+
+    t.insert(2, 20);
+    assert_eq!(t.get_many_sorted(&[1, 2, 3]), vec![None, Some(&20), None]);
+    assert_eq!(t.get_batch(&[3, 2]), vec![None, Some(&20)]);
+
+    let (t, _) = tree(10, 4);
+    assert!(t.get_many_sorted(&[]).is_empty());
+}
@@ -0,0 +1,166 @@
+use bplustree::{BPlusTreeError, BytesBPlusTreeMap};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+fn url(i: u64) -> Vec<u8> {
+    format!(
+        "https://example.com/static/{}/assets/{:05}.png",
+        ["css", "img", "js"][(i % 3) as usize],
+        i.wrapping_mul(7919) % 100_000
+    )
+    .into_bytes()
+}
+
+#[test]
+fn matches_btreemap_under_random_ops() {
+    for (leaf, branch) in [(320, 320), (512, 384), (4096, 4096)] {
+        let mut t = BytesBPlusTreeMap::with_budgets(leaf, branch).unwrap();
+        let mut m = BTreeMap::new();
+        let mut x = 0x2545_f491_4f6c_dd1du64;
+        for step in 0..6000u64 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            let k = url(x % 3000);
+            if x % 5 < 3 {
+                assert_eq!(t.insert(&k, step).unwrap(), m.insert(k, step));
+            } else {
+                assert_eq!(t.remove(&k), m.remove(&k));
+            }
+            if step.is_multiple_of(500) {
+                t.check_invariants_detailed().unwrap();
+            }
+        }
+        t.check_invariants_detailed().unwrap();
+        assert_eq!(t.len(), m.len());
+        assert!(t
+            .iter()
+            .map(|(k, v)| (k, *v))
+            .eq(m.iter().map(|(k, v)| (k.clone(), *v))));
+
+        let (a, b) = (url(100), url(2000));
+        let (lo, hi) = if a < b { (a, b) } else { (b, a) };
+        let got: Vec<_> = t
+            .range((Bound::Excluded(&lo[..]), Bound::Included(&hi[..])))
+            .map(|(k, _)| k)
+            .collect();
+        let want: Vec<_> = m
+            .range::<Vec<u8>, _>((Bound::Excluded(&lo), Bound::Included(&hi)))
+            .map(|(k, _)| k.clone())
+            .collect();
+        assert_eq!(got, want);
+
+        for k in m.keys().cloned().collect::<Vec<_>>() {
+            assert_eq!(t.remove(&k), m.remove(&k));
+        }
+        assert!(t.is_empty());
+        t.check_invariants_detailed().unwrap();
+    }
+}
+
+#[test]
+fn shared_prefixes_are_stored_once() {
+    let mut t = BytesBPlusTreeMap::with_budgets(4096, 4096).unwrap();
+    let n = 2000u64;
+    for i in 0..n {
+        t.insert(&url(i), i).unwrap();
+    }
+    t.check_invariants_detailed().unwrap();
+    // Uncompressed, a 4 KiB leaf holds about 4064 / (49 + 16) = 62 of these
+    // keys with their 16-byte slots, so 2000 keys would need 33 full leaves.
+    let key_len = url(0).len();
+    assert!((45..=50).contains(&key_len));
+    assert!(t.leaf_count() < 25, "{} leaves", t.leaf_count());
+    assert_eq!(t.get(&url(1234)), Some(&1234));
+    *t.get_mut(&url(1234)).unwrap() += 1;
+    assert_eq!(t.get(&url(1234)), Some(&1235));
+    assert!(!t.contains_key(b"https://example.com/static/img/"));
+}
+
+#[test]
+fn ranges_of_slices() {
+    let mut t = BytesBPlusTreeMap::with_budgets(512, 512).unwrap();
+    for i in 0..500u64 {
+        t.insert(&url(i), i).unwrap();
+    }
+    let mut m: Vec<Vec<u8>> = (0..500).map(url).collect();
+    m.sort();
+    let (a, b) = (m[100].clone(), m[300].clone());
+    let keys = |r: bplustree::BytesRange<'_, u64>| r.map(|(k, _)| k).collect::<Vec<_>>();
+
+    assert_eq!(keys(t.range(&a[..]..&b[..])), m[100..300]);
+    assert_eq!(keys(t.range(&a[..]..=&b[..])), m[100..=300]);
+    assert_eq!(keys(t.range(a.as_slice()..)), m[100..]);
+    assert_eq!(keys(t.range(..b.as_slice())), m[..300]);
+    assert_eq!(keys(t.range(..)), m);
+    assert_eq!(
+        keys(t.range((Bound::Excluded(&a[..]), Bound::Unbounded))),
+        m[101..]
+    );
+    assert!(keys(t.range(&b[..]..&a[..])).is_empty());
+}
+
+#[test]
+fn keys_without_common_prefix_and_edge_lengths() {
+    let mut t = BytesBPlusTreeMap::with_cache_lines(4, 4).unwrap();
+    let max = t.max_key_len();
+    let mut keys: Vec<Vec<u8>> = vec![Vec::new(), vec![0], vec![0, 0], vec![0xff; max]];
+    for i in 0..300u32 {
+        keys.push(vec![b'a'; (i as usize % max) + 1]);
+        keys.push(i.to_be_bytes().to_vec());
+    }
+    for (i, k) in keys.iter().enumerate() {
+        t.insert(k, i).unwrap();
+    }
+    t.check_invariants_detailed().unwrap();
+    let mut sorted = keys.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert!(t.iter().map(|(k, _)| k).eq(sorted.iter().cloned()));
+    assert_eq!(t.get(&[]), Some(&0));
+
+    assert!(matches!(
+        t.insert(&vec![1; max + 1], 0),
+        Err(BPlusTreeError::InvalidState(_))
+    ));
+    for k in &keys {
+        t.remove(k);
+    }
+    assert!(t.is_empty());
+    assert_eq!(t.leaf_count(), 0);
+}
+
+#[test]
+fn rejects_unusable_budgets() {
+    assert!(matches!(
+        BytesBPlusTreeMap::<u64>::with_budgets(64, 4096),
+        Err(BPlusTreeError::InvalidCapacity(_))
+    ));
+    assert!(matches!(
+        BytesBPlusTreeMap::<u64>::with_budgets(70_000, 4096),
+        Err(BPlusTreeError::InvalidCapacity(_))
+    ));
+    assert!(matches!(
+        BytesBPlusTreeMap::<[u8; 1024]>::with_budgets(4096, 4096),
+        Err(BPlusTreeError::InvalidCapacity(_))
+    ));
+}
+
+#[test]
+fn values_are_dropped_once() {
+    use std::rc::Rc;
+    let marker = Rc::new(());
+    {
+        let mut t = BytesBPlusTreeMap::with_budgets(320, 320).unwrap();
+        for i in 0..1000 {
+            t.insert(&url(i), Rc::clone(&marker)).unwrap();
+        }
+        for i in (0..1000).step_by(2) {
+            drop(t.remove(&url(i)));
+        }
+        assert_eq!(Rc::strong_count(&marker), 501);
+        t.insert(&url(1), Rc::clone(&marker)).unwrap();
+        assert_eq!(Rc::strong_count(&marker), 501);
+    }
+    assert_eq!(Rc::strong_count(&marker), 1);
+}
@@ -0,0 +1,100 @@
+use bplustree::{BPlusTreeMap, Comparator, DeriveSeparator};
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+/// ASCII case-insensitive order for string keys.
+#[derive(Clone, Copy, Default)]
+struct CaseInsensitive;
+
+impl Comparator<String> for CaseInsensitive {
+    fn compare(&self, a: &String, b: &String) -> Ordering {
+        a.bytes()
+            .map(|c| c.to_ascii_lowercase())
+            .cmp(b.bytes().map(|c| c.to_ascii_lowercase()))
+    }
+}
+
+impl DeriveSeparator<String> for CaseInsensitive {
+    fn separator(&self, _left: &String, right: &String) -> String {
+        right.clone()
+    }
+}
+
+/// A key type with no `Ord` impl at all.
+#[derive(Clone, Debug, PartialEq)]
+struct Reading(f64);
+
+#[test]
+fn case_insensitive_keys_collapse() {
+    let mut t = BPlusTreeMap::with_comparator(4, CaseInsensitive).unwrap();
+    for (i, w) in [
+        "delta", "Alpha", "charlie", "BRAVO", "echo", "ALPHA", "Charlie",
+    ]
+    .iter()
+    .enumerate()
+    {
+        t.insert(w.to_string(), i);
+    }
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), 5);
+    assert_eq!(t.get(&"alpha".to_string()), Some(&5));
+    assert_eq!(t.get(&"CHARLIE".to_string()), Some(&6));
+    let keys: Vec<&String> = t.keys().collect();
+    assert_eq!(keys, ["Alpha", "BRAVO", "charlie", "delta", "echo"]);
+
+    let mid: Vec<usize> = t
+        .range("b".to_string().."D".to_string())
+        .map(|(_, v)| *v)
+        .collect();
+    assert_eq!(mid, vec![3, 6]);
+    assert_eq!(t.remove(&"ECHO".to_string()), Some(4));
+    assert!(!t.contains_key(&"echo".to_string()));
+}
+
+#[test]
+fn reverse_order_with_fn_pointer() {
+    let rev: fn(&u64, &u64) -> Ordering = |a, b| b.cmp(a);
+    let mut t = BPlusTreeMap::with_comparator(5, rev).unwrap();
+    for i in 0..2000u64 {
+        t.insert(i * 7 % 2000, i);
+    }
+    for i in (0..2000u64).step_by(3) {
+        assert!(t.remove(&i).is_some());
+    }
+    t.check_invariants_detailed().unwrap();
+    let keys: Vec<u64> = t.keys().copied().collect();
+    let mut expected: Vec<u64> = (0..2000u64).filter(|k| !k.is_multiple_of(3)).collect();
+    expected.reverse();
+    assert_eq!(keys, expected);
+
+    // Bounds follow the comparator: from 100 down to (excluding) 50.
+    let run: Vec<u64> = t
+        .range((Bound::Included(100), Bound::Excluded(50)))
+        .map(|(k, _)| *k)
+        .collect();
+    assert_eq!(
+        run,
+        (51..=100u64)
+            .rev()
+            .filter(|k| !k.is_multiple_of(3))
+            .collect::<Vec<_>>()
+    );
+    let tail: Vec<u64> = t.items().rev().take(3).map(|(k, _)| *k).collect();
+    assert_eq!(tail, vec![1, 2, 4]);
+}
+
+#[test]
+fn keys_without_ord() {
+    let by_value = |a: &Reading, b: &Reading| a.0.total_cmp(&b.0);
+    let mut t = BPlusTreeMap::with_comparator(4, by_value).unwrap();
+    for i in 0..500 {
+        let x = ((i * 37) % 500) as f64 / 10.0 - 25.0;
+        t.insert(Reading(x), i);
+    }
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), 500);
+    assert_eq!(t.first().map(|(k, _)| k.0), Some(-25.0));
+    assert_eq!(t.last().map(|(k, _)| k.0), Some(24.9));
+    assert_eq!(t.range(Reading(-0.05)..Reading(0.25)).count(), 3);
+    assert!(t.comparator()(&Reading(1.0), &Reading(2.0)).is_lt());
+}
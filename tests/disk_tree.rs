@@ -0,0 +1,199 @@
+#![cfg(feature = "std")]
+
+use bplustree::disk::{DiskBPlusTree, FileStorage, MemStorage};
+use bplustree::BPlusTreeError;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!("bplustree_disk_{}_{}.db", name, std::process::id()));
+    p
+}
+
+fn lcg(state: &mut u64) -> u64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *state >> 33
+}
+
+#[test]
+fn insert_get_remove_in_memory() {
+    let mut t: DiskBPlusTree<u64, u64, MemStorage> =
+        DiskBPlusTree::create_with_storage(MemStorage::new(), 128, 8).unwrap();
+    assert!(t.layout().leaf_cap >= 4);
+    for i in 0..500u64 {
+        assert_eq!(t.insert(i * 7 % 500, i).unwrap(), None);
+    }
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), 500);
+    for i in 0..500u64 {
+        assert_eq!(t.get(&(i * 7 % 500)).unwrap(), Some(i));
+    }
+    assert!(t.insert(3, 99).unwrap().is_some());
+    assert_eq!(t.get(&3).unwrap(), Some(99));
+
+    for k in (0..500u64).step_by(2) {
+        assert!(t.remove(&k).unwrap().is_some());
+    }
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), 250);
+    assert_eq!(t.remove(&0).unwrap(), None);
+    let keys: Vec<u64> = t.iter().map(|r| r.unwrap().0).collect();
+    assert_eq!(keys, (1..500).step_by(2).collect::<Vec<_>>());
+}
+
+#[test]
+fn randomized_against_btreemap() {
+    let mut t: DiskBPlusTree<u32, [u8; 12], MemStorage> =
+        DiskBPlusTree::create_with_storage(MemStorage::new(), 256, 4).unwrap();
+    let mut model = BTreeMap::new();
+    let mut s = 42u64;
+    for round in 0..4000u32 {
+        let k = (lcg(&mut s) % 600) as u32;
+        if lcg(&mut s).is_multiple_of(3) {
+            assert_eq!(t.remove(&k).unwrap(), model.remove(&k));
+        } else {
+            let v = [round as u8; 12];
+            assert_eq!(t.insert(k, v).unwrap(), model.insert(k, v));
+        }
+        if round.is_multiple_of(500) {
+            t.check_invariants_detailed().unwrap();
+        }
+    }
+    t.check_invariants_detailed().unwrap();
+    let got: Vec<_> = t.iter().map(|r| r.unwrap()).collect();
+    let want: Vec<_> = model.iter().map(|(k, v)| (*k, *v)).collect();
+    assert_eq!(got, want);
+
+    let got: Vec<_> = t.range(100..=200).map(|r| r.unwrap().0).collect();
+    let want: Vec<_> = model.range(100..=200).map(|(k, _)| *k).collect();
+    assert_eq!(got, want);
+}
+
+#[test]
+fn drain_to_empty_reuses_free_pages() {
+    let mut t: DiskBPlusTree<u64, u64, MemStorage> =
+        DiskBPlusTree::create_with_storage(MemStorage::new(), 128, 16).unwrap();
+    for i in 0..300 {
+        t.insert(i, i).unwrap();
+    }
+    let pages = t.page_count();
+    for i in 0..300 {
+        assert_eq!(t.remove(&i).unwrap(), Some(i));
+    }
+    assert!(t.is_empty());
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.iter().count(), 0);
+    for i in 0..300 {
+        t.insert(i, i).unwrap();
+    }
+    assert_eq!(t.page_count(), pages, "freed pages should be reused");
+    t.check_invariants_detailed().unwrap();
+}
+
+#[test]
+fn reopen_file_after_drop() {
+    let path = temp_path("reopen");
+    {
+        let mut t: DiskBPlusTree<u64, u64> = DiskBPlusTree::create(&path, 512, 4).unwrap();
+        for i in 0..2000u64 {
+            t.insert(i, i * i).unwrap();
+        }
+        for i in (0..2000u64).step_by(3) {
+            t.remove(&i).unwrap();
+        }
+    }
+    {
+        let t: DiskBPlusTree<u64, u64> = DiskBPlusTree::open(&path, 4).unwrap();
+        t.check_invariants_detailed().unwrap();
+        assert_eq!(t.len(), 2000 - 667);
+        assert_eq!(t.get(&5).unwrap(), Some(25));
+        assert_eq!(t.get(&6).unwrap(), None);
+        let stats = t.pool_stats();
+        let _ = t.iter().count();
+        assert!(t.pool_stats().misses > stats.misses);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn tree_larger_than_pool_uses_eviction() {
+    let mut t: DiskBPlusTree<u64, [u8; 32], MemStorage> =
+        DiskBPlusTree::create_with_storage(MemStorage::new(), 256, 3).unwrap();
+    for i in 0..1000u64 {
+        t.insert(i, [i as u8; 32]).unwrap();
+    }
+    assert!(t.pool_stats().evictions > 0);
+    let storage = t.into_storage().unwrap();
+    let t: DiskBPlusTree<u64, [u8; 32], MemStorage> =
+        DiskBPlusTree::open_with_storage(storage, 3).unwrap();
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.get(&999).unwrap(), Some([999u64 as u8; 32]));
+}
+
+#[test]
+fn self_referencing_branch_reports_corruption() {
+    let mut t: DiskBPlusTree<u64, u64, MemStorage> =
+        DiskBPlusTree::create_with_storage(MemStorage::new(), 128, 8).unwrap();
+    for i in 0..200u64 {
+        t.insert(i, i).unwrap();
+    }
+    let mut bytes = t.into_storage().unwrap().bytes().to_vec();
+
+    // Point the root's first child back at the root itself.
+    let root = u32::from_ne_bytes(bytes[24..28].try_into().unwrap());
+    let page = root as usize * 128;
+    assert_eq!(bytes[page], 0, "root should be a branch page");
+    bytes[page + 4..page + 8].copy_from_slice(&root.to_ne_bytes());
+
+    // A failed mutation poisons the tree, so each one gets a fresh open.
+    let open = || {
+        DiskBPlusTree::<u64, u64, MemStorage>::open_with_storage(
+            MemStorage::from_bytes(bytes.clone()),
+            8,
+        )
+        .unwrap()
+    };
+    let t = open();
+    assert!(matches!(t.get(&0), Err(BPlusTreeError::CorruptedTree(_))));
+    assert!(matches!(
+        t.iter().last(),
+        Some(Err(BPlusTreeError::CorruptedTree(_)))
+    ));
+    assert!(t.check_invariants_detailed().is_err());
+    assert!(matches!(
+        open().insert(0, 1),
+        Err(BPlusTreeError::CorruptedTree(_))
+    ));
+    assert!(matches!(
+        open().remove(&0),
+        Err(BPlusTreeError::CorruptedTree(_))
+    ));
+}
+
+#[test]
+fn open_rejects_mismatched_or_corrupt_files() {
+    let t: DiskBPlusTree<u64, u64, MemStorage> =
+        DiskBPlusTree::create_with_storage(MemStorage::new(), 128, 4).unwrap();
+    let storage = t.into_storage().unwrap();
+
+    let wrong = DiskBPlusTree::<u32, u64, MemStorage>::open_with_storage(storage.clone(), 4);
+    assert!(matches!(wrong, Err(BPlusTreeError::InvalidState(_))));
+
+    let mut bytes = storage.bytes().to_vec();
+    bytes[0] ^= 0xff;
+    let bad =
+        DiskBPlusTree::<u64, u64, MemStorage>::open_with_storage(MemStorage::from_bytes(bytes), 4);
+    assert!(matches!(bad, Err(BPlusTreeError::CorruptedTree(_))));
+
+    let tiny = DiskBPlusTree::<u64, u64, MemStorage>::create_with_storage(MemStorage::new(), 32, 4);
+    assert!(matches!(tiny, Err(BPlusTreeError::InvalidCapacity(_))));
+
+    let missing = DiskBPlusTree::<u64, u64, FileStorage>::open(
+        std::path::Path::new("/nonexistent-dir/x.db"),
+        4,
+    );
+    assert!(matches!(missing, Err(BPlusTreeError::IoError(_))));
+}
@@ -0,0 +1,318 @@
+#![cfg(feature = "std")]
+
+//! Write-ahead log tests, including a fault-injection harness that "crashes"
+//! the storage at every write point of a workload and checks that recovery
+//! yields a valid tree holding a committed prefix of the operations.
+
+use bplustree::disk::{wal_path, DiskBPlusTree, MemStorage, Storage};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::io;
+use std::rc::Rc;
+
+/// Storage whose contents outlive the tree and which fails every write after
+/// a shared budget runs out. Writes land in a pending view that `sync` copies
+/// to the durable store; a crash discards whatever was not synced. The write
+/// that exhausts the budget is torn: only its first half reaches the durable
+/// store.
+#[derive(Clone)]
+struct FaultyStorage {
+    pending: Rc<RefCell<MemStorage>>,
+    durable: Rc<RefCell<MemStorage>>,
+    budget: Rc<Cell<Option<u64>>>,
+    writes: Rc<Cell<u64>>,
+}
+
+impl FaultyStorage {
+    fn new(budget: &Rc<Cell<Option<u64>>>, writes: &Rc<Cell<u64>>) -> Self {
+        Self {
+            pending: Rc::new(RefCell::new(MemStorage::new())),
+            durable: Rc::new(RefCell::new(MemStorage::new())),
+            budget: budget.clone(),
+            writes: writes.clone(),
+        }
+    }
+
+    /// What survives a crash: the contents as of the last successful sync.
+    fn snapshot(&self) -> MemStorage {
+        self.durable.borrow().clone()
+    }
+
+    fn crashed(&self) -> bool {
+        self.budget.get() == Some(0)
+    }
+
+    /// Consume one write point; `false` means this write is the crash.
+    fn tick(&self) -> io::Result<bool> {
+        if self.crashed() {
+            return Err(io::Error::other("crashed"));
+        }
+        self.writes.set(self.writes.get() + 1);
+        match self.budget.get() {
+            Some(1) => {
+                self.budget.set(Some(0));
+                Ok(false)
+            }
+            Some(n) => {
+                self.budget.set(Some(n - 1));
+                Ok(true)
+            }
+            None => Ok(true),
+        }
+    }
+}
+
+impl Storage for FaultyStorage {
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        if self.crashed() {
+            return Err(io::Error::other("crashed"));
+        }
+        self.pending.borrow_mut().read_at(offset, buf)
+    }
+
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> io::Result<()> {
+        if self.tick()? {
+            self.pending.borrow_mut().write_at(offset, buf)
+        } else {
+            self.durable
+                .borrow_mut()
+                .write_at(offset, &buf[..buf.len() / 2])?;
+            Err(io::Error::other("crashed mid-write"))
+        }
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        if self.crashed() {
+            return Err(io::Error::other("crashed"));
+        }
+        *self.durable.borrow_mut() = self.pending.borrow().clone();
+        Ok(())
+    }
+
+    fn len(&mut self) -> io::Result<u64> {
+        self.pending.borrow_mut().len()
+    }
+
+    fn set_len(&mut self, len: u64) -> io::Result<()> {
+        if self.tick()? {
+            self.pending.borrow_mut().set_len(len)
+        } else {
+            Err(io::Error::other("crashed before truncate"))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Insert(u32, u64),
+    Remove(u32),
+    Sync,
+    Checkpoint,
+}
+
+fn workload() -> Vec<Op> {
+    let mut ops = Vec::new();
+    let mut s = 7u64;
+    for i in 0..240u32 {
+        s = s
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let k = ((s >> 33) % 150) as u32;
+        if i > 80 && (s >> 20).is_multiple_of(3) {
+            ops.push(Op::Remove(k));
+        } else {
+            ops.push(Op::Insert(k, i as u64));
+        }
+        if i % 7 == 6 {
+            ops.push(Op::Sync);
+        }
+        if i % 90 == 89 {
+            ops.push(Op::Checkpoint);
+        }
+    }
+    ops
+}
+
+fn model_after(ops: &[Op], n: usize) -> Vec<(u32, u64)> {
+    let mut m = BTreeMap::new();
+    for op in &ops[..n] {
+        match *op {
+            Op::Insert(k, v) => {
+                m.insert(k, v);
+            }
+            Op::Remove(k) => {
+                m.remove(&k);
+            }
+            Op::Sync | Op::Checkpoint => {}
+        }
+    }
+    m.into_iter().collect()
+}
+
+struct CrashRun {
+    data: MemStorage,
+    log: MemStorage,
+    /// Operations known durable (a later sync or checkpoint succeeded).
+    durable: usize,
+    /// Operations started, including the one that hit the crash.
+    attempted: usize,
+    writes: u64,
+}
+
+fn run_until_crash(ops: &[Op], budget: Option<u64>) -> CrashRun {
+    let limit = Rc::new(Cell::new(None));
+    let writes = Rc::new(Cell::new(0));
+    let data = FaultyStorage::new(&limit, &writes);
+    let log = FaultyStorage::new(&limit, &writes);
+    let mut tree: DiskBPlusTree<u32, u64, FaultyStorage> =
+        DiskBPlusTree::create_with_log(data.clone(), log.clone(), 128, 4).unwrap();
+    writes.set(0);
+    limit.set(budget);
+
+    let mut durable = 0;
+    let mut attempted = 0;
+    for (i, op) in ops.iter().enumerate() {
+        attempted = i + 1;
+        let ok = match *op {
+            Op::Insert(k, v) => tree.insert(k, v).is_ok(),
+            Op::Remove(k) => tree.remove(&k).is_ok(),
+            Op::Sync => tree.sync().is_ok(),
+            Op::Checkpoint => tree.checkpoint().is_ok(),
+        };
+        if !ok {
+            break;
+        }
+        if matches!(op, Op::Sync | Op::Checkpoint) {
+            durable = i + 1;
+        }
+    }
+    let finished = attempted == ops.len() && !data.crashed();
+    // Dropping a healthy tree checkpoints, which makes the unsynced tail durable.
+    drop(tree);
+    if finished && !data.crashed() {
+        durable = ops.len();
+    }
+    CrashRun {
+        data: data.snapshot(),
+        log: log.snapshot(),
+        durable,
+        attempted,
+        writes: writes.get(),
+    }
+}
+
+#[test]
+fn recovers_committed_prefix_at_every_write_point() {
+    let ops = workload();
+    let clean = run_until_crash(&ops, None);
+    assert!(clean.writes > 50, "workload should exercise many writes");
+
+    for crash_at in 1..=clean.writes {
+        let run = run_until_crash(&ops, Some(crash_at));
+        let tree: DiskBPlusTree<u32, u64, MemStorage> =
+            DiskBPlusTree::open_with_log(run.data, run.log, 4)
+                .unwrap_or_else(|e| panic!("crash at write {}: open failed: {}", crash_at, e));
+        if let Err(e) = tree.check_invariants_detailed() {
+            panic!("crash at write {}: invariants violated: {}", crash_at, e);
+        }
+        let got: Vec<(u32, u64)> = tree.iter().map(|r| r.unwrap()).collect();
+        let matched = (run.durable..=run.attempted).any(|n| model_after(&ops, n) == got);
+        assert!(
+            matched,
+            "crash at write {}: recovered state is not a prefix in {}..={}",
+            crash_at, run.durable, run.attempted
+        );
+        assert_eq!(tree.len(), got.len());
+    }
+}
+
+#[test]
+fn unsynced_operations_are_lost_and_synced_ones_survive() {
+    let limit = Rc::new(Cell::new(None));
+    let writes = Rc::new(Cell::new(0));
+    let data = FaultyStorage::new(&limit, &writes);
+    let log = FaultyStorage::new(&limit, &writes);
+    let mut tree: DiskBPlusTree<u32, u64, FaultyStorage> =
+        DiskBPlusTree::create_with_log(data.clone(), log.clone(), 256, 64).unwrap();
+    for k in 0..100 {
+        tree.insert(k, k as u64).unwrap();
+    }
+    tree.sync().unwrap();
+    assert!(tree.log_len() > 0);
+    for k in 100..150 {
+        tree.insert(k, k as u64).unwrap();
+    }
+    // Simulate process death: nothing further reaches storage.
+    std::mem::forget(tree);
+
+    let tree: DiskBPlusTree<u32, u64, MemStorage> =
+        DiskBPlusTree::open_with_log(data.snapshot(), log.snapshot(), 8).unwrap();
+    tree.check_invariants_detailed().unwrap();
+    assert_eq!(tree.len(), 100);
+    assert!(tree.recovery_stats().replayed_commits >= 100);
+    assert_eq!(tree.get(&99).unwrap(), Some(99));
+    assert_eq!(tree.get(&100).unwrap(), None);
+}
+
+#[test]
+fn checkpoint_truncates_log() {
+    let mut tree: DiskBPlusTree<u64, u64, MemStorage> =
+        DiskBPlusTree::create_with_log(MemStorage::new(), MemStorage::new(), 256, 8).unwrap();
+    assert!(tree.is_logged());
+    for k in 0..500 {
+        tree.insert(k, k).unwrap();
+    }
+    assert!(tree.log_len() > 0);
+    tree.checkpoint().unwrap();
+    assert_eq!(tree.log_len(), 0);
+    tree.check_invariants_detailed().unwrap();
+}
+
+#[test]
+fn torn_log_tail_is_discarded() {
+    let limit = Rc::new(Cell::new(None));
+    let writes = Rc::new(Cell::new(0));
+    let data = FaultyStorage::new(&limit, &writes);
+    let log = FaultyStorage::new(&limit, &writes);
+    let mut tree: DiskBPlusTree<u32, u64, FaultyStorage> =
+        DiskBPlusTree::create_with_log(data.clone(), log.clone(), 128, 64).unwrap();
+    for k in 0..40 {
+        tree.insert(k, 1).unwrap();
+    }
+    tree.sync().unwrap();
+    std::mem::forget(tree);
+
+    // Corrupt the final record (the last commit) and append garbage.
+    let mut bytes = log.snapshot().bytes().to_vec();
+    let n = bytes.len();
+    bytes[n - 5] ^= 0x40;
+    bytes.extend_from_slice(&[0xAB; 37]);
+    let tree: DiskBPlusTree<u32, u64, MemStorage> =
+        DiskBPlusTree::open_with_log(data.snapshot(), MemStorage::from_bytes(bytes), 8).unwrap();
+    tree.check_invariants_detailed().unwrap();
+    assert_eq!(tree.len(), 39);
+    assert!(tree.recovery_stats().discarded_records > 0);
+}
+
+#[test]
+fn logged_file_tree_roundtrip() {
+    let mut path = std::env::temp_dir();
+    path.push(format!("bplustree_wal_{}.db", std::process::id()));
+    {
+        let mut t: DiskBPlusTree<u64, u64> = DiskBPlusTree::create_logged(&path, 512, 8).unwrap();
+        for k in 0..1000 {
+            t.insert(k, k + 1).unwrap();
+        }
+        t.sync().unwrap();
+        assert!(wal_path(&path).exists());
+    }
+    {
+        let t: DiskBPlusTree<u64, u64> = DiskBPlusTree::open_logged(&path, 8).unwrap();
+        t.check_invariants_detailed().unwrap();
+        assert_eq!(t.len(), 1000);
+        assert_eq!(t.get(&500).unwrap(), Some(501));
+        assert_eq!(t.log_len(), 0);
+    }
+    std::fs::remove_file(wal_path(&path)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,112 @@
+use bplustree::{BPlusTreeMap, DumpOptions};
+
+fn tree(n: u64) -> BPlusTreeMap<u64, u64> {
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    for k in 0..n {
+        t.insert(k, k * 10);
+    }
+    t
+}
+
+fn dot(t: &BPlusTreeMap<u64, u64>, opts: &DumpOptions) -> String {
+    let mut out = String::new();
+    t.to_dot_with(&mut out, opts).unwrap();
+    out
+}
+
+#[test]
+fn dot_has_every_node_and_edge() {
+    let t = tree(40);
+    let stats = t.stats();
+    let mut out = String::new();
+    t.to_dot(&mut out).unwrap();
+    assert!(out.starts_with("digraph bplustree {\n"));
+    assert!(out.ends_with("}\n"));
+    let count = |needle: &str| out.matches(needle).count();
+    assert_eq!(count("[label="), stats.leaves + stats.branches);
+    assert_eq!(
+        count(";\n") - count("[label=") - count("style=dashed") - 1,
+        stats.leaves + stats.branches - 1,
+        "one solid edge per child"
+    );
+    assert_eq!(count("[style=dashed, constraint=false]"), stats.leaves - 1);
+    assert_eq!(count("color=gray"), stats.leaves - 1);
+    assert!(out.contains("0: 0|1: 10"));
+    assert!(!out.contains("fillcolor"));
+
+    let mut out = String::new();
+    BPlusTreeMap::<u64, u64>::with_cache_lines(2, 2)
+        .to_dot(&mut out)
+        .unwrap();
+    assert_eq!(
+        out,
+        "digraph bplustree {\n  node [shape=record, fontname=\"monospace\"];\n}\n"
+    );
+}
+
+#[test]
+fn dot_escapes_record_syntax() {
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    t.insert(String::from("x|y"), "<{}>");
+    let mut out = String::new();
+    t.to_dot(&mut out).unwrap();
+    assert!(out.contains(r#"\"x\|y\": \"\<\{\}\>\""#), "{}", out);
+}
+
+#[test]
+fn ascii_lists_levels_by_path() {
+    let t = tree(40);
+    let stats = t.stats();
+    let out = t.dump_ascii();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[0], "level 0 (1 node)");
+    assert!(lines[1].starts_with("  [] branch "), "{}", out);
+    let leaves: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|l| l.contains("] leaf "))
+        .collect();
+    assert_eq!(leaves.len(), stats.leaves);
+    assert_eq!(
+        out.matches("level ").count(),
+        stats.height,
+        "one header per level"
+    );
+    assert!(leaves[0].starts_with(&format!(
+        "  {:?} leaf 0: 0, 1: 10",
+        vec![0; stats.height - 1]
+    )));
+    assert!(!out.contains("underfull"));
+
+    assert_eq!(
+        BPlusTreeMap::<u64, u64>::with_cache_lines(2, 2).dump_ascii(),
+        "(empty tree)\n"
+    );
+}
+
+#[test]
+fn truncation_and_underfull_highlighting() {
+    let mut t = BPlusTreeMap::new(16).unwrap();
+    for k in 0..200u64 {
+        t.insert(k, k);
+    }
+    let short = DumpOptions::new().max_entries(2);
+    let out = t.dump_ascii_with(&short);
+    assert!(out.contains("] leaf 0: 0, 1: 1 ... 6 more\n"), "{}", out);
+    assert!(dot(&t, &short).contains("|... "));
+    assert!(dot(&t, &short).contains(":more -> "));
+
+    t.set_deferred_rebalance(true);
+    for k in 20..30 {
+        t.remove(&k);
+    }
+    let out = t.dump_ascii();
+    assert!(out.contains("<- underfull"), "{}", out);
+    assert!(dot(&t, &DumpOptions::new()).contains("fillcolor"));
+    let quiet = DumpOptions::new().highlight_underfull(false);
+    assert!(!t.dump_ascii_with(&quiet).contains("underfull"));
+    assert!(!dot(&t, &quiet).contains("fillcolor"));
+
+    t.compact();
+    assert!(!t.dump_ascii().contains("underfull"));
+}
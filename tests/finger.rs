@@ -0,0 +1,112 @@
+use bplustree::{BPlusTreeMap, NodeTag};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+thread_local! {
+    static VISITS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_visit(_: NodeTag) {
+    VISITS.with(|v| v.set(v.get() + 1));
+}
+
+#[test]
+fn ascending_inserts_with_interleaved_removes() {
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    let mut m = BTreeMap::new();
+    for i in 0..4000u64 {
+        assert_eq!(t.insert(i, i), m.insert(i, i));
+        // Reads and removes behind the insertion point split, merge and
+        // borrow around the cached leaf.
+        if i % 3 == 2 {
+            let k = i / 2;
+            assert_eq!(t.get(&k), m.get(&k));
+            assert_eq!(t.remove(&k), m.remove(&k));
+        }
+        assert_eq!(t.get(&i), Some(&i));
+        if i % 250 == 0 {
+            t.check_invariants_detailed().unwrap();
+        }
+    }
+    t.check_invariants_detailed().unwrap();
+    assert!(t.items().eq(m.iter()));
+}
+
+#[test]
+fn clustered_inserts_and_lookups_around_moving_hot_spots() {
+    let mut t = BPlusTreeMap::new(5).unwrap();
+    let mut m = BTreeMap::new();
+    let mut x = 0x2545_f491_4f6c_dd1du64;
+    for round in 0..200u64 {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        let base = (x % 1000) * 64;
+        for j in 0..40 {
+            let k = base + (j * 7 + round) % 64;
+            assert_eq!(t.insert(k, round), m.insert(k, round));
+            assert_eq!(t.get(&(base + j)), m.get(&(base + j)));
+        }
+        if round % 4 == 0 {
+            for j in 0..30 {
+                assert_eq!(t.remove(&(base + j)), m.remove(&(base + j)));
+            }
+        }
+        if let Some(v) = t.get_mut(&base) {
+            *v += 1;
+            *m.get_mut(&base).unwrap() += 1;
+        }
+    }
+    t.check_invariants_detailed().unwrap();
+    assert!(t.items().eq(m.iter()));
+}
+
+#[test]
+fn rebuilding_and_clearing_forget_the_cached_leaf() {
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    for i in 0..500u32 {
+        t.insert(i, i);
+    }
+    assert_eq!(t.get(&499), Some(&499));
+    assert_eq!(t.retain(|k, _| k % 7 == 0), 428);
+    assert_eq!(t.get(&497), Some(&497));
+    assert_eq!(t.get(&499), None);
+    t.insert(501, 1);
+    t.check_invariants_detailed().unwrap();
+
+    t.clear();
+    assert_eq!(t.get(&497), None);
+    t.insert(7, 7);
+    assert_eq!(t.get(&7), Some(&7));
+    assert_eq!(t.len(), 1);
+
+    for i in (0..300u32).rev() {
+        t.insert(i, i);
+    }
+    for i in 0..300u32 {
+        assert_eq!(t.remove(&i), Some(i));
+    }
+    assert!(t.is_empty());
+    assert_eq!(t.get(&3), None);
+    t.insert(3, 3);
+    t.check_invariants_detailed().unwrap();
+}
+
+#[test]
+fn inserts_descend_at_most_once() {
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    for i in 0..2000u64 {
+        t.insert(i * 2, i);
+    }
+    t.set_visit_hook(Some(count_visit));
+    // Odd keys scattered across full leaves: each misses the finger, and
+    // the descent that finds the leaf is the one that splits it.
+    for i in 0..500u64 {
+        let k = i * 1237 % 2000 * 2 + 1;
+        let height = t.stats().height;
+        VISITS.with(|v| v.set(0));
+        assert_eq!(t.insert(k, 0), None);
+        assert!(VISITS.with(|v| v.get()) <= height);
+    }
+    t.check_invariants_detailed().unwrap();
+}
@@ -0,0 +1,143 @@
+use bplustree::{BPlusTreeError, BPlusTreeMap, FrozenBPlusTree, InvariantViolation};
+
+fn build(n: u64, cap: usize) -> BPlusTreeMap<u64, [u32; 3]> {
+    let mut t = BPlusTreeMap::new(cap).unwrap();
+    for i in (0..n).rev() {
+        t.insert(i * 2, [i as u32, 7, 9]);
+    }
+    t
+}
+
+#[test]
+fn frozen_matches_map_lookups_and_ranges() {
+    for cap in [4usize, 5, 16] {
+        for n in [0u64, 1, 4, 5, 37, 1000] {
+            let t = build(n, cap);
+            let f = t.freeze();
+            f.check_invariants_detailed().unwrap();
+            assert_eq!(f.len(), t.len());
+            assert!(f.iter().eq(t.items()));
+            assert!(f.iter().rev().eq(t.items().rev()));
+            for k in 0..2 * n + 2 {
+                assert_eq!(f.get(&k), t.get(&k), "cap {} n {} key {}", cap, n, k);
+            }
+            let (a, b) = (n / 3, n + 5);
+            assert!(f.range(a..b).eq(t.range(a..b)));
+            assert!(f.range(a..=b).rev().eq(t.range(a..=b).rev()));
+            assert!(f.range(..a).eq(t.range(..a)));
+            assert!(f.range(b..).eq(t.range(b..)));
+            assert_eq!(f.range(b..a).count(), 0);
+        }
+    }
+}
+
+#[test]
+fn range_mixes_front_and_back() {
+    let f = build(100, 4).freeze();
+    let mut r = f.range(10..=60);
+    let mut seen = Vec::new();
+    loop {
+        match (r.next(), r.next_back()) {
+            (Some(a), Some(b)) => seen.extend([*a.0, *b.0]),
+            (Some(a), None) | (None, Some(a)) => seen.push(*a.0),
+            (None, None) => break,
+        }
+    }
+    seen.sort_unstable();
+    assert_eq!(seen, (5..=30).map(|i| i * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn leaves_are_packed_full() {
+    // Descending inserts leave the mutable tree's leaves about half full.
+    let f = build(1000, 16).freeze();
+    // 64-byte header; 62 full leaves (24 + 16 * 8 + 16 * 12 bytes) and one of
+    // 8 entries; four branches over 16/16/16/15 leaves and a root over them.
+    let leaves = 62 * 344 + (24 + 8 * 8 + 8 * 12);
+    let branches = 3 * (8 + 16 * 8 + 15 * 8) + (8 + 15 * 8 + 14 * 8) + (8 + 4 * 8 + 3 * 8);
+    assert_eq!(f.as_bytes().len(), 64 + leaves + branches);
+}
+
+#[test]
+fn reopen_from_borrowed_and_copied_bytes() {
+    let f = build(500, 8).freeze();
+    let bytes = f.as_bytes();
+
+    let opened = FrozenBPlusTree::<u64, [u32; 3]>::open(bytes).unwrap();
+    assert_eq!(opened.get(&998), Some(&[499, 7, 9]));
+    assert!(opened.iter().eq(f.iter()));
+
+    let mut path = std::env::temp_dir();
+    path.push(format!("bplustree_frozen_{}.img", std::process::id()));
+    std::fs::write(&path, bytes).unwrap();
+    let read = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let loaded = FrozenBPlusTree::<u64, [u32; 3]>::load(&read).unwrap();
+    loaded.check_invariants_detailed().unwrap();
+    assert!(loaded.range(100..200).eq(f.range(100..200)));
+}
+
+#[test]
+fn open_rejects_bad_images() {
+    let f = build(300, 4).freeze();
+    let bytes = f.as_bytes().to_vec();
+    let load = |b: &[u8]| FrozenBPlusTree::<u64, [u32; 3]>::load(b);
+
+    assert!(matches!(
+        load(&bytes[..40]),
+        Err(BPlusTreeError::CorruptedTree(_))
+    ));
+    assert!(matches!(
+        load(&bytes[..bytes.len() - 8]),
+        Err(BPlusTreeError::CorruptedTree(_))
+    ));
+    assert!(matches!(
+        FrozenBPlusTree::<u32, [u32; 3]>::load(&bytes),
+        Err(BPlusTreeError::CorruptedTree(_))
+    ));
+
+    // Magic, endian marker, root offset and a leaf tag.
+    for (pos, xor) in [(0usize, 1u8), (10, 0xff), (48, 0x08), (64, 0x03)] {
+        let mut bad = bytes.clone();
+        bad[pos] ^= xor;
+        assert!(
+            matches!(load(&bad), Err(BPlusTreeError::CorruptedTree(_))),
+            "flip at {}",
+            pos
+        );
+    }
+
+    // A child pointer redirected at an earlier subtree breaks the leaf chain.
+    let root = u64::from_ne_bytes(bytes[48..56].try_into().unwrap()) as usize;
+    let mut bad = bytes.clone();
+    let first_child = bad[root + 8..root + 16].to_vec();
+    bad[root + 16..root + 24].copy_from_slice(&first_child);
+    assert!(matches!(load(&bad), Err(BPlusTreeError::CorruptedTree(_))));
+
+    // Misaligned borrowed buffer.
+    let mut shifted = vec![0u8; bytes.len() + 1];
+    let start = if (shifted.as_ptr() as usize).is_multiple_of(8) {
+        1
+    } else {
+        0
+    };
+    shifted[start..start + bytes.len()].copy_from_slice(&bytes);
+    let res = FrozenBPlusTree::<u64, [u32; 3]>::open(&shifted[start..start + bytes.len()]);
+    assert!(matches!(res, Err(BPlusTreeError::InvalidState(_))));
+}
+
+#[test]
+fn detailed_check_catches_unsorted_keys() {
+    let f = build(8, 4).freeze();
+    let mut bytes = f.as_bytes().to_vec();
+    // First leaf sits right after the header; its keys start after 24 bytes.
+    bytes[64 + 24..64 + 32].copy_from_slice(&1000u64.to_ne_bytes());
+    let g = FrozenBPlusTree::<u64, [u32; 3]>::load(&bytes).unwrap();
+    assert_eq!(
+        g.check_invariants_detailed(),
+        Err(InvariantViolation::UnsortedKeys {
+            path: vec![0],
+            index: 1
+        })
+    );
+}
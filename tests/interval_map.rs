@@ -0,0 +1,99 @@
+use bplustree::BPlusIntervalMap;
+
+const SPAN: u32 = 300;
+
+/// Maximal runs of one value in a per-point model, as the map should hold
+/// them after coalescing.
+fn runs(model: &[Option<u8>]) -> Vec<(u32, u32, u8)> {
+    let mut out: Vec<(u32, u32, u8)> = Vec::new();
+    for (k, v) in model.iter().enumerate() {
+        let (k, Some(v)) = (k as u32, *v) else {
+            continue;
+        };
+        match out.last_mut() {
+            Some(last) if last.1 == k && last.2 == v => last.1 = k + 1,
+            _ => out.push((k, k + 1, v)),
+        }
+    }
+    out
+}
+
+fn collect<'a>(
+    it: impl Iterator<Item = (std::ops::Range<&'a u32>, &'a u8)>,
+) -> Vec<(u32, u32, u8)> {
+    it.map(|(r, v)| (*r.start, *r.end, *v)).collect()
+}
+
+#[test]
+fn matches_per_point_model() {
+    let mut t = BPlusIntervalMap::new(4).unwrap();
+    let mut model = vec![None; SPAN as usize];
+    let mut x = 0x9e37_79b9_7f4a_7c15u64;
+    for step in 0..4000u32 {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        let a = (x % SPAN as u64) as u32;
+        let len = ((x >> 20) % 40) as u32;
+        let b = (a + len).min(SPAN);
+        // Few values, so neighbours often match and must merge.
+        let v = ((x >> 40) % 3) as u8;
+        if (x >> 50).is_multiple_of(4) {
+            t.remove_range(a..b);
+            model[a as usize..b as usize].fill(None);
+        } else {
+            t.insert_range(a..b, v);
+            model[a as usize..b as usize].fill(Some(v));
+        }
+        if step.is_multiple_of(50) {
+            t.check_invariants_detailed().unwrap();
+            for k in 0..SPAN {
+                assert_eq!(t.get_point(&k), model[k as usize].as_ref(), "step {}", step);
+            }
+        }
+        let want: Vec<_> = runs(&model)
+            .into_iter()
+            .filter(|&(s, e, _)| a < b && s < b && a < e)
+            .collect();
+        assert_eq!(collect(t.overlapping(a..b)), want, "step {}", step);
+    }
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(collect(t.iter()), runs(&model));
+    assert_eq!(t.len(), runs(&model).len());
+}
+
+#[test]
+fn splits_overwrites_and_coalesces() {
+    let mut t = BPlusIntervalMap::new(4).unwrap();
+    t.insert_range(10..20, 'a');
+    t.insert_range(14..16, 'b');
+    assert_eq!(
+        collect_chars(&t),
+        [(10, 14, 'a'), (14, 16, 'b'), (16, 20, 'a')]
+    );
+    t.insert_range(14..16, 'a');
+    assert_eq!(collect_chars(&t), [(10, 20, 'a')]);
+    t.insert_range(20..25, 'a');
+    t.insert_range(5..10, 'a');
+    assert_eq!(collect_chars(&t), [(5, 25, 'a')]);
+    t.insert_range(25..30, 'c');
+    assert_eq!(collect_chars(&t), [(5, 25, 'a'), (25, 30, 'c')]);
+
+    assert_eq!(t.get_point(&4), None);
+    assert_eq!(t.get_point(&5), Some(&'a'));
+    assert_eq!(t.get_point(&25), Some(&'c'));
+    assert_eq!(t.get_point(&30), None);
+
+    t.remove_range(8..27);
+    assert_eq!(collect_chars(&t), [(5, 8, 'a'), (27, 30, 'c')]);
+    t.remove_range(0..100);
+    assert!(t.is_empty());
+    t.insert_range(3..3, 'z');
+    assert!(t.is_empty());
+    assert_eq!(t.overlapping(0..100).count(), 0);
+    t.check_invariants_detailed().unwrap();
+}
+
+fn collect_chars(t: &BPlusIntervalMap<u32, char>) -> Vec<(u32, u32, char)> {
+    t.iter().map(|(r, v)| (*r.start, *r.end, *v)).collect()
+}
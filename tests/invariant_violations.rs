@@ -0,0 +1,189 @@
+//! Corrupt one thing at a time in a healthy tree and check the violation
+//! reported, then undo it so the tree drops cleanly.
+
+use bplustree::{carve_leaf, BPlusTreeMap, InvariantViolation, LeafLink, LeafParts, TreeConfig};
+use std::ptr::NonNull;
+
+/// A root branch over a handful of leaves, so leaf `i` has path `[i]`.
+fn two_levels() -> BPlusTreeMap<u64, u64> {
+    let mut t = TreeConfig::new().capacity(16).build().unwrap();
+    for k in 0..60 {
+        t.insert(k * 10, k);
+    }
+    assert_eq!(t.stats().height, 2);
+    t.check_invariants_detailed().unwrap();
+    t
+}
+
+/// The leaves in chain order.
+fn leaves(t: &BPlusTreeMap<u64, u64>) -> Vec<LeafParts<u64, u64>> {
+    let layout = *t.leaf_layout();
+    let first = t.first().unwrap().0 as *const u64 as *mut u8;
+    let mut cur = NonNull::new(first.wrapping_sub(layout.keys_off));
+    let mut out = Vec::new();
+    while let Some(leaf) = cur {
+        let parts = unsafe { carve_leaf::<u64, u64>(leaf, &layout) };
+        cur = NonNull::new(unsafe { *parts.next_ptr });
+        out.push(parts);
+    }
+    assert!(out.len() >= 3);
+    out
+}
+
+fn key(parts: &LeafParts<u64, u64>, i: usize) -> *mut u64 {
+    unsafe { parts.keys_ptr.add(i) as *mut u64 }
+}
+
+fn len(parts: &LeafParts<u64, u64>) -> usize {
+    unsafe { (*parts.hdr).len as usize }
+}
+
+#[test]
+fn unsorted_keys_name_the_leaf_and_index() {
+    let t = two_levels();
+    let l = leaves(&t);
+    unsafe {
+        std::ptr::swap(key(&l[1], 2), key(&l[1], 3));
+        assert_eq!(
+            t.check_invariants_detailed(),
+            Err(InvariantViolation::UnsortedKeys {
+                path: vec![1],
+                index: 3
+            })
+        );
+        std::ptr::swap(key(&l[1], 2), key(&l[1], 3));
+    }
+    t.check_invariants_detailed().unwrap();
+}
+
+#[test]
+fn separator_bounds() {
+    let t = two_levels();
+    let l = leaves(&t);
+    let last = len(&l[0]) - 1;
+    unsafe {
+        let saved = *key(&l[0], last);
+        *key(&l[0], last) = 5_000;
+        let err = t.check_invariants_detailed().unwrap_err();
+        assert_eq!(
+            err,
+            InvariantViolation::SeparatorBound {
+                path: vec![0],
+                index: last
+            }
+        );
+        assert_eq!(err.path(), [0]);
+        assert_eq!(
+            err.to_string(),
+            format!("node [0] key {} outside its separator bounds", last)
+        );
+        *key(&l[0], last) = saved;
+    }
+    t.check_invariants_detailed().unwrap();
+}
+
+#[test]
+fn broken_links() {
+    let t = two_levels();
+    let l = leaves(&t);
+    unsafe {
+        let next = l[0].next_ptr;
+        let saved = *next;
+        *next = l[2].hdr as *mut u8;
+        assert_eq!(
+            t.check_invariants_detailed(),
+            Err(InvariantViolation::BrokenLink {
+                path: vec![0],
+                link: LeafLink::Next
+            })
+        );
+        *next = saved;
+
+        let prev = l[2].prev_ptr.unwrap();
+        let saved = *prev;
+        *prev = l[0].hdr as *mut u8;
+        assert_eq!(
+            t.check_invariants_detailed(),
+            Err(InvariantViolation::BrokenLink {
+                path: vec![2],
+                link: LeafLink::Prev
+            })
+        );
+        *prev = saved;
+
+        let last = l.last().unwrap();
+        *last.next_ptr = l[0].hdr as *mut u8;
+        let err = t.check_invariants_detailed().unwrap_err();
+        assert_eq!(err.path(), [l.len() - 1]);
+        assert!(matches!(
+            err,
+            InvariantViolation::BrokenLink {
+                link: LeafLink::Next,
+                ..
+            }
+        ));
+        *last.next_ptr = std::ptr::null_mut();
+    }
+    t.check_invariants_detailed().unwrap();
+}
+
+#[test]
+fn fill_violations() {
+    let t = two_levels();
+    let l = leaves(&t);
+    let cap = t.leaf_layout().cap;
+    let hdr = l[1].hdr;
+    unsafe {
+        let saved = (*hdr).len;
+        (*hdr).len = 1;
+        assert!(matches!(
+            t.check_invariants_detailed(),
+            Err(InvariantViolation::Underfull { ref path, len: 1, .. }) if *path == [1]
+        ));
+        (*hdr).len = 0;
+        assert_eq!(
+            t.check_invariants_detailed(),
+            Err(InvariantViolation::EmptyNode { path: vec![1] })
+        );
+        (*hdr).len = cap + 1;
+        assert_eq!(
+            t.check_invariants_detailed(),
+            Err(InvariantViolation::Overfull {
+                path: vec![1],
+                len: cap as usize + 1,
+                cap: cap as usize
+            })
+        );
+        (*hdr).len = saved;
+    }
+    t.check_invariants_detailed().unwrap();
+    assert!(t.check_invariants());
+}
+
+#[test]
+fn violations_without_a_node_have_an_empty_path() {
+    let cases = [
+        (
+            InvariantViolation::CountMismatch { counted: 3, len: 4 },
+            "3 entries but len is 4",
+        ),
+        (
+            InvariantViolation::EmptyInterval { index: 2 },
+            "interval 2 is empty",
+        ),
+        (
+            InvariantViolation::OverlappingIntervals { index: 5 },
+            "interval 5 overlaps the one before",
+        ),
+        (
+            InvariantViolation::UncoalescedIntervals { index: 1 },
+            "interval 1 was not coalesced",
+        ),
+    ];
+    for (v, msg) in cases {
+        assert_eq!(v.to_string(), msg);
+        assert!(v.path().is_empty());
+    }
+    let heap = InvariantViolation::CorruptHeap { path: vec![0, 2] };
+    assert_eq!(heap.path(), [0, 2]);
+}
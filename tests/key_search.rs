@@ -0,0 +1,84 @@
+use bplustree::{branchless_search, BPlusTreeMap, SearchKey};
+use std::collections::BTreeMap;
+
+fn lcg(state: &mut u64) -> u64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *state
+}
+
+fn check_against_binary_search<T: SearchKey + std::fmt::Debug>(keys: &[T], probes: &[T]) {
+    for p in probes {
+        let expected = keys.binary_search(p);
+        assert_eq!(
+            T::search(keys, p),
+            expected,
+            "len {} probe {:?}",
+            keys.len(),
+            p
+        );
+        assert_eq!(branchless_search(keys, p), expected);
+    }
+}
+
+#[test]
+fn search_matches_binary_search_for_all_lengths() {
+    let mut state = 7;
+    for len in 0..=130usize {
+        let mut raw: Vec<u64> = (0..len).map(|_| lcg(&mut state)).collect();
+        // Keep a few extremes in play for the sign-bit flip.
+        raw.extend(
+            [0, 1, u64::MAX, 1 << 63, (1 << 63) - 1]
+                .iter()
+                .take(len.min(5)),
+        );
+        raw.sort_unstable();
+        raw.dedup();
+        let mut probes = raw.clone();
+        probes.extend(raw.iter().map(|k| k.wrapping_add(1)));
+        probes.extend([0, u64::MAX, 1 << 63, (1 << 63) - 1]);
+        check_against_binary_search(&raw, &probes);
+
+        let mut signed: Vec<i64> = raw.iter().map(|&k| k as i64).collect();
+        signed.sort_unstable();
+        let sprobes: Vec<i64> = probes.iter().map(|&k| k as i64).collect();
+        check_against_binary_search(&signed, &sprobes);
+
+        let mut small: Vec<u32> = raw.iter().map(|&k| (k >> 32) as u32).collect();
+        small.sort_unstable();
+        small.dedup();
+        let small_probes: Vec<u32> = probes.iter().map(|&k| (k >> 32) as u32).collect();
+        check_against_binary_search(&small, &small_probes);
+
+        let mut small_signed: Vec<i32> = small.iter().map(|&k| k as i32).collect();
+        small_signed.sort_unstable();
+        let ssp: Vec<i32> = small_probes.iter().map(|&k| k as i32).collect();
+        check_against_binary_search(&small_signed, &ssp);
+    }
+}
+
+#[test]
+fn map_with_key_search_matches_btreemap() {
+    for cap in [4usize, 16, 128] {
+        let mut t = BPlusTreeMap::new(cap).unwrap();
+        assert!(!t.uses_key_search());
+        t.enable_key_search();
+        assert!(t.uses_key_search());
+        let mut model = BTreeMap::new();
+        let mut state = cap as u64;
+        for i in 0..20_000u32 {
+            let k = (lcg(&mut state) % 5000) as i64 - 2500;
+            if i.is_multiple_of(3) {
+                assert_eq!(t.remove(&k), model.remove(&k));
+            } else {
+                assert_eq!(t.insert(k, i), model.insert(k, i));
+            }
+        }
+        t.check_invariants_detailed().unwrap();
+        for k in -2600..2600 {
+            assert_eq!(t.get(&k), model.get(&k));
+        }
+        assert!(t.range(-100..100).eq(model.range(-100..100)));
+    }
+}
@@ -0,0 +1,275 @@
+use bplustree::{
+    alloc_branch_block, alloc_leaf_block, carve_branch, carve_leaf, dealloc_raw, BPlusTreeMap,
+    BranchLayout, LeafLayout, NodeHdr, TreeConfig,
+};
+use std::collections::BTreeMap;
+use std::mem::{align_of, size_of};
+use std::ptr::NonNull;
+
+macro_rules! padded {
+    ($($name:ident: align $align:literal, $bytes:literal bytes;)*) => {$(
+        #[repr(C, align($align))]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct $name([u8; $bytes]);
+    )*};
+}
+
+padded! {
+    A1S3: align 1, 3 bytes;
+    A2S6: align 2, 6 bytes;
+    A8S0: align 8, 0 bytes;
+    A8S24: align 8, 24 bytes;
+    A64S64: align 64, 64 bytes;
+    A128S0: align 128, 0 bytes;
+    A128S128: align 128, 128 bytes;
+    A512S1024: align 512, 1024 bytes;
+    A4096S4096: align 4096, 4096 bytes;
+}
+
+/// Byte ranges `(start, len, what)` a node's parts occupy; zero-length parts
+/// occupy nothing.
+type Regions = Vec<(usize, usize, &'static str)>;
+
+fn check_regions(regions: &Regions, bytes: usize, ctx: &str) {
+    for &(start, len, what) in regions {
+        assert!(
+            start + len <= bytes,
+            "{}: {} ends past {}",
+            ctx,
+            what,
+            bytes
+        );
+    }
+    let mut live: Vec<_> = regions.iter().filter(|r| r.1 > 0).collect();
+    live.sort();
+    for w in live.windows(2) {
+        assert!(
+            w[0].0 + w[0].1 <= w[1].0,
+            "{}: {} overlaps {}",
+            ctx,
+            w[0].2,
+            w[1].2
+        );
+    }
+}
+
+fn offset<T>(base: NonNull<u8>, p: *mut T, align: usize, ctx: &str) -> usize {
+    assert_eq!(p as usize % align, 0, "{}: misaligned", ctx);
+    p as usize - base.as_ptr() as usize
+}
+
+unsafe fn check_leaf<K, V>(layout: &LeafLayout, ctx: &str) {
+    if layout.cap == 0 {
+        return;
+    }
+    let base = alloc_leaf_block(layout).unwrap();
+    let parts = carve_leaf::<K, V>(base, layout);
+    let word = size_of::<*mut u8>();
+    let cap = layout.cap as usize;
+    let mut regions: Regions = vec![
+        (0, size_of::<NodeHdr>(), "header"),
+        (
+            offset(base, parts.next_ptr, align_of::<*mut u8>(), ctx),
+            word,
+            "next",
+        ),
+        (
+            offset(base, parts.keys_ptr, align_of::<K>(), ctx),
+            cap * size_of::<K>(),
+            "keys",
+        ),
+        (
+            offset(base, parts.vals_ptr, align_of::<V>(), ctx),
+            cap * size_of::<V>(),
+            "values",
+        ),
+    ];
+    if let Some(prev) = parts.prev_ptr {
+        regions.push((offset(base, prev, align_of::<*mut u8>(), ctx), word, "prev"));
+    }
+    check_regions(&regions, layout.bytes, ctx);
+    assert_eq!(base.as_ptr() as usize % layout.max_align, 0);
+    dealloc_raw(base, layout.bytes, layout.max_align);
+}
+
+unsafe fn check_branch<K>(layout: &BranchLayout, ctx: &str) {
+    if layout.cap == 0 {
+        return;
+    }
+    let base = alloc_branch_block(layout).unwrap();
+    let parts = carve_branch::<K>(base, layout);
+    let cap = layout.cap as usize;
+    let regions: Regions = vec![
+        (0, size_of::<NodeHdr>(), "header"),
+        (
+            offset(base, parts.children_ptr, align_of::<*mut u8>(), ctx),
+            (cap + 1) * size_of::<*mut u8>(),
+            "children",
+        ),
+        (
+            offset(base, parts.keys_ptr, align_of::<K>(), ctx),
+            cap * size_of::<K>(),
+            "keys",
+        ),
+    ];
+    check_regions(&regions, layout.bytes, ctx);
+    dealloc_raw(base, layout.bytes, layout.max_align);
+}
+
+fn check_pair<K, V>() {
+    let name = format!(
+        "<{}, {}>",
+        std::any::type_name::<K>(),
+        std::any::type_name::<V>()
+    );
+    let budgets = (0..=24).map(|i| i * 40).chain([4096, 8192, 20_000, 70_000]);
+    for bytes in budgets {
+        for doubly_linked in [true, false] {
+            let layout = LeafLayout::compute::<K, V>(bytes, doubly_linked);
+            assert!(layout.bytes <= bytes || layout.cap == 0);
+            let ctx = format!("{} leaf budget {} linked {}", name, bytes, doubly_linked);
+            unsafe { check_leaf::<K, V>(&layout, &ctx) };
+        }
+        let layout = BranchLayout::compute::<K>(bytes);
+        unsafe { check_branch::<K>(&layout, &format!("{} branch budget {}", name, bytes)) };
+    }
+    for cap in [1u16, 4, 5, 16, 255, 1000] {
+        for doubly_linked in [true, false] {
+            let layout = LeafLayout::compute_for_cap::<K, V>(cap, doubly_linked);
+            assert_eq!(layout.cap, cap);
+            let ctx = format!("{} leaf cap {} linked {}", name, cap, doubly_linked);
+            unsafe { check_leaf::<K, V>(&layout, &ctx) };
+        }
+        let layout = BranchLayout::compute_for_cap::<K>(cap);
+        assert_eq!(layout.cap, cap);
+        unsafe { check_branch::<K>(&layout, &format!("{} branch cap {}", name, cap)) };
+    }
+}
+
+macro_rules! matrix {
+    ($($k:ty),*) => {{
+        $( matrix!(@row $k; (), u8, u64, A1S3, A2S6, A8S0, A8S24, A64S64, A128S0,
+            A128S128, A512S1024, A4096S4096); )*
+    }};
+    (@row $k:ty; $($v:ty),*) => {{
+        $( check_pair::<$k, $v>(); )*
+    }};
+}
+
+#[test]
+fn carved_regions_are_disjoint_aligned_and_in_bounds() {
+    matrix!(
+        (),
+        u8,
+        u64,
+        u128,
+        A1S3,
+        A2S6,
+        A8S0,
+        A8S24,
+        A64S64,
+        A128S0,
+        A128S128,
+        A512S1024,
+        A4096S4096
+    );
+}
+
+#[test]
+fn zero_sized_entries_fill_any_budget() {
+    let l = LeafLayout::compute::<(), ()>(256, true);
+    assert_eq!(l.cap, u16::MAX);
+    let l = LeafLayout::compute::<A128S0, ()>(64, false);
+    assert_eq!(l.cap, 0, "the header alone needs 128 bytes");
+
+    // Without a value array a leaf fits twice as many u64 keys.
+    let set = LeafLayout::compute::<u64, ()>(256, true);
+    let map = LeafLayout::compute::<u64, u64>(256, true);
+    assert!(set.cap >= 2 * map.cap, "{} vs {}", set.cap, map.cap);
+    assert!(TreeConfig::new()
+        .leaf_bytes(1 << 20)
+        .build::<(), ()>()
+        .is_ok());
+}
+
+#[test]
+fn unit_map() {
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    assert_eq!(t.insert((), ()), None);
+    assert_eq!(t.insert((), ()), Some(()));
+    assert_eq!(t.len(), 1);
+    assert_eq!(t.get(&()), Some(&()));
+    assert_eq!(t.items().rev().count(), 1);
+    assert_eq!(t.remove(&()), Some(()));
+    assert!(t.is_empty());
+    t.check_invariants_detailed().unwrap();
+
+    let mut t = TreeConfig::new()
+        .cache_lines(1, 1)
+        .build::<(), u64>()
+        .unwrap();
+    t.insert((), 7);
+    assert_eq!(t.insert((), 8), Some(7));
+    assert_eq!(t.get(&()), Some(&8));
+}
+
+#[test]
+fn zero_sized_values_make_a_set() {
+    let mut t = BPlusTreeMap::with_cache_lines(2, 2);
+    let mut m = BTreeMap::new();
+    for i in 0..5000u64 {
+        let k = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) % 3000;
+        if i.is_multiple_of(3) {
+            assert_eq!(t.remove(&k), m.remove(&k));
+        } else {
+            assert_eq!(t.insert(k, ()), m.insert(k, ()));
+        }
+    }
+    t.check_invariants_detailed().unwrap();
+    assert!(t.keys().eq(m.keys()));
+    assert!(t.items().rev().eq(m.iter().rev()));
+}
+
+fn over_aligned_round_trip<K: Ord + Clone + std::fmt::Debug>(key: impl Fn(u16) -> K, cap: usize) {
+    let mut t = BPlusTreeMap::new(cap).unwrap();
+    let mut m = BTreeMap::new();
+    for i in 0..600u16 {
+        let k = key(i.wrapping_mul(7919) % 400);
+        if i % 4 == 3 {
+            assert_eq!(t.remove(&k), m.remove(&k));
+        } else {
+            assert_eq!(t.insert(k.clone(), i), m.insert(k, i));
+        }
+    }
+    t.check_invariants_detailed().unwrap();
+    assert!(t.items().eq(m.iter()));
+    for k in t.keys() {
+        assert_eq!(k as *const K as usize % align_of::<K>(), 0);
+    }
+    let keys: Vec<K> = m.keys().cloned().collect();
+    for k in keys {
+        assert_eq!(t.remove(&k), m.remove(&k));
+    }
+    assert!(t.is_empty());
+}
+
+#[test]
+fn over_aligned_keys() {
+    let be = |i: u16| i.to_be_bytes();
+    over_aligned_round_trip(
+        |i| {
+            let mut a = [0; 128];
+            a[..2].copy_from_slice(&be(i));
+            A128S128(a)
+        },
+        5,
+    );
+    over_aligned_round_trip(
+        |i| {
+            let mut a = [0; 4096];
+            a[..2].copy_from_slice(&be(i));
+            A4096S4096(a)
+        },
+        4,
+    );
+}
@@ -0,0 +1,178 @@
+use bplustree::{BPlusTreeMap, MergeThreshold};
+use std::collections::BTreeMap;
+
+fn full_tree(threshold: MergeThreshold, n: u64) -> BPlusTreeMap<u64, u64> {
+    let mut t = BPlusTreeMap::new(16).unwrap();
+    t.set_merge_threshold(threshold);
+    assert_eq!(t.merge_threshold(), threshold);
+    for k in 0..n {
+        t.insert(k, k);
+    }
+    t
+}
+
+/// Remove all keys but those divisible by `keep_every`.
+fn thin_out(t: &mut BPlusTreeMap<u64, u64>, n: u64, keep_every: u64) {
+    for k in 0..n {
+        if !k.is_multiple_of(keep_every) {
+            assert_eq!(t.remove(&k), Some(k));
+        }
+    }
+    t.check_invariants_detailed().unwrap();
+    assert!(t.keys().copied().eq((0..n).step_by(keep_every as usize)));
+}
+
+#[test]
+fn lower_thresholds_merge_later() {
+    let leaves = |threshold, keep_every| {
+        let mut t = full_tree(threshold, 4000);
+        let before = t.stats().leaves;
+        thin_out(&mut t, 4000, keep_every);
+        (before, t.stats().leaves)
+    };
+    // Leaves split by sequential inserts hold 8 or 9 of 16 entries; keeping
+    // every other key leaves 4 or 5, below half but above a quarter.
+    let (before, half) = leaves(MergeThreshold::Half, 2);
+    assert!(half < before * 3 / 4, "{} -> {}", before, half);
+    assert_eq!(leaves(MergeThreshold::Percent(25), 2), (before, before));
+    assert!(leaves(MergeThreshold::Percent(25), 8).1 < before);
+    assert_eq!(leaves(MergeThreshold::Empty, 8), (before, before));
+    // Out-of-range percentages are clamped to 50.
+    assert_eq!(leaves(MergeThreshold::Percent(200), 2).1, half);
+}
+
+#[test]
+fn alternating_insert_remove_at_the_boundary_keeps_structure() {
+    let mut t = full_tree(MergeThreshold::Percent(25), 2000);
+    thin_out(&mut t, 2000, 2);
+    let shape = t.stats();
+    for round in 0..200u64 {
+        let k = 1 + 2 * (round % 900);
+        t.insert(k, k);
+        assert_eq!(t.remove(&k), Some(k));
+    }
+    assert_eq!(t.stats(), shape);
+    t.check_invariants_detailed().unwrap();
+}
+
+#[test]
+fn deferred_deletes_mark_nodes_until_compact() {
+    let mut t = full_tree(MergeThreshold::Half, 5000);
+    t.set_deferred_rebalance(true);
+    assert!(t.deferred_rebalance());
+    let before = t.stats();
+    thin_out(&mut t, 5000, 5);
+    // Only leaves emptied outright were removed.
+    assert_eq!(t.stats().leaves, before.leaves);
+    assert!(t.stats().leaf_fill() < 0.2);
+
+    assert!(t.compact() > 0);
+    t.check_invariants_detailed().unwrap();
+    assert!(t.stats().leaf_fill() >= 0.5, "{:?}", t.stats());
+    assert!(t.keys().copied().eq((0..5000).step_by(5)));
+    assert_eq!(t.compact(), 0);
+
+    // Emptying leaves still removes them immediately.
+    for k in (0..5000).step_by(5) {
+        assert_eq!(t.remove(&k), Some(k));
+        if k % 1000 == 0 {
+            t.check_invariants_detailed().unwrap();
+        }
+    }
+    assert!(t.is_empty());
+    assert_eq!(t.items().count(), 0);
+}
+
+#[test]
+fn raising_the_threshold_compacts() {
+    let mut t = full_tree(MergeThreshold::Empty, 3000);
+    thin_out(&mut t, 3000, 6);
+    assert!(t.stats().leaf_fill() < 0.2);
+    t.set_merge_threshold(MergeThreshold::Half);
+    t.check_invariants_detailed().unwrap();
+    assert!(t.stats().leaf_fill() >= 0.5, "{:?}", t.stats());
+    assert!(t.keys().copied().eq((0..3000).step_by(6)));
+}
+
+#[test]
+fn random_operations_under_each_mode() {
+    for (threshold, deferred) in [
+        (MergeThreshold::Half, true),
+        (MergeThreshold::Percent(20), false),
+        (MergeThreshold::Percent(20), true),
+        (MergeThreshold::Empty, false),
+    ] {
+        let mut t = BPlusTreeMap::new(5).unwrap();
+        t.set_merge_threshold(threshold);
+        t.set_deferred_rebalance(deferred);
+        let mut m = BTreeMap::new();
+        let mut x = 0x2545_f491_4f6c_dd1du64;
+        for step in 0..8000u64 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            let k = x % 2000;
+            if x % 5 < 2 {
+                assert_eq!(t.insert(k, step), m.insert(k, step));
+            } else {
+                assert_eq!(t.remove(&k), m.remove(&k));
+            }
+            if step.is_multiple_of(400) {
+                t.check_invariants_detailed().unwrap();
+            }
+            if step.is_multiple_of(1500) {
+                t.compact();
+                t.check_invariants_detailed().unwrap();
+            }
+            if step == 4000 {
+                // Refill so the second half deletes from a large tree again.
+                for k in 0..2000 {
+                    t.insert(k, k);
+                    m.insert(k, k);
+                }
+            }
+        }
+        t.check_invariants_detailed().unwrap();
+        assert!(t.items().eq(m.iter()));
+        assert!(t.items().rev().eq(m.iter().rev()));
+    }
+}
+
+#[test]
+fn compact_fixes_underfull_grandchildren() {
+    // From capacity 6 up, merging two nearly empty leaves can still leave
+    // one below half full, under a branch that has no other child until
+    // its own parent rebalances it.
+    for cap in 6..=9 {
+        for seed in 1..=12u64 {
+            let mut t = BPlusTreeMap::new(cap).unwrap();
+            t.set_deferred_rebalance(true);
+            let mut m = BTreeMap::new();
+            let mut x = 0x9e37_79b9_7f4a_7c15u64 ^ seed.wrapping_mul(0x0123_4567);
+            for round in 0..2u64 {
+                for i in 0..2000u64 {
+                    let k = i.wrapping_mul(0x9e37_79b9) % 8000;
+                    t.insert(k, i);
+                    m.insert(k, i);
+                }
+                // Mostly deletes, scattered so that many nodes are left
+                // short before a single compact.
+                for step in 0..8000u64 {
+                    x ^= x << 13;
+                    x ^= x >> 7;
+                    x ^= x << 17;
+                    let k = x % 8000;
+                    if x % 100 < 10 {
+                        assert_eq!(t.insert(k, step), m.insert(k, step));
+                    } else {
+                        assert_eq!(t.remove(&k), m.remove(&k));
+                    }
+                }
+                t.compact();
+                t.check_invariants_detailed()
+                    .unwrap_or_else(|e| panic!("cap {} seed {} round {}: {}", cap, seed, round, e));
+                assert!(t.items().eq(m.iter()));
+            }
+        }
+    }
+}
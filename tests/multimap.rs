@@ -0,0 +1,88 @@
+use bplustree::BPlusTreeMultiMap;
+use std::collections::BTreeMap;
+
+fn flatten(m: &BTreeMap<u64, Vec<u64>>) -> Vec<(u64, u64)> {
+    m.iter()
+        .flat_map(|(k, vs)| vs.iter().map(move |v| (*k, *v)))
+        .collect()
+}
+
+#[test]
+fn matches_btreemap_of_vecs() {
+    let mut t = BPlusTreeMultiMap::new(4).unwrap();
+    let mut m: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    let mut x = 0x9e37_79b9_7f4a_7c15u64;
+    for step in 0..8000u64 {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        // Few keys, so most of them have values across several leaves.
+        let k = x % 40;
+        match x % 10 {
+            0..=5 => {
+                t.insert(k, step);
+                m.entry(k).or_default().push(step);
+            }
+            6..=8 => {
+                let want = m.get_mut(&k).map(|vs| vs.remove(0));
+                if m.get(&k).is_some_and(|vs| vs.is_empty()) {
+                    m.remove(&k);
+                }
+                assert_eq!(t.remove_one(&k), want);
+            }
+            _ => {
+                let want = m.remove(&k).unwrap_or_default();
+                assert_eq!(t.remove_all(&k), want);
+            }
+        }
+        if step.is_multiple_of(250) {
+            t.check_invariants_detailed().unwrap();
+        }
+        let want = m.get(&k).map_or(&[][..], |vs| &vs[..]);
+        assert!(t.get_all(&k).eq(want.iter()), "step {}", step);
+        assert_eq!(t.count(&k), want.len());
+        assert_eq!(t.contains_key(&k), !want.is_empty());
+    }
+    t.check_invariants_detailed().unwrap();
+    let flat = flatten(&m);
+    assert_eq!(t.len(), flat.len());
+    assert!(t.iter().map(|(k, v)| (*k, *v)).eq(flat.iter().copied()));
+    assert!(t
+        .iter()
+        .rev()
+        .map(|(k, v)| (*k, *v))
+        .eq(flat.iter().rev().copied()));
+    for k in 0..40 {
+        assert!(t.get_all(&k).eq(m.get(&k).into_iter().flatten()));
+    }
+    t.clear();
+    assert!(t.is_empty());
+    assert_eq!(t.get_all(&1).next(), None);
+}
+
+#[test]
+fn one_key_across_many_leaves() {
+    let mut t = BPlusTreeMultiMap::new(4).unwrap();
+    t.insert(0u32, 0u32);
+    t.insert(2, 0);
+    for v in 0..1000 {
+        t.insert(1, v);
+    }
+    t.check_invariants_detailed().unwrap();
+    assert!(t.as_map().leaf_count() > 250);
+    assert!(t.get_all(&1).copied().eq(0..1000));
+    assert_eq!(t.count(&0), 1);
+    assert_eq!(t.count(&2), 1);
+    assert!(!t.contains_key(&3));
+
+    for v in 0..500 {
+        assert_eq!(t.remove_one(&1), Some(v));
+    }
+    t.check_invariants_detailed().unwrap();
+    assert!(t.get_all(&1).copied().eq(500..1000));
+    assert_eq!(t.remove_all(&1), (500..1000).collect::<Vec<_>>());
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), 2);
+    assert_eq!(t.remove_one(&1), None);
+    assert!(t.iter().eq([(&0, &0), (&2, &0)]));
+}
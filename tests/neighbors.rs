@@ -0,0 +1,106 @@
+use bplustree::{BPlusTreeMap, Comparator, DeriveSeparator, TreeConfig};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ops::Bound::{Excluded, Unbounded};
+use std::rc::Rc;
+
+fn check_neighbors(
+    t: &BPlusTreeMap<u64, u64>,
+    m: &BTreeMap<u64, u64>,
+    keys: impl Iterator<Item = u64>,
+) {
+    for k in keys {
+        assert_eq!(t.floor(&k), m.range(..=k).next_back(), "floor {}", k);
+        assert_eq!(t.ceiling(&k), m.range(k..).next(), "ceiling {}", k);
+        assert_eq!(
+            t.predecessor(&k),
+            m.range(..k).next_back(),
+            "predecessor {}",
+            k
+        );
+        assert_eq!(
+            t.successor(&k),
+            m.range((Excluded(k), Unbounded)).next(),
+            "successor {}",
+            k
+        );
+    }
+}
+
+#[test]
+fn neighbors_match_btreemap() {
+    for doubly_linked in [true, false] {
+        let mut t = TreeConfig::new()
+            .capacity(4)
+            .doubly_linked(doubly_linked)
+            .build::<u64, u64>()
+            .unwrap();
+        let mut m = BTreeMap::new();
+        check_neighbors(&t, &m, 0..3);
+        let mut x = 0x9e37_79b9_7f4a_7c15u64;
+        for step in 0..3000u64 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            // Even keys only, so odd queries fall between entries.
+            let k = 2 * (x % 1000);
+            if x.is_multiple_of(3) {
+                assert_eq!(t.remove(&k), m.remove(&k));
+            } else {
+                assert_eq!(t.insert(k, step), m.insert(k, step));
+            }
+            if step.is_multiple_of(300) {
+                check_neighbors(&t, &m, 0..2002);
+            }
+        }
+        t.check_invariants_detailed().unwrap();
+        check_neighbors(&t, &m, 0..2002);
+    }
+}
+
+/// `Ord` order, counting comparisons.
+#[derive(Clone, Default)]
+struct Counting(Rc<Cell<usize>>);
+
+impl Comparator<u64> for Counting {
+    fn compare(&self, a: &u64, b: &u64) -> Ordering {
+        self.0.set(self.0.get() + 1);
+        a.cmp(b)
+    }
+}
+
+impl DeriveSeparator<u64> for Counting {
+    fn separator(&self, _left: &u64, right: &u64) -> u64 {
+        *right
+    }
+}
+
+type Tree = BPlusTreeMap<u64, u64, Counting>;
+
+#[test]
+fn neighbors_take_one_descent() {
+    let counter = Counting::default();
+    let mut t = BPlusTreeMap::with_comparator(16, counter.clone()).unwrap();
+    for k in 0..100_000u64 {
+        t.insert(2 * k, k);
+    }
+    // Leaf boundaries are where a hop to the sibling happens.
+    type Query = for<'a> fn(&'a Tree, &u64) -> Option<(&'a u64, &'a u64)>;
+    for k in [0, 1, 31, 32, 33, 100_001, 199_998, 199_999, 200_000] {
+        for (name, query) in [
+            ("floor", Tree::floor as Query),
+            ("ceiling", Tree::ceiling),
+            ("predecessor", Tree::predecessor),
+            ("successor", Tree::successor),
+        ] {
+            counter.0.set(0);
+            query(&t, &k);
+            // About 4 levels of binary search over at most 16 keys.
+            assert!(counter.0.get() < 40, "{} {}: {}", name, k, counter.0.get());
+        }
+    }
+    assert_eq!(t.floor(&199_999), Some((&199_998, &99_999)));
+    assert_eq!(t.successor(&199_998), None);
+    assert_eq!(t.predecessor(&0), None);
+}
@@ -0,0 +1,67 @@
+use bplustree::{BPlusTreeError, BPlusTreeMap};
+use std::collections::BTreeMap;
+
+fn shuffled_tree(n: u64) -> (BPlusTreeMap<u64, u64>, BTreeMap<u64, u64>) {
+    let mut t = BPlusTreeMap::new(8).unwrap();
+    let mut m = BTreeMap::new();
+    for i in 0..n {
+        let k = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) % (4 * n);
+        t.insert(k, i);
+        m.insert(k, i);
+    }
+    (t, m)
+}
+
+#[test]
+fn prefetching_does_not_change_iteration() {
+    let (mut t, m) = shuffled_tree(5000);
+    assert_eq!(t.prefetch_distance(), 0);
+    for distance in [0, 1, 4, 10_000] {
+        t.set_prefetch_distance(distance);
+        assert_eq!(t.prefetch_distance(), distance);
+        assert!(t.items().eq(m.iter()));
+        assert!(t.items().rev().eq(m.iter().rev()));
+        assert!(t.range(3000..9000).eq(m.range(3000..9000)));
+        assert!(t.range(..=777).rev().eq(m.range(..=777).rev()));
+
+        let mut both = t.range(100..15_000);
+        let mut want = m.range(100..15_000);
+        for step in 0.. {
+            let (got, exp) = if step % 3 == 0 {
+                (both.next_back(), want.next_back())
+            } else {
+                (both.next(), want.next())
+            };
+            assert_eq!(got, exp);
+            if got.is_none() {
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn batched_get_many_matches_get() {
+    let (mut t, m) = shuffled_tree(3000);
+    let present: Vec<u64> = m.keys().rev().step_by(7).copied().collect();
+    let with_missing: Vec<u64> = present.iter().copied().chain([u64::MAX]).collect();
+    for distance in [0, 2] {
+        t.set_prefetch_distance(distance);
+        let got: Vec<u64> = t.get_many(&present).unwrap().into_iter().copied().collect();
+        let want: Vec<u64> = present.iter().map(|k| m[k]).collect();
+        assert_eq!(got, want);
+        assert_eq!(t.get_many(&[]).unwrap(), Vec::<&u64>::new());
+        assert!(matches!(
+            t.get_many(&with_missing),
+            Err(BPlusTreeError::KeyNotFound)
+        ));
+    }
+
+    let mut empty: BPlusTreeMap<u64, u64> = BPlusTreeMap::with_cache_lines(2, 2);
+    empty.set_prefetch_distance(2);
+    assert!(matches!(
+        empty.get_many(&[1]),
+        Err(BPlusTreeError::KeyNotFound)
+    ));
+    assert_eq!(empty.items().count(), 0);
+}
@@ -0,0 +1,99 @@
+use bplustree::{BPlusTreeMap, MergeThreshold, TreeConfig};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Counts its drops.
+struct Tracked(u64, Rc<Cell<usize>>);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.1.set(self.1.get() + 1);
+    }
+}
+
+fn lcg(state: &mut u64) -> u64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *state >> 33
+}
+
+#[test]
+fn retain_matches_btreemap() {
+    let thresholds = [
+        MergeThreshold::Half,
+        MergeThreshold::Percent(25),
+        MergeThreshold::Empty,
+    ];
+    let mut seed = 7;
+    for threshold in thresholds {
+        for (doubly_linked, deferred) in [(true, false), (false, false), (true, true)] {
+            let mut t = TreeConfig::new()
+                .capacity(5)
+                .doubly_linked(doubly_linked)
+                .merge_threshold(threshold)
+                .build()
+                .unwrap();
+            t.set_deferred_rebalance(deferred);
+            let mut m = BTreeMap::new();
+            for _ in 0..3000 {
+                let k = lcg(&mut seed) % 5000;
+                t.insert(k, k);
+                m.insert(k, k);
+            }
+            // From a few scattered removals up to emptying the tree.
+            for modulus in [97, 5, 2, 1] {
+                let rejects = |k: &u64| k.is_multiple_of(modulus);
+                let removed = t.retain(|k, v| {
+                    *v += 1;
+                    !rejects(k)
+                });
+                let before = m.len();
+                m.retain(|k, v| {
+                    *v += 1;
+                    !rejects(k)
+                });
+                assert_eq!(removed, before - m.len());
+                t.check_invariants_detailed().unwrap();
+                assert!(t.items().eq(m.iter()));
+                assert!(t.items().rev().eq(m.iter().rev()));
+            }
+            assert!(t.is_empty());
+            t.insert(1, 1);
+            assert_eq!(t.get(&1), Some(&1));
+        }
+    }
+}
+
+#[test]
+fn few_rejections_leave_other_leaves_in_place() {
+    let mut t = BPlusTreeMap::new(16).unwrap();
+    for k in 0..10_000u64 {
+        t.insert(k, k);
+    }
+    let before = t.stats();
+    let far = t.get(&9_000).unwrap() as *const u64;
+    assert_eq!(t.retain(|k, _| *k != 10), 1);
+    t.check_invariants_detailed().unwrap();
+    // A rebuild would move every entry and pack the half-full leaves; at
+    // most the one leaf that lost an entry merges with a neighbour.
+    assert_eq!(t.get(&9_000).unwrap() as *const u64, far);
+    assert!(t.stats().leaves + 1 >= before.leaves);
+    assert_eq!(t.len(), 9_999);
+}
+
+#[test]
+fn rejected_entries_are_dropped_once() {
+    let drops = Rc::new(Cell::new(0));
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    for k in 0..1000u64 {
+        t.insert(k, Tracked(k, drops.clone()));
+    }
+    assert_eq!(t.retain(|k, v| v.0 == *k && !k.is_multiple_of(3)), 334);
+    assert_eq!(drops.get(), 334);
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), 666);
+    drop(t);
+    assert_eq!(drops.get(), 1000);
+}
@@ -0,0 +1,88 @@
+use bplustree::{BPlusTreeMap, NodeTag, TreeConfig};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+thread_local! {
+    static VISITS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_visit(_: NodeTag) {
+    VISITS.with(|v| v.set(v.get() + 1));
+}
+
+/// Nodes `f` steps into.
+fn visits(f: impl FnOnce()) -> usize {
+    VISITS.with(|v| v.set(0));
+    f();
+    VISITS.with(|v| v.get())
+}
+
+fn tree(n: u64, doubly_linked: bool) -> (BPlusTreeMap<u64, u64>, BTreeMap<u64, u64>) {
+    let mut t = TreeConfig::new()
+        .capacity(16)
+        .doubly_linked(doubly_linked)
+        .build()
+        .unwrap();
+    let mut m = BTreeMap::new();
+    for i in 0..n {
+        let k = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) % (4 * n);
+        t.insert(k, i);
+        m.insert(k, i);
+    }
+    t.set_visit_hook(Some(count_visit));
+    (t, m)
+}
+
+#[test]
+fn reverse_ranges_start_at_the_end_bound() {
+    let (t, m) = tree(200_000, true);
+    let height = t.stats().height;
+    // One descent per bound, and one more to the last leaf when the end
+    // bound is past every key. Leaves are at least half full, so k entries
+    // span at most k / 8 + 1 of them.
+    let budget = |k: usize| 3 * height + k / 8 + 2;
+
+    let got = visits(|| {
+        let tail: Vec<_> = t.range(..).rev().take(100).collect();
+        assert!(tail.into_iter().eq(m.iter().rev().take(100)));
+    });
+    assert!(got <= budget(100), "{} nodes for the last 100", got);
+
+    for a in [0, 1, 123_457, 400_000, 799_000] {
+        let b = a + 2_000;
+        let want = m.range(a..b).count();
+        let got = visits(|| {
+            assert!(t.range(a..b).rev().eq(m.range(a..b).rev()));
+        });
+        assert!(got <= budget(want), "{}..{}: {} nodes", a, b, got);
+        let got = visits(|| {
+            assert!(t
+                .range(a..=b)
+                .rev()
+                .take(3)
+                .eq(m.range(a..=b).rev().take(3)));
+        });
+        assert!(got <= budget(3), "{}..={}: {} nodes", a, b, got);
+    }
+
+    let got = visits(|| assert_eq!(t.last(), m.iter().next_back()));
+    assert!(got <= height, "last: {} nodes", got);
+    let got = visits(|| assert_eq!(t.first(), m.iter().next()));
+    assert!(got <= height, "first: {} nodes", got);
+}
+
+#[test]
+fn reverse_ranges_without_back_links() {
+    let (t, m) = tree(50_000, false);
+    let height = t.stats().height;
+    // Each step back to another leaf is a descent from the root.
+    let got = visits(|| {
+        assert!(t.range(..).rev().take(100).eq(m.iter().rev().take(100)));
+    });
+    assert!(
+        got <= (100 / 8 + 3) * height,
+        "{} nodes for the last 100",
+        got
+    );
+    assert!(t.range(1000..9000).rev().eq(m.range(1000..9000).rev()));
+}
@@ -0,0 +1,114 @@
+use bplustree::{BPlusTreeMap, SeparatorKey, SeparatorOrd};
+use std::ops::Bound;
+
+/// A key that deliberately does not implement `Clone`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Token(u64);
+
+impl SeparatorKey for Token {
+    fn separator(_left: &Self, right: &Self) -> Self {
+        Token(right.0)
+    }
+}
+
+#[test]
+fn non_clone_keys_insert_remove_and_retain() {
+    let mut t = BPlusTreeMap::with_comparator(4, SeparatorOrd).unwrap();
+    for i in 0..500u64 {
+        t.insert(Token(i * 7 % 500), i);
+    }
+    t.check_invariants_detailed().unwrap();
+    for i in (0..500u64).step_by(3) {
+        assert!(t.remove(&Token(i)).is_some());
+    }
+    t.check_invariants_detailed().unwrap();
+    t.retain(|k, _| !k.0.is_multiple_of(5));
+    t.check_invariants_detailed().unwrap();
+
+    let expected: Vec<u64> = (0..500u64)
+        .filter(|i| !i.is_multiple_of(3) && !i.is_multiple_of(5))
+        .collect();
+    assert!(t.keys().map(|k| k.0).eq(expected.iter().copied()));
+    let window: Vec<u64> = t.range(Token(100)..Token(200)).map(|(k, _)| k.0).collect();
+    assert!(window.iter().copied().eq(expected
+        .iter()
+        .copied()
+        .filter(|&i| (100..200).contains(&i))));
+}
+
+#[test]
+fn string_separators_are_shortest_prefixes() {
+    let left = String::from("applesauce");
+    let right = String::from("applet");
+    assert_eq!(String::separator(&left, &right), "applet");
+    assert_eq!(String::separator(&"b".into(), &"cherry".into()), "c");
+    assert_eq!(
+        Vec::<u8>::separator(&b"abc".to_vec(), &b"abd-long-tail".to_vec()),
+        b"abd"
+    );
+    // A cut inside a multi-byte char extends to the next boundary.
+    assert_eq!(String::separator(&"aé".into(), &"aêx".into()), "aê");
+
+    let mut t = BPlusTreeMap::with_comparator(4, SeparatorOrd).unwrap();
+    for i in 0..2000u32 {
+        t.insert(
+            format!("user/{:08}/profile", i.wrapping_mul(2654435761) % 100_000),
+            i,
+        );
+    }
+    t.check_invariants_detailed().unwrap();
+    for i in (0..2000u32).step_by(2) {
+        t.remove(&format!(
+            "user/{:08}/profile",
+            i.wrapping_mul(2654435761) % 100_000
+        ));
+    }
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), 1000);
+}
+
+#[test]
+fn range_rev_and_mixed_ends() {
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    for i in 0..200u64 {
+        t.insert(i * 2, i);
+    }
+    let fwd: Vec<_> = t.range(31..=120).collect();
+    let rev: Vec<_> = t.range(31..=120).rev().collect();
+    assert!(rev.into_iter().eq(fwd.into_iter().rev()));
+    assert_eq!(t.range(..).next_back(), Some((&398, &199)));
+    assert_eq!(t.range(500..).next_back(), None);
+    assert_eq!(t.range(50..50).next_back(), None);
+
+    let mut r = t.range(10..61);
+    let mut seen = Vec::new();
+    loop {
+        match (r.next(), r.next_back()) {
+            (Some(a), Some(b)) => seen.extend([*a.0, *b.0]),
+            (Some(a), None) | (None, Some(a)) => seen.push(*a.0),
+            (None, None) => break,
+        }
+    }
+    seen.sort_unstable();
+    assert_eq!(seen, (5..=30).map(|i| i * 2).collect::<Vec<_>>());
+}
+
+/// Reads, iteration and validation only need `K: Ord`, not `K: Clone`.
+fn summarize<K: Ord>(t: &BPlusTreeMap<K, u64>, lo: &K, hi: &K) -> (Option<u64>, u64, usize) {
+    t.check_invariants_detailed().unwrap();
+    let window = t.range((Bound::Included(lo), Bound::Excluded(hi)));
+    (
+        t.get(lo).copied(),
+        window.map(|(_, v)| v).sum(),
+        t.items().count(),
+    )
+}
+
+#[test]
+fn read_paths_do_not_need_clone() {
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    for i in 0..100u64 {
+        t.insert(i, i);
+    }
+    assert_eq!(summarize(&t, &10, &20), (Some(10), (10..20).sum(), 100));
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "serde")]
+
+use bincode::Options;
+use bplustree::{BPlusTreeMap, BPlusTreeMapSeed};
+use serde::de::DeserializeSeed;
+
+fn sample(n: u32) -> BPlusTreeMap<u32, String> {
+    let mut t = BPlusTreeMap::new(8).unwrap();
+    for i in (0..n).rev() {
+        t.insert(i * 5, format!("value-{}", i));
+    }
+    t
+}
+
+#[test]
+fn json_roundtrip_in_key_order() {
+    let t = sample(300);
+    let json = serde_json::to_string(&t).unwrap();
+    assert!(json.starts_with("{\"0\":\"value-0\",\"5\":\"value-1\""));
+
+    let back: BPlusTreeMap<u32, String> = serde_json::from_str(&json).unwrap();
+    back.check_invariants_detailed().unwrap();
+    assert!(back.items().eq(t.items()));
+    assert_eq!(
+        back.leaf_layout().cap as usize,
+        BPlusTreeMapSeed::<u32, String>::DEFAULT_CAPACITY
+    );
+}
+
+#[test]
+fn bincode_roundtrip_with_seed_capacity() {
+    let t = sample(1000);
+    let bytes = bincode::serialize(&t).unwrap();
+    let seed = BPlusTreeMapSeed::<u32, String>::new(6);
+    let back = seed
+        .deserialize(&mut bincode::Deserializer::from_slice(
+            &bytes,
+            bincode::DefaultOptions::new()
+                .with_fixint_encoding()
+                .allow_trailing_bytes(),
+        ))
+        .unwrap();
+    back.check_invariants_detailed().unwrap();
+    assert_eq!(back.leaf_layout().cap, 6);
+    assert!(back.items().eq(t.items()));
+}
+
+#[test]
+fn unsorted_input_falls_back_to_inserts() {
+    let json = r#"{"5":1,"9":2,"12":3,"3":4,"7":5,"9":6,"1":7,"40":8}"#;
+    let seed = BPlusTreeMapSeed::<u32, u32>::new(4);
+    let t = seed
+        .deserialize(&mut serde_json::Deserializer::from_str(json))
+        .unwrap();
+    t.check_invariants_detailed().unwrap();
+    let items: Vec<(u32, u32)> = t.items().map(|(k, v)| (*k, *v)).collect();
+    assert_eq!(
+        items,
+        vec![(1, 7), (3, 4), (5, 1), (7, 5), (9, 6), (12, 3), (40, 8)]
+    );
+}
+
+#[test]
+fn empty_and_invalid_capacity() {
+    let t: BPlusTreeMap<u32, u32> = serde_json::from_str("{}").unwrap();
+    assert!(t.is_empty());
+    t.check_invariants_detailed().unwrap();
+
+    let seed = BPlusTreeMapSeed::<u32, u32>::new(2);
+    let err = seed.deserialize(&mut serde_json::Deserializer::from_str("{}"));
+    assert!(err.is_err());
+}
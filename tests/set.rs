@@ -0,0 +1,166 @@
+use bplustree::{BPlusTreeMap, BPlusTreeSet, Comparator, DeriveSeparator, TreeConfig};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+fn both(keys: impl IntoIterator<Item = u64>) -> (BPlusTreeSet<u64>, BTreeSet<u64>) {
+    let mut t = BPlusTreeSet::new(8).unwrap();
+    let mut s = BTreeSet::new();
+    for k in keys {
+        assert_eq!(t.insert(k), s.insert(k));
+    }
+    t.check_invariants_detailed().unwrap();
+    (t, s)
+}
+
+fn check_algebra(a: &[u64], b: &[u64]) {
+    let (ta, sa) = both(a.iter().copied());
+    let (tb, sb) = both(b.iter().copied());
+    let ctx = format!("{} vs {} keys", sa.len(), sb.len());
+    assert!(ta.union(&tb).eq(sa.union(&sb)), "union {}", ctx);
+    assert!(
+        ta.intersection(&tb).eq(sa.intersection(&sb)),
+        "intersection {}",
+        ctx
+    );
+    assert!(
+        ta.difference(&tb).eq(sa.difference(&sb)),
+        "difference {}",
+        ctx
+    );
+    assert!(
+        tb.difference(&ta).eq(sb.difference(&sa)),
+        "difference {}",
+        ctx
+    );
+    assert!(
+        ta.symmetric_difference(&tb)
+            .eq(sa.symmetric_difference(&sb)),
+        "symmetric difference {}",
+        ctx
+    );
+    assert_eq!(ta.is_subset(&tb), sa.is_subset(&sb), "{}", ctx);
+    assert_eq!(tb.is_subset(&ta), sb.is_subset(&sa), "{}", ctx);
+    assert_eq!(ta.is_superset(&tb), sa.is_superset(&sb), "{}", ctx);
+    assert_eq!(ta.is_disjoint(&tb), sa.is_disjoint(&sb), "{}", ctx);
+}
+
+fn spread(n: u64, mul: u64, modulo: u64) -> Vec<u64> {
+    (0..n).map(|i| i.wrapping_mul(mul) % modulo).collect()
+}
+
+#[test]
+fn algebra_matches_btreeset() {
+    let evens: Vec<u64> = (0..2000).map(|i| 2 * i).collect();
+    let odds: Vec<u64> = (0..2000).map(|i| 2 * i + 1).collect();
+    let cases: Vec<(Vec<u64>, Vec<u64>)> = vec![
+        (vec![], vec![]),
+        (vec![], evens.clone()),
+        (evens.clone(), odds.clone()),
+        (evens.clone(), evens.clone()),
+        (evens.clone(), (0..4000).collect()),
+        (vec![7, 1000, 3999], evens.clone()),
+        (vec![8, 1000, 3998], evens.clone()),
+        (spread(30, 7919, 100_000), spread(20_000, 104_729, 100_000)),
+        (spread(3000, 7919, 9000), spread(3000, 104_729, 9000)),
+        ((0..500).collect(), (500..1000).collect()),
+        ((0..600).collect(), (500..1000).collect()),
+    ];
+    for (a, b) in &cases {
+        check_algebra(a, b);
+        check_algebra(b, a);
+    }
+}
+
+#[test]
+fn basic_operations_and_range() {
+    let (mut t, mut s) = both(spread(3000, 7919, 5000));
+    for i in 0..3000u64 {
+        let k = i.wrapping_mul(104_729) % 5000;
+        if i.is_multiple_of(2) {
+            assert_eq!(t.remove(&k), s.remove(&k));
+        }
+        assert_eq!(t.contains(&(k + 1)), s.contains(&(k + 1)));
+    }
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), s.len());
+    assert!(t.iter().eq(s.iter()));
+    assert!(t.iter().rev().eq(s.iter().rev()));
+    assert!(t.range(1000..2000).eq(s.range(1000..2000)));
+    assert!(t.range(..=77).rev().eq(s.range(..=77).rev()));
+    assert_eq!(t.first(), s.first());
+    assert_eq!(t.last(), s.last());
+    assert!((&t).into_iter().eq(&s));
+    t.extend([1, 2, 3]);
+    s.extend([1, 2, 3]);
+    assert!(t.iter().eq(s.iter()));
+    assert_eq!(format!("{:?}", t), format!("{:?}", s));
+    t.clear();
+    assert!(t.is_empty());
+    assert_eq!(t.first(), None);
+}
+
+#[test]
+fn leaves_have_no_value_array() {
+    let set = TreeConfig::new()
+        .cache_lines(4, 4)
+        .build_set::<u64>()
+        .unwrap();
+    let map = TreeConfig::new()
+        .cache_lines(4, 4)
+        .build::<u64, u64>()
+        .unwrap();
+    let (set_cap, map_cap) = (set.as_map().leaf_layout().cap, map.leaf_layout().cap);
+    assert!(set_cap >= 2 * map_cap, "{} vs {}", set_cap, map_cap);
+    assert_eq!(format!("{:?}", set), "{}");
+}
+
+/// `Ord` order, counting comparisons.
+#[derive(Clone, Default)]
+struct Counting(Rc<Cell<usize>>);
+
+impl Comparator<u64> for Counting {
+    fn compare(&self, a: &u64, b: &u64) -> Ordering {
+        self.0.set(self.0.get() + 1);
+        a.cmp(b)
+    }
+}
+
+impl DeriveSeparator<u64> for Counting {
+    fn separator(&self, _left: &u64, right: &u64) -> u64 {
+        *right
+    }
+}
+
+#[test]
+fn small_sets_skip_through_large_ones() {
+    let counter = Counting::default();
+    let mut big = BPlusTreeSet::with_comparator(16, counter.clone()).unwrap();
+    let mut small = BPlusTreeSet::with_comparator(16, counter.clone()).unwrap();
+    big.extend(0..200_000u64);
+    small.extend((0..20u64).map(|i| i * 9_999 + 5));
+
+    for (name, run) in [
+        (
+            "intersection",
+            &(|| small.intersection(&big).count()) as &dyn Fn() -> usize,
+        ),
+        ("intersection", &|| big.intersection(&small).count()),
+        ("difference", &|| small.difference(&big).count()),
+        ("is_subset", &|| small.is_subset(&big) as usize),
+        ("is_disjoint", &|| small.is_disjoint(&big) as usize),
+    ] {
+        counter.0.set(0);
+        run();
+        // A walk through the big set would take 200k comparisons; 20
+        // descents take a few hundred.
+        assert!(counter.0.get() < 2_000, "{}: {}", name, counter.0.get());
+    }
+    assert_eq!(small.intersection(&big).count(), 20);
+    assert!(small.is_subset(&big));
+    assert!(!big.is_subset(&small));
+
+    let map: &BPlusTreeMap<u64, (), Counting> = big.as_map();
+    assert_eq!(map.len(), 200_000);
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "std")]
+
+use bplustree::{BPlusTreeError, BPlusTreeMap, NodeSize, SnapshotCodec, TreeConfig};
+
+fn build(n: u64, cap: usize) -> BPlusTreeMap<u64, String> {
+    let mut t = BPlusTreeMap::new(cap).unwrap();
+    for i in 0..n {
+        t.insert(i * 3, format!("v{}", i));
+    }
+    t
+}
+
+#[test]
+fn from_sorted_iter_builds_valid_trees() {
+    for cap in [4usize, 5, 8, 16, 64] {
+        for n in [0u64, 1, 3, 4, 5, 9, 17, 100, 1000, 4097] {
+            let t = BPlusTreeMap::from_sorted_iter(cap, (0..n).map(|i| (i, i * 2))).unwrap();
+            if let Err(e) = t.check_invariants_detailed() {
+                panic!("cap {} n {}: {}", cap, n, e);
+            }
+            assert_eq!(t.len(), n as usize);
+            assert!(t
+                .items()
+                .map(|(k, v)| (*k, *v))
+                .eq((0..n).map(|i| (i, i * 2))));
+            assert!(t.items().rev().map(|(k, _)| *k).eq((0..n).rev()));
+        }
+    }
+}
+
+#[test]
+fn from_sorted_iter_tree_stays_mutable() {
+    let mut t = BPlusTreeMap::from_sorted_iter(8, (0..500u32).map(|i| (i * 2, i))).unwrap();
+    for i in 0..500u32 {
+        t.insert(i * 2 + 1, i);
+    }
+    for i in (0..1000u32).step_by(3) {
+        assert!(t.remove(&i).is_some());
+    }
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), 1000 - 334);
+}
+
+#[test]
+fn from_sorted_iter_rejects_unsorted_input() {
+    let data = vec![
+        (1, "a".to_string()),
+        (3, "b".to_string()),
+        (2, "c".to_string()),
+    ];
+    let err = BPlusTreeMap::from_sorted_iter(4, data).err().unwrap();
+    assert_eq!(err, BPlusTreeError::DataIntegrityError(String::new()));
+
+    let dup = (0..20).chain(19..25).map(|i| (i, i));
+    assert!(BPlusTreeMap::<i32, i32>::from_sorted_iter(4, dup).is_err());
+}
+
+#[test]
+fn snapshot_roundtrip() {
+    for n in [0u64, 1, 7, 1000] {
+        let t = build(n, 8);
+        let mut buf = Vec::new();
+        t.save_to(&mut buf).unwrap();
+        let loaded: BPlusTreeMap<u64, String> = BPlusTreeMap::load_from(&buf[..]).unwrap();
+        loaded.check_invariants_detailed().unwrap();
+        assert_eq!(loaded.leaf_layout().cap, t.leaf_layout().cap);
+        assert!(loaded.items().eq(t.items()));
+    }
+}
+
+#[test]
+fn snapshot_keeps_the_node_configuration() {
+    for doubly_linked in [true, false] {
+        let mut t = TreeConfig::new()
+            .leaf_capacity(8)
+            .branch_capacity(32)
+            .doubly_linked(doubly_linked)
+            .build()
+            .unwrap();
+        for i in 0..500u64 {
+            t.insert(i, i);
+        }
+        let mut buf = Vec::new();
+        t.save_to(&mut buf).unwrap();
+        let loaded: BPlusTreeMap<u64, u64> = BPlusTreeMap::load_from(&buf[..]).unwrap();
+        loaded.check_invariants_detailed().unwrap();
+        let config = loaded.config();
+        assert_eq!(config, t.config());
+        assert_eq!(config.leaf, NodeSize::Capacity(8));
+        assert_eq!(config.branch, NodeSize::Capacity(32));
+        assert_eq!(config.doubly_linked, doubly_linked);
+        assert_eq!(loaded.branch_layout().cap, 32);
+        assert_eq!(loaded.leaf_layout().prev_off.is_some(), doubly_linked);
+        assert!(loaded.items().rev().eq(t.items().rev()));
+    }
+}
+
+#[test]
+fn word_sized_codecs_use_fixed_width() {
+    let mut out = Vec::new();
+    usize::MAX.encode(&mut out);
+    (-2isize).encode(&mut out);
+    assert_eq!(out.len(), 16);
+    assert_eq!(out[..8], (usize::MAX as u64).to_le_bytes());
+    assert_eq!(out[8..], (-2i64).to_le_bytes());
+
+    let mut input = &out[..];
+    assert_eq!(usize::decode(&mut input), Some(usize::MAX));
+    assert_eq!(isize::decode(&mut input), Some(-2));
+    assert!(input.is_empty());
+
+    let mut t = BPlusTreeMap::new(8).unwrap();
+    for i in 0..300usize {
+        t.insert(i, -(i as isize));
+    }
+    let mut buf = Vec::new();
+    t.save_to(&mut buf).unwrap();
+    let loaded: BPlusTreeMap<usize, isize> = BPlusTreeMap::load_from(&buf[..]).unwrap();
+    assert!(loaded.items().eq(t.items()));
+}
+
+#[test]
+fn snapshot_through_file() {
+    let t = build(3000, 32);
+    let mut path = std::env::temp_dir();
+    path.push(format!("bplustree_snapshot_{}.bin", std::process::id()));
+    {
+        let mut f = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+        t.save_to(&mut f).unwrap();
+    }
+    let f = std::io::BufReader::new(std::fs::File::open(&path).unwrap());
+    let loaded: BPlusTreeMap<u64, String> = BPlusTreeMap::load_from(f).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    loaded.check_invariants_detailed().unwrap();
+    assert_eq!(loaded.get(&2997), Some(&"v999".to_string()));
+    assert_eq!(loaded.len(), 3000);
+}
+
+#[test]
+fn snapshot_detects_corruption() {
+    let t = build(200, 8);
+    let mut buf = Vec::new();
+    t.save_to(&mut buf).unwrap();
+
+    for pos in [3usize, 20, 40, 100, buf.len() / 2, buf.len() - 6] {
+        let mut bad = buf.clone();
+        bad[pos] ^= 0x10;
+        let res = BPlusTreeMap::<u64, String>::load_from(&bad[..]);
+        assert!(
+            matches!(res, Err(BPlusTreeError::CorruptedTree(_))),
+            "flip at {}",
+            pos
+        );
+    }
+
+    for len in [10, buf.len() / 3, buf.len() - 1] {
+        let res = BPlusTreeMap::<u64, String>::load_from(&buf[..len]);
+        assert!(
+            matches!(res, Err(BPlusTreeError::CorruptedTree(_))),
+            "truncated to {}",
+            len
+        );
+    }
+
+    let res = BPlusTreeMap::<u32, String>::load_from(&buf[..]);
+    assert!(matches!(res, Err(BPlusTreeError::CorruptedTree(_))));
+    let res = BPlusTreeMap::<u64, Vec<u8>>::load_from(&buf[..]);
+    assert!(matches!(res, Err(BPlusTreeError::CorruptedTree(_))));
+}
@@ -0,0 +1,155 @@
+use bplustree::{BPlusTreeMap, MergeThreshold, SplitPolicy, TreeConfig};
+use std::collections::BTreeMap;
+
+fn loaded(policy: SplitPolicy, keys: impl Iterator<Item = u64>) -> BPlusTreeMap<u64, u64> {
+    let mut t = BPlusTreeMap::new(16).unwrap();
+    t.set_split_policy(policy);
+    assert_eq!(t.split_policy(), policy);
+    for k in keys {
+        t.insert(k, k);
+    }
+    t.check_invariants_detailed().unwrap();
+    t
+}
+
+fn shuffled(n: u64) -> impl Iterator<Item = u64> {
+    (0..n).map(move |i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) % (4 * n))
+}
+
+#[test]
+fn sequential_loads_fill_nodes() {
+    let even = loaded(SplitPolicy::Even, 0..20_000).stats();
+    assert!(even.leaf_fill() < 0.6, "{:?}", even);
+    assert_eq!(even.len, 20_000);
+
+    let right = loaded(SplitPolicy::RightMost, 0..20_000).stats();
+    assert!(right.leaf_fill() > 0.99, "{:?}", right);
+    assert!(right.branch_fill() > 0.9, "{:?}", right);
+    assert!(right.leaves * 2 < even.leaves + 2);
+    assert!(right.height <= even.height);
+
+    let ascending = loaded(SplitPolicy::Adaptive, 0..20_000).stats();
+    assert!(ascending.leaf_fill() > 0.99, "{:?}", ascending);
+    let descending = loaded(SplitPolicy::Adaptive, (0..20_000).rev()).stats();
+    assert!(descending.leaf_fill() > 0.99, "{:?}", descending);
+    // RightMost does not skew at the left edge.
+    let right_desc = loaded(SplitPolicy::RightMost, (0..20_000).rev()).stats();
+    assert!(right_desc.leaf_fill() < 0.6, "{:?}", right_desc);
+}
+
+#[test]
+fn adaptive_splits_random_loads_evenly() {
+    let even = loaded(SplitPolicy::Even, shuffled(20_000)).stats();
+    let adaptive = loaded(SplitPolicy::Adaptive, shuffled(20_000)).stats();
+    assert_eq!(adaptive.leaves, even.leaves);
+    assert!(adaptive.leaf_fill() < 0.8, "{:?}", adaptive);
+}
+
+#[test]
+fn skewed_trees_stay_valid_under_mixed_operations() {
+    for policy in [SplitPolicy::RightMost, SplitPolicy::Adaptive] {
+        let mut t = loaded(policy, 0..3000);
+        let mut m: BTreeMap<u64, u64> = (0..3000).map(|k| (k, k)).collect();
+        let mut x = 0x2545_f491_4f6c_dd1du64;
+        for step in 0..6000u64 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            let k = match step % 3 {
+                0 => 3000 + step,
+                _ => x % 9000,
+            };
+            if x.is_multiple_of(4) {
+                assert_eq!(t.remove(&k), m.remove(&k));
+            } else {
+                assert_eq!(t.insert(k, step), m.insert(k, step));
+            }
+            if step.is_multiple_of(500) {
+                t.check_invariants_detailed().unwrap();
+            }
+        }
+        t.check_invariants_detailed().unwrap();
+        assert!(t.items().eq(m.iter()));
+        assert_eq!(t.stats().len, m.len());
+
+        for k in m.keys() {
+            t.remove(k);
+        }
+        assert!(t.is_empty());
+        t.check_invariants_detailed().unwrap();
+    }
+}
+
+#[test]
+fn skewed_edges_settle_as_they_fill() {
+    let thresholds = [
+        MergeThreshold::Half,
+        MergeThreshold::Percent(25),
+        MergeThreshold::Empty,
+    ];
+    for doubly_linked in [true, false] {
+        for threshold in thresholds {
+            let mut t = TreeConfig::new()
+                .capacity(8)
+                .doubly_linked(doubly_linked)
+                .split_policy(SplitPolicy::Adaptive)
+                .merge_threshold(threshold)
+                .build()
+                .unwrap();
+            let mut m = BTreeMap::new();
+            // Descending and ascending runs skew both edges, with or without
+            // back links to spot the leftmost leaf.
+            for k in (0..2000u64).rev().chain(4000..6000) {
+                t.insert(k * 2, k);
+                m.insert(k * 2, k);
+            }
+            assert!(t.stats().leaf_fill() > 0.99, "{:?}", t.stats());
+            t.check_invariants_detailed().unwrap();
+            // Filling the edge nodes clears their flags, so later deletes
+            // that leave them short are rebalanced like any other node.
+            let mut x = 0x9e37_79b9_7f4a_7c15u64;
+            for step in 0..8000u64 {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                let k = match step % 4 {
+                    0 => step / 4,
+                    1 => 12_000 - step / 4,
+                    _ => x % 12_000,
+                };
+                if x.is_multiple_of(3) {
+                    assert_eq!(t.remove(&k), m.remove(&k));
+                } else {
+                    assert_eq!(t.insert(k, step), m.insert(k, step));
+                }
+                if step.is_multiple_of(250) {
+                    t.check_invariants_detailed().unwrap();
+                }
+            }
+            t.check_invariants_detailed().unwrap();
+            assert!(t.items().eq(m.iter()));
+        }
+    }
+}
+
+#[test]
+fn stats_of_small_trees() {
+    let t: BPlusTreeMap<u64, u64> = BPlusTreeMap::with_cache_lines(4, 4);
+    let s = t.stats();
+    assert_eq!((s.len, s.height, s.leaves, s.branches), (0, 0, 0, 0));
+    assert_eq!(s.leaf_fill(), 0.0);
+
+    let mut t = BPlusTreeMap::new(4).unwrap();
+    t.insert(1u32, ());
+    let s = t.stats();
+    assert_eq!((s.len, s.height, s.leaves, s.branches), (1, 1, 1, 0));
+    assert_eq!(s.leaf_fill(), 0.25);
+    for k in 2..=5 {
+        t.insert(k, ());
+    }
+    let s = t.stats();
+    assert_eq!(
+        (s.height, s.leaves, s.branches, s.branch_keys),
+        (2, 2, 1, 1)
+    );
+}
@@ -0,0 +1,159 @@
+use bplustree::{BPlusTreeError, BPlusTreeMap, MergeThreshold, NodeSize, SplitPolicy, TreeConfig};
+use std::collections::BTreeMap;
+
+fn invalid<K: Ord + Clone, V>(config: TreeConfig) -> bool {
+    matches!(
+        config.build::<K, V>(),
+        Err(BPlusTreeError::InvalidCapacity(_))
+    )
+}
+
+#[test]
+fn capacities_and_budgets_are_validated() {
+    assert!(invalid::<u64, u64>(TreeConfig::new().capacity(3)));
+    assert!(invalid::<u64, u64>(TreeConfig::new().branch_capacity(0)));
+    assert!(invalid::<u64, u64>(TreeConfig::new().leaf_capacity(70_000)));
+    assert!(!invalid::<u64, u64>(
+        TreeConfig::new().capacity(TreeConfig::MAX_CAPACITY)
+    ));
+    // `new` used to truncate capacities above u16::MAX.
+    assert!(matches!(
+        BPlusTreeMap::<u64, u64>::new(70_000),
+        Err(BPlusTreeError::InvalidCapacity(_))
+    ));
+
+    // 64 bytes hold the header, two links and just two u64 pairs.
+    assert!(invalid::<u64, u64>(TreeConfig::new().leaf_bytes(64)));
+    assert!(!invalid::<u64, u64>(TreeConfig::new().leaf_bytes(128)));
+    assert!(invalid::<u64, u64>(TreeConfig::new().branch_bytes(16)));
+    assert!(invalid::<u8, u8>(TreeConfig::new().leaf_bytes(1 << 20)));
+    assert!(invalid::<u8, u8>(TreeConfig::new().branch_bytes(1 << 20)));
+    assert!(!invalid::<u64, u64>(TreeConfig::new().cache_lines(4, 4)));
+}
+
+#[test]
+fn constructors_are_configurations() {
+    let t = BPlusTreeMap::<u64, u64>::new(16).unwrap();
+    let c = t.config();
+    assert_eq!(
+        (c.leaf, c.branch),
+        (NodeSize::Capacity(16), NodeSize::Capacity(16))
+    );
+    assert!(c.eager_root && c.doubly_linked);
+    assert_eq!(t.leaf_count(), 1);
+
+    let t = BPlusTreeMap::<u64, u64>::with_cache_lines(4, 2);
+    let c = t.config();
+    assert_eq!(
+        (c.leaf, c.branch),
+        (NodeSize::Bytes(256), NodeSize::Bytes(128))
+    );
+    assert!(!c.eager_root);
+    assert_eq!(t.leaf_count(), 0);
+
+    assert_eq!(TreeConfig::default(), TreeConfig::new());
+    let t = TreeConfig::new().build::<u64, u64>().unwrap();
+    assert_eq!(t.leaf_layout().cap as usize, TreeConfig::DEFAULT_CAPACITY);
+    assert_eq!(t.config(), TreeConfig::new());
+}
+
+#[test]
+fn config_round_trips_from_a_live_tree() {
+    let config = TreeConfig::new()
+        .leaf_bytes(512)
+        .branch_capacity(9)
+        .doubly_linked(false)
+        .split_policy(SplitPolicy::RightMost)
+        .merge_threshold(MergeThreshold::Percent(30))
+        .eager_root(true);
+    let mut t = config.build::<u32, u64>().unwrap();
+    assert_eq!(t.config(), config);
+    assert_eq!(t.split_policy(), SplitPolicy::RightMost);
+    assert_eq!(t.merge_threshold(), MergeThreshold::Percent(30));
+    assert_eq!(t.branch_layout().cap, 9);
+
+    let copy = t.config().build::<u32, u64>().unwrap();
+    assert_eq!(copy.leaf_layout().cap, t.leaf_layout().cap);
+    assert_eq!(copy.leaf_layout().bytes, 512);
+
+    t.set_split_policy(SplitPolicy::Even);
+    t.set_merge_threshold(MergeThreshold::Empty);
+    let live = t.config();
+    assert_eq!(live.split_policy, SplitPolicy::Even);
+    assert_eq!(live.merge_threshold, MergeThreshold::Empty);
+    assert_eq!(live.leaf, NodeSize::Bytes(512));
+}
+
+#[test]
+fn singly_linked_leaves_are_smaller() {
+    let single = TreeConfig::new().capacity(8).doubly_linked(false);
+    let single = single.build::<u64, u64>().unwrap();
+    let double = TreeConfig::new().capacity(8).build::<u64, u64>().unwrap();
+    assert!(single.leaf_layout().prev_off.is_none());
+    assert!(single.leaf_layout().bytes < double.leaf_layout().bytes);
+
+    let by_bytes = |doubly_linked| {
+        let config = TreeConfig::new()
+            .leaf_bytes(256)
+            .doubly_linked(doubly_linked);
+        config.build::<u64, u64>().unwrap().leaf_layout().cap
+    };
+    assert!(by_bytes(false) >= by_bytes(true));
+
+    // Adaptive splits still find the left edge without back links.
+    let mut t = TreeConfig::new()
+        .capacity(16)
+        .doubly_linked(false)
+        .split_policy(SplitPolicy::Adaptive)
+        .build::<u64, u64>()
+        .unwrap();
+    for k in (0..5000).rev() {
+        t.insert(k, k);
+    }
+    t.check_invariants_detailed().unwrap();
+    assert!(t.stats().leaf_fill() > 0.99, "{:?}", t.stats());
+}
+
+#[test]
+fn singly_linked_trees_match_btreemap() {
+    for policy in [SplitPolicy::Even, SplitPolicy::Adaptive] {
+        let mut t = TreeConfig::new()
+            .capacity(5)
+            .doubly_linked(false)
+            .split_policy(policy)
+            .build::<u64, u64>()
+            .unwrap();
+        let mut m = BTreeMap::new();
+        let mut x = 0x9e37_79b9_7f4a_7c15u64;
+        for step in 0..6000u64 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            let k = match step % 4 {
+                0 => 10_000 - step,
+                _ => x % 3000,
+            };
+            if x.is_multiple_of(3) {
+                assert_eq!(t.remove(&k), m.remove(&k));
+            } else {
+                assert_eq!(t.insert(k, step), m.insert(k, step));
+            }
+            if step.is_multiple_of(500) {
+                t.check_invariants_detailed().unwrap();
+                assert!(t.items().rev().eq(m.iter().rev()));
+            }
+        }
+        t.check_invariants_detailed().unwrap();
+        assert!(t.items().eq(m.iter()));
+        assert!(t.items().rev().eq(m.iter().rev()));
+        assert!(t.range(500..2500).rev().eq(m.range(500..2500).rev()));
+        assert_eq!(t.last(), m.iter().next_back());
+
+        let keys: Vec<u64> = m.keys().copied().collect();
+        for k in keys {
+            assert_eq!(t.remove(&k), m.remove(&k));
+        }
+        t.check_invariants_detailed().unwrap();
+        assert!(t.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+use bplustree::{BPlusTreeError, VarBPlusTreeMap};
+use std::collections::BTreeMap;
+
+fn value_for(i: u64) -> String {
+    // Mostly short values, with the occasional one far larger than a page.
+    let len = match i % 50 {
+        0 => 10_000,
+        1..=5 => 300,
+        _ => (i % 40) as usize,
+    };
+    format!("{:x}", i).repeat(len / 4 + 1)
+}
+
+#[test]
+fn string_map_matches_btreemap_with_overflow() {
+    let mut t: VarBPlusTreeMap<String, String> = VarBPlusTreeMap::with_budgets(1024, 512).unwrap();
+    let mut m = BTreeMap::new();
+    let mut x = 0x9e37_79b9_7f4a_7c15u64;
+    for step in 0..5000u64 {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        let k = format!("/home/user/projects/{}/src/file_{}.rs", x % 7, x % 1500);
+        if x % 4 < 3 {
+            let v = value_for(step);
+            assert_eq!(t.insert(k.clone(), v.clone()).unwrap(), m.insert(k, v));
+        } else {
+            assert_eq!(t.remove(&k), m.remove(&k));
+        }
+        if step.is_multiple_of(500) {
+            t.check_invariants_detailed().unwrap();
+        }
+    }
+    t.check_invariants_detailed().unwrap();
+    assert_eq!(t.len(), m.len());
+    assert!(t.iter().eq(m.clone().into_iter()));
+    assert!(t.overflow_count() > 0);
+    assert!(t.overflow_count() < t.len() / 5);
+
+    let lo = "/home/user/projects/2".to_string();
+    let hi = "/home/user/projects/4/src/file_9".to_string();
+    assert!(t
+        .range(lo.clone()..hi.clone())
+        .eq(m.range(lo..hi).map(|(k, v)| (k.clone(), v.clone()))));
+
+    let (k, v) = m.iter().next().unwrap();
+    assert_eq!(t.get_bytes(k), Some(v.as_bytes()));
+    t.clear();
+    assert!(t.is_empty());
+}
+
+#[test]
+fn replacing_moves_values_between_inline_and_overflow() {
+    let mut t: VarBPlusTreeMap<String, Vec<u8>> = VarBPlusTreeMap::with_cache_lines(8, 8).unwrap();
+    let key = "k".to_string();
+    assert_eq!(t.insert(key.clone(), vec![1; 8]).unwrap(), None);
+    assert_eq!(t.overflow_count(), 0);
+    assert_eq!(
+        t.insert(key.clone(), vec![2; 5000]).unwrap(),
+        Some(vec![1; 8])
+    );
+    assert_eq!(t.overflow_count(), 1);
+    assert_eq!(
+        t.insert(key.clone(), vec![3; 6000]).unwrap(),
+        Some(vec![2; 5000])
+    );
+    assert_eq!(
+        t.insert(key.clone(), Vec::new()).unwrap(),
+        Some(vec![3; 6000])
+    );
+    assert_eq!(t.overflow_count(), 0);
+    assert_eq!(t.get(&key), Some(Vec::new()));
+    assert_eq!(t.remove(&key), Some(Vec::new()));
+
+    let long_key = "x".repeat(t.max_key_len() + 1);
+    assert!(matches!(
+        t.insert(long_key, Vec::new()),
+        Err(BPlusTreeError::InvalidState(_))
+    ));
+}
+
+#[test]
+fn leaves_split_by_byte_fill() {
+    let fill = |value_len: usize| {
+        let mut t: VarBPlusTreeMap<u32, Vec<u8>> =
+            VarBPlusTreeMap::with_budgets(4096, 4096).unwrap();
+        for i in 0..2000u32 {
+            t.insert(i, vec![7; value_len]).unwrap();
+        }
+        t.check_invariants_detailed().unwrap();
+        t.leaf_count()
+    };
+    let (small, large) = (fill(4), fill(64));
+    assert!(large > 3 * small, "{} vs {} leaves", small, large);
+}
+
+#[test]
+fn signed_keys_keep_their_order() {
+    let mut t: VarBPlusTreeMap<i64, f64> = VarBPlusTreeMap::with_cache_lines(4, 4).unwrap();
+    let keys = [i64::MIN, -1_000_000, -1, 0, 1, 42, i64::MAX];
+    for &k in keys.iter().rev() {
+        t.insert(k, k as f64 / 2.0).unwrap();
+    }
+    assert!(t.iter().map(|(k, _)| k).eq(keys.iter().copied()));
+    assert!(t.range(-1..=1).map(|(k, _)| k).eq([-1, 0, 1]));
+    assert_eq!(t.get(&-1), Some(-0.5));
+}
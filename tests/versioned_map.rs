@@ -0,0 +1,159 @@
+use bplustree::{BPlusTreeError, VersionedBPlusTreeMap};
+use std::collections::BTreeMap;
+
+#[test]
+fn test_versions_are_monotonic_and_reads_are_consistent() {
+    let mut map = VersionedBPlusTreeMap::new(4).unwrap();
+    let v1 = map.insert(1, "a");
+    let v2 = map.insert(2, "b");
+    let v3 = map.insert(1, "a2");
+    let v4 = map.remove(&2).unwrap();
+    assert!(v1 < v2 && v2 < v3 && v3 < v4);
+    assert_eq!(map.current_version(), v4);
+
+    let at1 = map.read_at(v1).unwrap();
+    assert_eq!(at1.get(&1), Some(&"a"));
+    assert_eq!(at1.get(&2), None);
+
+    let at2 = map.read_at(v2).unwrap();
+    assert_eq!(at2.get(&1), Some(&"a"));
+    assert_eq!(at2.get(&2), Some(&"b"));
+
+    let at3 = map.read_at(v3).unwrap();
+    assert_eq!(at3.get(&1), Some(&"a2"));
+    assert_eq!(at3.get(&2), Some(&"b"));
+
+    let latest = map.latest();
+    assert_eq!(latest.get(&1), Some(&"a2"));
+    assert_eq!(latest.get(&2), None);
+}
+
+#[test]
+fn test_remove_of_absent_key_does_not_consume_version() {
+    let mut map: VersionedBPlusTreeMap<i32, i32> = VersionedBPlusTreeMap::new(4).unwrap();
+    assert_eq!(map.remove(&7), None);
+    assert_eq!(map.current_version(), 0);
+    map.insert(7, 70);
+    map.remove(&7).unwrap();
+    assert_eq!(map.remove(&7), None);
+    assert_eq!(map.current_version(), 2);
+}
+
+#[test]
+fn test_read_at_rejects_future_and_collected_versions() {
+    let mut map = VersionedBPlusTreeMap::new(4).unwrap();
+    for i in 0..10 {
+        map.insert(i, i);
+    }
+    assert_eq!(
+        map.read_at(11).err(),
+        Some(BPlusTreeError::InvalidState(String::new()))
+    );
+    map.gc(5);
+    assert!(map.read_at(4).is_err());
+    assert!(map.read_at(5).is_ok());
+}
+
+#[test]
+fn test_range_at_version_skips_invisible_entries() {
+    let mut map = VersionedBPlusTreeMap::new(4).unwrap();
+    for i in 0..50 {
+        map.insert(i, i * 10);
+    }
+    let snapshot = map.current_version();
+    for i in (0..50).step_by(2) {
+        map.remove(&i);
+    }
+    for i in 50..60 {
+        map.insert(i, i * 10);
+    }
+
+    let old: Vec<_> = map
+        .read_at(snapshot)
+        .unwrap()
+        .range(10..20)
+        .map(|(k, v)| (*k, *v))
+        .collect();
+    let expected: Vec<_> = (10..20).map(|i| (i, i * 10)).collect();
+    assert_eq!(old, expected);
+
+    let now: Vec<_> = map.latest().range(10..).map(|(k, _)| *k).collect();
+    let expected: Vec<_> = (10..50).filter(|i| i % 2 == 1).chain(50..60).collect();
+    assert_eq!(now, expected);
+    assert_eq!(map.read_at(snapshot).unwrap().len(), 50);
+}
+
+#[test]
+fn test_gc_prunes_history_and_removes_dead_keys() {
+    let mut map = VersionedBPlusTreeMap::new(4).unwrap();
+    for round in 0..5 {
+        for i in 0..40 {
+            map.insert(i, round);
+        }
+    }
+    for i in 0..20 {
+        map.remove(&i);
+    }
+    let horizon = map.current_version();
+    let pruned = map.gc(horizon);
+
+    // 40 keys x 5 versions + 20 tombstones; 20 live keys keep one version each.
+    assert_eq!(pruned, 40 * 5 + 20 - 20);
+    assert_eq!(map.key_count(), 20);
+    assert!(map.tree().check_invariants());
+    for i in 20..40 {
+        assert_eq!(map.latest().get(&i), Some(&4));
+        assert_eq!(map.tree().get(&i).unwrap().len(), 1);
+    }
+}
+
+#[test]
+fn test_gc_keeps_versions_visible_to_live_readers() {
+    let mut map = VersionedBPlusTreeMap::new(4).unwrap();
+    let v1 = map.insert(1, "one");
+    let _ = map.insert(2, "two");
+    let v3 = map.insert(1, "uno");
+    map.remove(&2);
+
+    map.gc(v1);
+    assert_eq!(map.read_at(v1).unwrap().get(&1), Some(&"one"));
+    map.gc(v3);
+    assert_eq!(map.read_at(v3).unwrap().get(&1), Some(&"uno"));
+    assert_eq!(map.read_at(v3).unwrap().get(&2), Some(&"two"));
+    assert_eq!(map.latest().get(&2), None);
+}
+
+#[test]
+fn test_versioned_matches_model_under_mixed_workload() {
+    let mut map = VersionedBPlusTreeMap::new(5).unwrap();
+    let mut history: Vec<BTreeMap<u32, u32>> = vec![BTreeMap::new()];
+    let mut model = BTreeMap::new();
+    let mut state: u64 = 0x2545f4914f6cdd1d;
+    for _ in 0..2000 {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        let key = ((state >> 33) % 200) as u32;
+        if (state >> 20).is_multiple_of(3) {
+            if map.remove(&key).is_some() {
+                model.remove(&key);
+                history.push(model.clone());
+            }
+        } else {
+            map.insert(key, (state >> 40) as u32);
+            model.insert(key, (state >> 40) as u32);
+            history.push(model.clone());
+        }
+        if (state >> 50).is_multiple_of(97) {
+            map.gc(map.current_version() / 2);
+        }
+    }
+
+    for version in map.oldest_live_version()..=map.current_version() {
+        let view = map.read_at(version).unwrap();
+        let got: Vec<_> = view.iter().map(|(k, v)| (*k, *v)).collect();
+        let exp: Vec<_> = history[version as usize]
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        assert_eq!(got, exp, "mismatch at version {}", version);
+    }
+}